@@ -0,0 +1,65 @@
+use axum::http::StatusCode;
+use axum::response::{IntoResponse, Response};
+use axum::Json;
+use serde_json::json;
+
+#[derive(Debug, thiserror::Error)]
+pub enum Error {
+    #[error("failed to bind the fake 1Money node: {0}")]
+    Bind(#[source] std::io::Error),
+    #[error("invalid URL of the fake 1Money node: {0}")]
+    Url(#[from] url::ParseError),
+}
+
+/// Error answered by the REST API, with the `error_code` and `message` of the 1Money API.
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+pub enum ApiError {
+    /// Unknown resource, answered with `404` and the `resource_<resource>` code.
+    #[error("{resource} not found")]
+    NotFound { resource: &'static str },
+    /// Transaction rejected by the ledger, answered with `422` and the `business_<operation>`
+    /// code.
+    #[error("{reason}")]
+    Rejected {
+        operation: &'static str,
+        reason: String,
+    },
+    /// Malformed request, answered with `400` and the `validation_<field>` code.
+    #[error("invalid {field}: {message}")]
+    Invalid {
+        field: &'static str,
+        message: String,
+    },
+}
+
+impl ApiError {
+    pub fn rejected(operation: &'static str, reason: impl Into<String>) -> Self {
+        Self::Rejected {
+            operation,
+            reason: reason.into(),
+        }
+    }
+
+    fn error_code(&self) -> String {
+        match self {
+            Self::NotFound { resource } => format!("resource_{resource}"),
+            Self::Rejected { operation, .. } => format!("business_{operation}"),
+            Self::Invalid { field, .. } => format!("validation_{field}"),
+        }
+    }
+}
+
+impl IntoResponse for ApiError {
+    fn into_response(self) -> Response {
+        let status = match self {
+            Self::NotFound { .. } => StatusCode::NOT_FOUND,
+            Self::Rejected { .. } => StatusCode::UNPROCESSABLE_ENTITY,
+            Self::Invalid { .. } => StatusCode::BAD_REQUEST,
+        };
+        let body = json!({
+            "error_code": self.error_code(),
+            "message": self.to_string(),
+        });
+        (status, Json(body)).into_response()
+    }
+}
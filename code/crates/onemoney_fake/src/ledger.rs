@@ -0,0 +1,468 @@
+//! In-memory state of the fake 1Money network.
+//!
+//! Submitted transactions are checked and applied at once, then wait in the pending list
+//! until [`Ledger::seal_checkpoint`] includes them in the next checkpoint. All hashes, roots
+//! and timestamps derive from the submitted transactions, so that the same submissions always
+//! produce the same checkpoints.
+
+use std::collections::HashMap;
+
+use alloy_primitives::{keccak256, Address, B256, U256};
+use alloy_rlp::RlpEncodable;
+use alloy_signer::k256::ecdsa::VerifyingKey;
+use alloy_signer::utils::public_key_to_address;
+use onemoney_protocol::{
+    Authority, AuthorityAction, PaymentPayload, Signature, TokenAuthorityPayload,
+    TokenBridgeAndMintPayload, TokenBurnAndBridgePayload, TokenMintPayload,
+};
+use serde::{Deserialize, Serialize};
+
+use crate::error::ApiError;
+
+/// Payload of a token issuance, which the SDK does not submit.
+#[derive(Clone, Debug, PartialEq, Eq, RlpEncodable, Serialize, Deserialize)]
+pub struct TokenIssuePayload {
+    pub chain_id: u64,
+    pub nonce: u64,
+    pub symbol: String,
+    pub name: String,
+    pub decimals: u8,
+    pub master_authority: Address,
+    pub is_private: bool,
+}
+
+/// Operation of a 1Money transaction.
+#[derive(Clone, Debug)]
+pub enum Payload {
+    TokenCreate {
+        token: Address,
+        payload: TokenIssuePayload,
+    },
+    TokenAuthority(TokenAuthorityPayload),
+    TokenMint(TokenMintPayload),
+    TokenTransfer(PaymentPayload),
+    TokenBridgeAndMint(TokenBridgeAndMintPayload),
+    TokenBurnAndBridge(TokenBurnAndBridgePayload),
+}
+
+/// Transaction whose signature has been recovered, before it is checked by the ledger.
+#[derive(Clone, Debug)]
+pub struct Submission {
+    pub hash: B256,
+    pub chain_id: u64,
+    pub from: Address,
+    pub nonce: u64,
+    pub signature: Signature,
+    pub payload: Payload,
+}
+
+/// Transaction accepted by the ledger.
+#[derive(Clone, Debug)]
+pub struct Transaction {
+    pub hash: B256,
+    pub chain_id: u64,
+    pub from: Address,
+    pub nonce: u64,
+    pub signature: Signature,
+    pub payload: Payload,
+    /// Burn and bridge nonce of the sender after a `BurnAndBridge`.
+    pub bbnonce: Option<u64>,
+    /// Position in its checkpoint, once sealed.
+    pub location: Option<Location>,
+}
+
+/// Position of a transaction in a sealed checkpoint.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Location {
+    pub checkpoint_hash: B256,
+    pub checkpoint_number: u64,
+    pub index: u64,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Checkpoint {
+    pub number: u64,
+    pub hash: B256,
+    pub parent_hash: B256,
+    pub state_root: B256,
+    pub transactions_root: B256,
+    pub receipts_root: B256,
+    pub timestamp: u64,
+    pub transactions: Vec<B256>,
+}
+
+/// Validator set of a governance epoch.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Epoch {
+    pub epoch_id: u64,
+    pub validators: Vec<VerifyingKey>,
+}
+
+#[derive(Clone, Debug, Default)]
+struct Account {
+    nonce: u64,
+    bbnonce: u64,
+    balances: HashMap<Address, U256>,
+}
+
+#[derive(Clone, Debug)]
+struct Token {
+    symbol: String,
+    master_authority: Address,
+    grants: Vec<Grant>,
+}
+
+#[derive(Clone, Debug)]
+struct Grant {
+    authority: Authority,
+    address: Address,
+    allowance: U256,
+}
+
+#[derive(Debug)]
+pub struct Ledger {
+    chain_id: u64,
+    operator: VerifyingKey,
+    genesis_timestamp: u64,
+    accounts: HashMap<Address, Account>,
+    tokens: HashMap<Address, Token>,
+    transactions: HashMap<B256, Transaction>,
+    pending: Vec<B256>,
+    checkpoints: Vec<Checkpoint>,
+    epochs: Vec<Epoch>,
+}
+
+impl Ledger {
+    /// Creates an empty ledger whose first epoch is validated by `validators`.
+    pub fn new(
+        chain_id: u64,
+        operator: VerifyingKey,
+        validators: Vec<VerifyingKey>,
+        genesis_timestamp: u64,
+    ) -> Self {
+        Self {
+            chain_id,
+            operator,
+            genesis_timestamp,
+            accounts: HashMap::new(),
+            tokens: HashMap::new(),
+            transactions: HashMap::new(),
+            pending: Vec::new(),
+            checkpoints: Vec::new(),
+            epochs: vec![Epoch {
+                epoch_id: 0,
+                validators,
+            }],
+        }
+    }
+
+    pub const fn chain_id(&self) -> u64 {
+        self.chain_id
+    }
+
+    pub const fn operator(&self) -> &VerifyingKey {
+        &self.operator
+    }
+
+    pub fn operator_address(&self) -> Address {
+        public_key_to_address(&self.operator)
+    }
+
+    /// Returns the next nonce of `address`.
+    pub fn nonce(&self, address: Address) -> u64 {
+        self.accounts
+            .get(&address)
+            .map_or(0, |account| account.nonce)
+    }
+
+    /// Returns the number of `BurnAndBridge` transactions sent by `address`.
+    pub fn bbnonce(&self, address: Address) -> u64 {
+        self.accounts
+            .get(&address)
+            .map_or(0, |account| account.bbnonce)
+    }
+
+    /// Returns the balance of `owner` in `token`, if it ever held the token.
+    pub fn balance(&self, owner: Address, token: Address) -> Option<U256> {
+        self.accounts
+            .get(&owner)
+            .and_then(|account| account.balances.get(&token))
+            .copied()
+    }
+
+    pub fn transaction(&self, hash: &B256) -> Option<&Transaction> {
+        self.transactions.get(hash)
+    }
+
+    pub fn checkpoint(&self, number: u64) -> Option<&Checkpoint> {
+        self.checkpoints.get(usize::try_from(number).ok()?)
+    }
+
+    pub fn latest_checkpoint(&self) -> Option<&Checkpoint> {
+        self.checkpoints.last()
+    }
+
+    pub fn epoch(&self, epoch_id: u64) -> Option<&Epoch> {
+        self.epochs.get(usize::try_from(epoch_id).ok()?)
+    }
+
+    pub fn latest_epoch(&self) -> &Epoch {
+        self.epochs.last().expect("the ledger starts with an epoch")
+    }
+
+    /// Starts a new epoch validated by `validators` and returns its id.
+    pub fn rotate_validators(&mut self, validators: Vec<VerifyingKey>) -> u64 {
+        let epoch_id = self.latest_epoch().epoch_id + 1;
+        self.epochs.push(Epoch {
+            epoch_id,
+            validators,
+        });
+        epoch_id
+    }
+
+    /// Checks `submission` against the state, applies it and adds it to the pending
+    /// transactions.
+    pub fn submit(&mut self, submission: Submission) -> Result<Transaction, ApiError> {
+        if self.transactions.contains_key(&submission.hash) {
+            return Err(ApiError::rejected(
+                "transaction_exists",
+                "transaction already exists",
+            ));
+        }
+        if submission.chain_id != self.chain_id {
+            return Err(ApiError::rejected(
+                "invalid_chain_id",
+                format!(
+                    "invalid chain id {}, expected {}",
+                    submission.chain_id, self.chain_id
+                ),
+            ));
+        }
+        let expected = self.nonce(submission.from);
+        if submission.nonce != expected {
+            return Err(ApiError::rejected(
+                "invalid_nonce",
+                format!("invalid nonce {}, expected {expected}", submission.nonce),
+            ));
+        }
+
+        let bbnonce = self.execute(submission.from, &submission.payload)?;
+        self.accounts.entry(submission.from).or_default().nonce += 1;
+
+        let transaction = Transaction {
+            hash: submission.hash,
+            chain_id: submission.chain_id,
+            from: submission.from,
+            nonce: submission.nonce,
+            signature: submission.signature,
+            payload: submission.payload,
+            bbnonce,
+            location: None,
+        };
+        self.transactions
+            .insert(transaction.hash, transaction.clone());
+        self.pending.push(transaction.hash);
+        Ok(transaction)
+    }
+
+    /// Seals the pending transactions in the next checkpoint, if there are any.
+    pub fn seal_checkpoint(&mut self) -> Option<&Checkpoint> {
+        if self.pending.is_empty() {
+            return None;
+        }
+
+        let number = self.checkpoints.len() as u64;
+        let parent_hash = self
+            .latest_checkpoint()
+            .map_or(B256::ZERO, |parent| parent.hash);
+        let transactions = core::mem::take(&mut self.pending);
+        let transactions_root = keccak256(
+            transactions
+                .iter()
+                .flat_map(|hash| hash.0)
+                .collect::<Vec<_>>(),
+        );
+        let receipts_root = keccak256([transactions_root.as_slice(), b"receipts"].concat());
+        let state_root = keccak256([parent_hash.as_slice(), transactions_root.as_slice()].concat());
+        let timestamp = self.genesis_timestamp + number;
+        let hash = keccak256(
+            [
+                &number.to_be_bytes()[..],
+                parent_hash.as_slice(),
+                state_root.as_slice(),
+                transactions_root.as_slice(),
+                receipts_root.as_slice(),
+                &timestamp.to_be_bytes()[..],
+            ]
+            .concat(),
+        );
+
+        for (index, tx_hash) in transactions.iter().enumerate() {
+            if let Some(transaction) = self.transactions.get_mut(tx_hash) {
+                transaction.location = Some(Location {
+                    checkpoint_hash: hash,
+                    checkpoint_number: number,
+                    index: index as u64,
+                });
+            }
+        }
+        self.checkpoints.push(Checkpoint {
+            number,
+            hash,
+            parent_hash,
+            state_root,
+            transactions_root,
+            receipts_root,
+            timestamp,
+            transactions,
+        });
+        self.checkpoints.last()
+    }
+
+    /// Applies `payload` sent by `from`, returning the new bbnonce of a `BurnAndBridge`.
+    ///
+    /// Every check happens before the first change, so that a rejected transaction leaves the
+    /// state untouched.
+    fn execute(&mut self, from: Address, payload: &Payload) -> Result<Option<u64>, ApiError> {
+        match payload {
+            Payload::TokenCreate { token, payload } => {
+                if from != self.operator_address() {
+                    return Err(ApiError::rejected(
+                        "unauthorized",
+                        "only the operator issues tokens",
+                    ));
+                }
+                if self
+                    .tokens
+                    .values()
+                    .any(|existing| existing.symbol == payload.symbol)
+                {
+                    return Err(ApiError::rejected(
+                        "token_exists",
+                        format!("token {} already exists", payload.symbol),
+                    ));
+                }
+                self.tokens.insert(
+                    *token,
+                    Token {
+                        symbol: payload.symbol.clone(),
+                        master_authority: payload.master_authority,
+                        grants: Vec::new(),
+                    },
+                );
+                Ok(None)
+            }
+            Payload::TokenAuthority(payload) => {
+                let token = self.token_mut(payload.token)?;
+                if token.master_authority != from {
+                    return Err(ApiError::rejected(
+                        "unauthorized",
+                        "only the master authority grants authorities",
+                    ));
+                }
+                token.grants.retain(|grant| {
+                    grant.authority != payload.authority_type
+                        || grant.address != payload.authority_address
+                });
+                if matches!(payload.action, AuthorityAction::Grant) {
+                    token.grants.push(Grant {
+                        authority: payload.authority_type,
+                        address: payload.authority_address,
+                        allowance: payload.value,
+                    });
+                }
+                Ok(None)
+            }
+            Payload::TokenMint(payload) => {
+                let token = self.token_mut(payload.token)?;
+                if token.master_authority != from {
+                    spend_allowance(token, from, &Authority::MintBurnTokens, payload.value)?;
+                }
+                self.credit(payload.recipient, payload.token, payload.value);
+                Ok(None)
+            }
+            Payload::TokenBridgeAndMint(payload) => {
+                let token = self.token_mut(payload.token)?;
+                spend_allowance(token, from, &Authority::Bridge, payload.value)?;
+                self.credit(payload.recipient, payload.token, payload.value);
+                Ok(None)
+            }
+            Payload::TokenTransfer(payload) => {
+                self.token_mut(payload.token)?;
+                self.debit(from, payload.token, payload.value)?;
+                self.credit(payload.recipient, payload.token, payload.value);
+                Ok(None)
+            }
+            Payload::TokenBurnAndBridge(payload) => {
+                self.token_mut(payload.token)?;
+                let burnt = payload
+                    .value
+                    .checked_add(payload.escrow_fee)
+                    .ok_or_else(|| ApiError::rejected("invalid_value", "value overflows"))?;
+                self.debit(from, payload.token, burnt)?;
+                let account = self.accounts.entry(from).or_default();
+                account.bbnonce += 1;
+                Ok(Some(account.bbnonce))
+            }
+        }
+    }
+
+    fn token_mut(&mut self, token: Address) -> Result<&mut Token, ApiError> {
+        self.tokens
+            .get_mut(&token)
+            .ok_or(ApiError::NotFound { resource: "token" })
+    }
+
+    fn credit(&mut self, owner: Address, token: Address, value: U256) {
+        let balance = self
+            .accounts
+            .entry(owner)
+            .or_default()
+            .balances
+            .entry(token)
+            .or_default();
+        *balance = balance.saturating_add(value);
+    }
+
+    fn debit(&mut self, owner: Address, token: Address, value: U256) -> Result<(), ApiError> {
+        let balance = self.balance(owner, token).unwrap_or_default();
+        let remaining = balance.checked_sub(value).ok_or_else(|| {
+            ApiError::rejected(
+                "insufficient_balance",
+                format!("balance {balance} is lower than {value}"),
+            )
+        })?;
+        self.accounts
+            .entry(owner)
+            .or_default()
+            .balances
+            .insert(token, remaining);
+        Ok(())
+    }
+}
+
+/// Spends `value` of the allowance granted to `address` for `authority` on `token`.
+fn spend_allowance(
+    token: &mut Token,
+    address: Address,
+    authority: &Authority,
+    value: U256,
+) -> Result<(), ApiError> {
+    let grant = token
+        .grants
+        .iter_mut()
+        .find(|grant| grant.address == address && grant.authority == *authority)
+        .ok_or_else(|| {
+            ApiError::rejected(
+                "unauthorized",
+                format!("{address} lacks the {authority:?} authority"),
+            )
+        })?;
+    grant.allowance = grant.allowance.checked_sub(value).ok_or_else(|| {
+        ApiError::rejected(
+            "insufficient_allowance",
+            format!("allowance {} is lower than {value}", grant.allowance),
+        )
+    })?;
+    Ok(())
+}
@@ -0,0 +1,151 @@
+//! In-process fake of a 1Money node, running the end-to-end tests of the relayer against
+//! Anvil only.
+//!
+//! [`FakeOneMoney`] serves on a local port the REST endpoints and the `CERTIFIED_TRANSACTIONS`
+//! websocket used by the relayer and its tests. Submitted transactions are checked against an
+//! in-memory [`Ledger`] and applied at once, then sealed in a checkpoint every
+//! [`FakeConfig::checkpoint_interval`] or on [`FakeOneMoney::seal_checkpoint`]. Sealing makes
+//! them queryable by hash and certifies them to the websocket subscribers.
+
+use core::net::Ipv4Addr;
+use core::time::Duration;
+use std::sync::{Arc, MutexGuard};
+
+use alloy_signer::k256::ecdsa::VerifyingKey;
+use tokio::net::TcpListener;
+use tokio::task::JoinHandle;
+use tracing::{info, warn};
+use url::Url;
+
+pub mod error;
+pub mod ledger;
+pub mod server;
+pub mod view;
+
+use error::Error;
+use ledger::Ledger;
+use server::Node;
+
+/// Chain id of the 1Money devnet.
+pub const DEFAULT_CHAIN_ID: u64 = 1_212_101;
+
+/// Timestamp of the checkpoint `0`, each following checkpoint is one second later.
+pub const DEFAULT_GENESIS_TIMESTAMP: u64 = 1_760_000_000;
+
+#[derive(Clone, Debug)]
+pub struct FakeConfig {
+    pub chain_id: u64,
+    /// Operator of the network, the only account allowed to issue tokens.
+    pub operator: VerifyingKey,
+    /// Validators of the first epoch.
+    pub validators: Vec<VerifyingKey>,
+    /// Interval between the checkpoints sealing the pending transactions.
+    pub checkpoint_interval: Duration,
+    pub genesis_timestamp: u64,
+}
+
+impl FakeConfig {
+    /// Configuration of a devnet operated and validated by `operator`.
+    pub fn new(operator: VerifyingKey) -> Self {
+        Self {
+            chain_id: DEFAULT_CHAIN_ID,
+            operator,
+            validators: vec![operator],
+            checkpoint_interval: Duration::from_millis(500),
+            genesis_timestamp: DEFAULT_GENESIS_TIMESTAMP,
+        }
+    }
+}
+
+/// Fake 1Money node, stopped when dropped.
+#[derive(Debug)]
+pub struct FakeOneMoney {
+    url: Url,
+    node: Arc<Node>,
+    tasks: Vec<JoinHandle<()>>,
+}
+
+impl FakeOneMoney {
+    /// Starts the node on a free local port.
+    pub async fn spawn(config: FakeConfig) -> Result<Self, Error> {
+        let listener = TcpListener::bind((Ipv4Addr::LOCALHOST, 0))
+            .await
+            .map_err(Error::Bind)?;
+        let url = Url::parse(&format!(
+            "http://{}/",
+            listener.local_addr().map_err(Error::Bind)?
+        ))?;
+
+        let node = Arc::new(Node::new(Ledger::new(
+            config.chain_id,
+            config.operator,
+            config.validators,
+            config.genesis_timestamp,
+        )));
+
+        let router = server::router(node.clone());
+        let server = tokio::spawn(async move {
+            if let Err(err) = axum::serve(listener, router).await {
+                warn!(%err, "fake 1Money node stopped");
+            }
+        });
+        let sealer = tokio::spawn({
+            let node = node.clone();
+            async move {
+                // The first checkpoint is sealed one interval after startup, not
+                // immediately, so transactions submitted right away land in it.
+                let start = tokio::time::Instant::now() + config.checkpoint_interval;
+                let mut interval = tokio::time::interval_at(start, config.checkpoint_interval);
+                loop {
+                    interval.tick().await;
+                    node.seal_checkpoint();
+                }
+            }
+        });
+
+        info!(%url, "fake 1Money node listening");
+        Ok(Self {
+            url,
+            node,
+            tasks: vec![server, sealer],
+        })
+    }
+
+    /// URL of the REST API.
+    pub const fn url(&self) -> &Url {
+        &self.url
+    }
+
+    /// URL of the certified transactions websocket.
+    pub fn ws_url(&self) -> Url {
+        let mut url = self.url.clone();
+        url.set_scheme("ws")
+            .expect("an http URL switches to the ws scheme");
+        url
+    }
+
+    /// Seals the pending transactions without waiting for the checkpoint interval.
+    ///
+    /// Returns the number of the sealed checkpoint, or `None` without pending transactions.
+    pub fn seal_checkpoint(&self) -> Option<u64> {
+        self.node.seal_checkpoint()
+    }
+
+    /// Starts a new epoch validated by `validators` and returns its id.
+    pub fn rotate_validators(&self, validators: Vec<VerifyingKey>) -> u64 {
+        self.node.ledger().rotate_validators(validators)
+    }
+
+    /// Locks the ledger, to inspect the state of the network.
+    pub fn ledger(&self) -> MutexGuard<'_, Ledger> {
+        self.node.ledger()
+    }
+}
+
+impl Drop for FakeOneMoney {
+    fn drop(&mut self) {
+        for task in &self.tasks {
+            task.abort();
+        }
+    }
+}
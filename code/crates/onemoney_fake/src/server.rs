@@ -0,0 +1,400 @@
+//! REST routes and certified transactions websocket of the fake node.
+//!
+//! The websocket is served at the root of the REST URL, so that the relayer derives its URL by
+//! switching the scheme of the node URL to `ws`.
+
+use std::sync::{Arc, Mutex, MutexGuard};
+
+use alloy_primitives::{keccak256, Address, B256};
+use alloy_rlp::Encodable;
+use axum::extract::ws::{Message, WebSocket, WebSocketUpgrade};
+use axum::extract::{Query, State};
+use axum::response::Response;
+use axum::routing::{get, post};
+use axum::{Json, Router};
+use onemoney_protocol::{
+    PaymentPayload, Signature, TokenAuthorityPayload, TokenBridgeAndMintPayload,
+    TokenBurnAndBridgePayload, TokenMintPayload,
+};
+use serde::de::DeserializeOwned;
+use serde::Deserialize;
+use serde_json::{json, Value};
+use tokio::sync::broadcast;
+use tracing::{debug, warn};
+
+use crate::error::ApiError;
+use crate::ledger::{Ledger, Payload, Submission, TokenIssuePayload, Transaction};
+use crate::view;
+
+/// Capacity of the certified transactions channel, per subscriber.
+const CERTIFIED_CAPACITY: usize = 1024;
+
+/// Ledger of the fake node, with the subscribers of its certified transactions.
+#[derive(Debug)]
+pub struct Node {
+    ledger: Mutex<Ledger>,
+    certified: broadcast::Sender<String>,
+}
+
+impl Node {
+    pub fn new(ledger: Ledger) -> Self {
+        Self {
+            ledger: Mutex::new(ledger),
+            certified: broadcast::channel(CERTIFIED_CAPACITY).0,
+        }
+    }
+
+    pub fn ledger(&self) -> MutexGuard<'_, Ledger> {
+        self.ledger
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    /// Seals the pending transactions and certifies them to the websocket subscribers.
+    ///
+    /// Returns the number of the sealed checkpoint, or `None` without pending transactions.
+    pub fn seal_checkpoint(&self) -> Option<u64> {
+        let mut ledger = self.ledger();
+        let checkpoint = ledger.seal_checkpoint()?.clone();
+        for hash in &checkpoint.transactions {
+            if let Some(transaction) = ledger.transaction(hash) {
+                // Without subscribers the certificate is dropped, like on a real node.
+                let _ = self
+                    .certified
+                    .send(view::certified_transaction(transaction).to_string());
+            }
+        }
+        debug!(
+            number = checkpoint.number,
+            transactions = checkpoint.transactions.len(),
+            "sealed checkpoint"
+        );
+        Some(checkpoint.number)
+    }
+
+    fn submit<P: Submitted>(&self, request: Signed<P>) -> Result<Transaction, ApiError> {
+        let (from, hash) = request.recover()?;
+        let submission = Submission {
+            hash,
+            chain_id: request.payload.chain_id(),
+            from,
+            nonce: request.payload.nonce(),
+            signature: request.signature,
+            payload: request.payload.into_payload(hash),
+        };
+        let transaction = self.ledger().submit(submission)?;
+        debug!(%hash, %from, nonce = transaction.nonce, "accepted transaction");
+        Ok(transaction)
+    }
+}
+
+pub fn router(node: Arc<Node>) -> Router {
+    Router::new()
+        .route("/", get(subscribe))
+        .route("/v1/chains/chain_id", get(chain_id))
+        .route("/v1/accounts/nonce", get(account_nonce))
+        .route("/v1/accounts/bbnonce", get(account_bbnonce))
+        .route("/v1/accounts/token_account", get(token_account))
+        .route("/v1/checkpoints/number", get(checkpoint_number))
+        .route("/v1/checkpoints/by_number", get(checkpoint_by_number))
+        .route("/v1/transactions/by_hash", get(transaction_by_hash))
+        .route("/v1/transactions/receipt/by_hash", get(receipt_by_hash))
+        .route("/v1/transactions/payment", post(submit::<PaymentPayload>))
+        .route("/v1/tokens/issue", post(issue))
+        .route(
+            "/v1/tokens/grant_authority",
+            post(submit::<TokenAuthorityPayload>),
+        )
+        .route("/v1/tokens/mint", post(submit::<TokenMintPayload>))
+        .route(
+            "/v1/tokens/bridge_and_mint",
+            post(submit::<TokenBridgeAndMintPayload>),
+        )
+        .route(
+            "/v1/tokens/burn_and_bridge",
+            post(submit::<TokenBurnAndBridgePayload>),
+        )
+        .route("/v1/governances/epoch", get(latest_epoch))
+        .route("/v1/governances/epoch/by_id", get(epoch_by_id))
+        .with_state(node)
+}
+
+/// Body of a submitted transaction: its payload and the signature of its RLP encoding.
+#[derive(Debug, Deserialize)]
+struct Signed<P> {
+    #[serde(flatten)]
+    payload: P,
+    signature: Signature,
+}
+
+impl<P: Encodable> Signed<P> {
+    /// Returns the signer of the payload and the hash of the transaction.
+    fn recover(&self) -> Result<(Address, B256), ApiError> {
+        let mut encoded = Vec::new();
+        self.payload.encode(&mut encoded);
+        let signing_hash = keccak256(encoded);
+
+        let parity = match self.signature.v {
+            0 | 27 => false,
+            1 | 28 => true,
+            v => {
+                return Err(ApiError::Invalid {
+                    field: "signature",
+                    message: format!("invalid recovery id {v}"),
+                })
+            }
+        };
+        let from = alloy_primitives::Signature::new(self.signature.r, self.signature.s, parity)
+            .recover_address_from_prehash(&signing_hash)
+            .map_err(|e| ApiError::Invalid {
+                field: "signature",
+                message: e.to_string(),
+            })?;
+
+        let hash = keccak256(
+            [
+                signing_hash.as_slice(),
+                &self.signature.r.to_be_bytes::<32>(),
+                &self.signature.s.to_be_bytes::<32>(),
+                &[u8::from(parity)],
+            ]
+            .concat(),
+        );
+        Ok((from, hash))
+    }
+}
+
+/// Payload of a transaction submitted to the REST API.
+trait Submitted: Encodable + DeserializeOwned + Send + 'static {
+    fn chain_id(&self) -> u64;
+
+    fn nonce(&self) -> u64;
+
+    fn into_payload(self, hash: B256) -> Payload;
+}
+
+macro_rules! submitted {
+    ($payload:ty, $variant:ident) => {
+        impl Submitted for $payload {
+            fn chain_id(&self) -> u64 {
+                self.chain_id
+            }
+
+            fn nonce(&self) -> u64 {
+                self.nonce
+            }
+
+            fn into_payload(self, _hash: B256) -> Payload {
+                Payload::$variant(self)
+            }
+        }
+    };
+}
+
+submitted!(TokenAuthorityPayload, TokenAuthority);
+submitted!(TokenMintPayload, TokenMint);
+submitted!(PaymentPayload, TokenTransfer);
+submitted!(TokenBridgeAndMintPayload, TokenBridgeAndMint);
+submitted!(TokenBurnAndBridgePayload, TokenBurnAndBridge);
+
+impl Submitted for TokenIssuePayload {
+    fn chain_id(&self) -> u64 {
+        self.chain_id
+    }
+
+    fn nonce(&self) -> u64 {
+        self.nonce
+    }
+
+    /// Issues the token at an address derived from the hash of its issuance.
+    fn into_payload(self, hash: B256) -> Payload {
+        Payload::TokenCreate {
+            token: Address::from_word(hash),
+            payload: self,
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct AddressQuery {
+    address: Address,
+}
+
+#[derive(Debug, Deserialize)]
+struct TokenAccountQuery {
+    #[serde(alias = "address")]
+    owner: Address,
+    token: Address,
+}
+
+#[derive(Debug, Deserialize)]
+struct HashQuery {
+    hash: B256,
+}
+
+#[derive(Debug, Deserialize)]
+struct CheckpointQuery {
+    number: u64,
+    #[serde(default)]
+    full: bool,
+}
+
+#[derive(Debug, Deserialize)]
+struct EpochQuery {
+    #[serde(alias = "epoch_id")]
+    id: u64,
+}
+
+async fn submit<P: Submitted>(
+    State(node): State<Arc<Node>>,
+    Json(request): Json<Signed<P>>,
+) -> Result<Json<Value>, ApiError> {
+    let transaction = node.submit(request)?;
+    Ok(Json(json!({ "hash": transaction.hash })))
+}
+
+async fn issue(
+    State(node): State<Arc<Node>>,
+    Json(request): Json<Signed<TokenIssuePayload>>,
+) -> Result<Json<Value>, ApiError> {
+    let transaction = node.submit(request)?;
+    let Payload::TokenCreate { token, .. } = transaction.payload else {
+        unreachable!("token issuances create tokens");
+    };
+    Ok(Json(json!({
+        "hash": { "hash": transaction.hash },
+        "token": token,
+    })))
+}
+
+async fn chain_id(State(node): State<Arc<Node>>) -> Json<Value> {
+    Json(json!({ "chain_id": node.ledger().chain_id() }))
+}
+
+async fn account_nonce(
+    State(node): State<Arc<Node>>,
+    Query(query): Query<AddressQuery>,
+) -> Json<Value> {
+    Json(json!({ "nonce": node.ledger().nonce(query.address) }))
+}
+
+async fn account_bbnonce(
+    State(node): State<Arc<Node>>,
+    Query(query): Query<AddressQuery>,
+) -> Json<Value> {
+    Json(json!({ "bbnonce": node.ledger().bbnonce(query.address) }))
+}
+
+async fn token_account(
+    State(node): State<Arc<Node>>,
+    Query(query): Query<TokenAccountQuery>,
+) -> Result<Json<Value>, ApiError> {
+    let balance = node
+        .ledger()
+        .balance(query.owner, query.token)
+        .ok_or(ApiError::NotFound {
+            resource: "token_account",
+        })?;
+    Ok(Json(view::token_account(query.owner, query.token, balance)))
+}
+
+/// Returns the number of the latest checkpoint, `0` before the first one is sealed.
+async fn checkpoint_number(State(node): State<Arc<Node>>) -> Json<Value> {
+    let number = node
+        .ledger()
+        .latest_checkpoint()
+        .map_or(0, |checkpoint| checkpoint.number);
+    Json(json!({ "number": number }))
+}
+
+async fn checkpoint_by_number(
+    State(node): State<Arc<Node>>,
+    Query(query): Query<CheckpointQuery>,
+) -> Result<Json<Value>, ApiError> {
+    let ledger = node.ledger();
+    let checkpoint = ledger.checkpoint(query.number).ok_or(ApiError::NotFound {
+        resource: "checkpoint",
+    })?;
+    Ok(Json(view::checkpoint(&ledger, checkpoint, query.full)))
+}
+
+/// Returns the transaction `hash`, once sealed in a checkpoint.
+async fn transaction_by_hash(
+    State(node): State<Arc<Node>>,
+    Query(query): Query<HashQuery>,
+) -> Result<Json<Value>, ApiError> {
+    let ledger = node.ledger();
+    let transaction = sealed_transaction(&ledger, &query.hash)?;
+    Ok(Json(view::transaction(transaction)))
+}
+
+async fn receipt_by_hash(
+    State(node): State<Arc<Node>>,
+    Query(query): Query<HashQuery>,
+) -> Result<Json<Value>, ApiError> {
+    let ledger = node.ledger();
+    let transaction = sealed_transaction(&ledger, &query.hash)?;
+    Ok(Json(view::receipt(transaction)))
+}
+
+fn sealed_transaction<'a>(ledger: &'a Ledger, hash: &B256) -> Result<&'a Transaction, ApiError> {
+    ledger
+        .transaction(hash)
+        .filter(|transaction| transaction.location.is_some())
+        .ok_or(ApiError::NotFound {
+            resource: "transaction",
+        })
+}
+
+async fn latest_epoch(State(node): State<Arc<Node>>) -> Json<Value> {
+    let ledger = node.ledger();
+    Json(view::epoch(&ledger, ledger.latest_epoch()))
+}
+
+async fn epoch_by_id(
+    State(node): State<Arc<Node>>,
+    Query(query): Query<EpochQuery>,
+) -> Result<Json<Value>, ApiError> {
+    let ledger = node.ledger();
+    let epoch = ledger
+        .epoch(query.id)
+        .ok_or(ApiError::NotFound { resource: "epoch" })?;
+    Ok(Json(view::epoch(&ledger, epoch)))
+}
+
+async fn subscribe(State(node): State<Arc<Node>>, upgrade: WebSocketUpgrade) -> Response {
+    let certified = node.certified.subscribe();
+    upgrade.on_upgrade(move |socket| stream_certified_transactions(socket, certified))
+}
+
+/// Forwards the certified transactions to `socket` once it subscribed to them.
+///
+/// The socket keeps being read while waiting for transactions, which answers its pings.
+async fn stream_certified_transactions(
+    mut socket: WebSocket,
+    mut certified: broadcast::Receiver<String>,
+) {
+    let mut subscribed = false;
+    loop {
+        tokio::select! {
+            message = socket.recv() => match message {
+                Some(Ok(Message::Text(request))) => {
+                    subscribed |= request.contains("CERTIFIED_TRANSACTIONS");
+                }
+                Some(Ok(_)) => {}
+                Some(Err(_)) | None => return,
+            },
+            transaction = certified.recv() => match transaction {
+                Ok(transaction) => {
+                    if subscribed && socket.send(Message::Text(transaction.into())).await.is_err() {
+                        return;
+                    }
+                }
+                Err(broadcast::error::RecvError::Lagged(skipped)) => {
+                    warn!(skipped, "certified transactions subscriber lagged behind");
+                }
+                Err(broadcast::error::RecvError::Closed) => return,
+            },
+        }
+    }
+}
@@ -0,0 +1,257 @@
+//! JSON bodies of the fake node, shaped like the responses of the 1Money API.
+
+use alloy_primitives::{keccak256, Address};
+use alloy_signer::k256::ecdsa::VerifyingKey;
+use alloy_signer::utils::public_key_to_address;
+use onemoney_protocol::AuthorityAction;
+use serde_json::{json, Value};
+
+use crate::ledger::{Checkpoint, Epoch, Ledger, Payload, Transaction};
+
+/// Returns the `transaction_type` and `data` of `payload` in the transactions of the REST API,
+/// with the values as decimal strings.
+fn transaction_data(payload: &Payload) -> (&'static str, Value) {
+    match payload {
+        Payload::TokenCreate { payload, .. } => (
+            "TokenCreate",
+            json!({
+                "symbol": payload.symbol,
+                "decimals": payload.decimals,
+                "master_authority": payload.master_authority,
+                "is_private": payload.is_private,
+                "name": payload.name,
+            }),
+        ),
+        Payload::TokenAuthority(payload) => (
+            if matches!(payload.action, AuthorityAction::Grant) {
+                "TokenGrantAuthority"
+            } else {
+                "TokenRevokeAuthority"
+            },
+            json!({
+                "authority_type": payload.authority_type,
+                "authority_address": payload.authority_address,
+                "value": payload.value.to_string(),
+                "token": payload.token,
+            }),
+        ),
+        Payload::TokenMint(payload) => (
+            "TokenMint",
+            json!({
+                "value": payload.value.to_string(),
+                "recipient": payload.recipient,
+                "token": payload.token,
+            }),
+        ),
+        Payload::TokenTransfer(payload) => (
+            "TokenTransfer",
+            json!({
+                "value": payload.value.to_string(),
+                "recipient": payload.recipient,
+                "token": payload.token,
+            }),
+        ),
+        Payload::TokenBridgeAndMint(payload) => (
+            "TokenBridgeAndMint",
+            json!({
+                "value": payload.value.to_string(),
+                "recipient": payload.recipient,
+                "token": payload.token,
+                "source_chain_id": payload.source_chain_id,
+                "source_tx_hash": payload.source_tx_hash,
+                "bridge_metadata": payload.bridge_metadata,
+            }),
+        ),
+        Payload::TokenBurnAndBridge(payload) => (
+            "TokenBurnAndBridge",
+            json!({
+                "value": payload.value.to_string(),
+                "sender": payload.sender,
+                "destination_chain_id": payload.destination_chain_id,
+                "destination_address": payload.destination_address,
+                "escrow_fee": payload.escrow_fee.to_string(),
+                "bridge_metadata": payload.bridge_metadata,
+                "token": payload.token,
+            }),
+        ),
+    }
+}
+
+/// Returns the signed payload of `payload`, as carried by the certified transactions.
+fn signed_payload(payload: &Payload) -> Value {
+    let payload = match payload {
+        Payload::TokenCreate { payload, .. } => serde_json::to_value(payload),
+        Payload::TokenAuthority(payload) => serde_json::to_value(payload),
+        Payload::TokenMint(payload) => serde_json::to_value(payload),
+        Payload::TokenTransfer(payload) => serde_json::to_value(payload),
+        Payload::TokenBridgeAndMint(payload) => serde_json::to_value(payload),
+        Payload::TokenBurnAndBridge(payload) => serde_json::to_value(payload),
+    };
+    payload.expect("payloads serialize to JSON")
+}
+
+pub fn transaction(transaction: &Transaction) -> Value {
+    let (transaction_type, data) = transaction_data(&transaction.payload);
+    let location = transaction.location;
+    json!({
+        "hash": transaction.hash,
+        "checkpoint_hash": location.map(|location| location.checkpoint_hash),
+        "checkpoint_number": location.map(|location| location.checkpoint_number),
+        "transaction_index": location.map(|location| location.index),
+        "chain_id": transaction.chain_id,
+        "from": transaction.from,
+        "nonce": transaction.nonce,
+        "transaction_type": transaction_type,
+        "data": data,
+        "signature": {
+            "r": transaction.signature.r,
+            "s": transaction.signature.s,
+            "v": transaction.signature.v,
+        },
+    })
+}
+
+pub fn receipt(transaction: &Transaction) -> Value {
+    let (recipient, token) = match &transaction.payload {
+        Payload::TokenCreate { token, .. } => (None, Some(*token)),
+        Payload::TokenAuthority(payload) => (Some(payload.authority_address), Some(payload.token)),
+        Payload::TokenMint(payload) => (Some(payload.recipient), Some(payload.token)),
+        Payload::TokenTransfer(payload) => (Some(payload.recipient), Some(payload.token)),
+        Payload::TokenBridgeAndMint(payload) => (Some(payload.recipient), Some(payload.token)),
+        Payload::TokenBurnAndBridge(payload) => (None, Some(payload.token)),
+    };
+    let bridge_info = match (&transaction.payload, transaction.bbnonce) {
+        (Payload::TokenBurnAndBridge(payload), Some(bbnonce)) => Some(json!({
+            "bbnonce": bbnonce,
+            "destination_chain_id": payload.destination_chain_id,
+            "destination_address": payload.destination_address,
+        })),
+        _ => None,
+    };
+    let location = transaction.location;
+    json!({
+        "success": true,
+        "transaction_hash": transaction.hash,
+        "transaction_index": location.map(|location| location.index),
+        "checkpoint_hash": location.map(|location| location.checkpoint_hash),
+        "checkpoint_number": location.map(|location| location.checkpoint_number),
+        "fee_used": "0",
+        "from": transaction.from,
+        "recipient": recipient,
+        "token_address": token,
+        "success_info": {
+            "sender": transaction.from,
+            "receiver": recipient.unwrap_or(transaction.from),
+            "is_private": false,
+            "message": "",
+            "bridge_info": bridge_info,
+        },
+    })
+}
+
+/// Returns `checkpoint`, with its full transactions if `full` is set.
+pub fn checkpoint(ledger: &Ledger, checkpoint: &Checkpoint, full: bool) -> Value {
+    let transactions = if full {
+        checkpoint
+            .transactions
+            .iter()
+            .filter_map(|hash| ledger.transaction(hash))
+            .map(transaction)
+            .collect::<Vec<_>>()
+    } else {
+        checkpoint
+            .transactions
+            .iter()
+            .map(|hash| json!(hash))
+            .collect()
+    };
+    json!({
+        "hash": checkpoint.hash,
+        "parent_hash": checkpoint.parent_hash,
+        "state_root": checkpoint.state_root,
+        "transactions_root": checkpoint.transactions_root,
+        "receipts_root": checkpoint.receipts_root,
+        "number": checkpoint.number,
+        "timestamp": checkpoint.timestamp,
+        "extra_data": "",
+        "transactions": transactions,
+        "size": checkpoint.transactions.len(),
+    })
+}
+
+/// Returns `transaction` as a message of the `CERTIFIED_TRANSACTIONS` stream.
+pub fn certified_transaction(transaction: &Transaction) -> Value {
+    let (transaction_type, _) = transaction_data(&transaction.payload);
+    json!({
+        "result": {
+            "certificate": {
+                "V0": {
+                    "tx": {
+                        "UserTransaction": {
+                            "envelope": {
+                                transaction_type: { "payload": signed_payload(&transaction.payload) }
+                            }
+                        }
+                    }
+                }
+            },
+            "tx_hash": transaction.hash,
+        }
+    })
+}
+
+/// Returns the governance certificate of `epoch`, whose special accounts are the operator.
+pub fn epoch(ledger: &Ledger, epoch: &Epoch) -> Value {
+    let operator_key = encode_key(ledger.operator());
+    let operator_address = ledger.operator_address();
+    let members = epoch
+        .validators
+        .iter()
+        .enumerate()
+        .map(|(index, key)| {
+            json!({
+                "consensus_public_key": encode_key(key),
+                "address": public_key_to_address(key),
+                "peer_id": format!("fake-{}-{index}", epoch.epoch_id),
+                "archive": false,
+            })
+        })
+        .collect::<Vec<_>>();
+    json!({
+        "epoch_id": epoch.epoch_id,
+        "certificate_hash": keccak256(epoch.epoch_id.to_be_bytes()),
+        "certificate": {
+            "type": "Genesis",
+            "proposal": {
+                "message": {
+                    "epoch": { "epoch_id": epoch.epoch_id },
+                    "chain": ledger.chain_id(),
+                    "special_accounts": {
+                        "operator_public_key": operator_key,
+                        "operator_address": operator_address,
+                        "escrow_account_public_key": operator_key,
+                        "escrow_account_address": operator_address,
+                        "pricing_authority_public_key": operator_key,
+                        "pricing_authority_address": operator_address,
+                    },
+                    "validator_set": { "members": members }
+                }
+            }
+        }
+    })
+}
+
+pub fn token_account(owner: Address, token: Address, balance: alloy_primitives::U256) -> Value {
+    json!({
+        "token_account_address": Address::from_word(keccak256([owner.as_slice(), token.as_slice()].concat())),
+        "balance": balance.to_string(),
+        "nonce": 0,
+    })
+}
+
+fn encode_key(key: &VerifyingKey) -> String {
+    format!(
+        "0x{}",
+        alloy_primitives::hex::encode(key.to_encoded_point(true).as_bytes())
+    )
+}
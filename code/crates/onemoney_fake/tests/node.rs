@@ -0,0 +1,267 @@
+use core::time::Duration;
+
+use alloy_primitives::hex::ToHexExt;
+use alloy_primitives::{keccak256, Address, U256};
+use alloy_rlp::Encodable;
+use alloy_signer_local::PrivateKeySigner;
+use color_eyre::eyre::{eyre, Result};
+use futures::{SinkExt, StreamExt};
+use onemoney_fake::ledger::TokenIssuePayload;
+use onemoney_fake::{FakeConfig, FakeOneMoney, DEFAULT_CHAIN_ID};
+use onemoney_protocol::crypto::signing::sign_hash;
+use onemoney_protocol::responses::CheckpointTransactions;
+use onemoney_protocol::{
+    Authority, AuthorityAction, Client, Error as OnemoneyError, TokenAuthorityPayload,
+    TokenBridgeAndMintPayload, TokenBurnAndBridgePayload, TokenMintPayload,
+};
+use serde_json::{json, Value};
+use tokio_tungstenite::tungstenite::Message;
+
+/// Fake node sealing checkpoints only on demand, with a client and its operator.
+async fn spawn() -> Result<(FakeOneMoney, Client, PrivateKeySigner)> {
+    let operator = PrivateKeySigner::random();
+    let fake = FakeOneMoney::spawn(FakeConfig {
+        checkpoint_interval: Duration::from_secs(3600),
+        ..FakeConfig::new(*operator.credential().verifying_key())
+    })
+    .await?;
+    let client = Client::custom(fake.url().to_string())?;
+    Ok((fake, client, operator))
+}
+
+fn private_key(wallet: &PrivateKeySigner) -> String {
+    wallet.to_bytes().encode_hex_with_prefix()
+}
+
+async fn issue_token(client: &Client, operator: &PrivateKeySigner) -> Result<Address> {
+    let payload = TokenIssuePayload {
+        chain_id: DEFAULT_CHAIN_ID,
+        nonce: client.get_account_nonce(operator.address()).await?.nonce,
+        symbol: "FAKE".to_owned(),
+        name: "Fake Token".to_owned(),
+        decimals: 6,
+        master_authority: operator.address(),
+        is_private: false,
+    };
+    let mut encoded = Vec::new();
+    payload.encode(&mut encoded);
+    let signature = sign_hash(&keccak256(encoded), &private_key(operator))?;
+
+    let mut request = serde_json::to_value(&payload)?;
+    request["signature"] = serde_json::to_value(&signature)?;
+    let response: Value = client.post("v1/tokens/issue", &request).await?;
+    response["token"]
+        .as_str()
+        .ok_or_else(|| eyre!("missing token in {response}"))?
+        .parse()
+        .map_err(|e| eyre!("invalid token address: {e}"))
+}
+
+async fn grant_bridge(
+    client: &Client,
+    operator: &PrivateKeySigner,
+    token: Address,
+    relayer: Address,
+) -> Result<()> {
+    let payload = TokenAuthorityPayload {
+        chain_id: DEFAULT_CHAIN_ID,
+        nonce: client.get_account_nonce(operator.address()).await?.nonce,
+        token,
+        action: AuthorityAction::Grant,
+        authority_type: Authority::Bridge,
+        authority_address: relayer,
+        value: U256::MAX,
+    };
+    client
+        .grant_authority(payload, &private_key(operator))
+        .await?;
+    Ok(())
+}
+
+#[tokio::test]
+#[test_log::test]
+async fn transactions_are_visible_once_sealed() -> Result<()> {
+    let (fake, client, operator) = spawn().await?;
+
+    let token = issue_token(&client, &operator).await?;
+    assert_eq!(client.get_account_nonce(operator.address()).await?.nonce, 1);
+    assert!(fake.ledger().latest_checkpoint().is_none());
+
+    let mint = client
+        .mint_token(
+            TokenMintPayload {
+                chain_id: DEFAULT_CHAIN_ID,
+                nonce: 1,
+                recipient: operator.address(),
+                value: U256::from(100u64),
+                token,
+            },
+            &private_key(&operator),
+        )
+        .await?;
+    assert!(matches!(
+        client
+            .get_transaction_by_hash(&mint.hash.encode_hex_with_prefix())
+            .await,
+        Err(OnemoneyError::ResourceNotFound { .. })
+    ));
+
+    assert_eq!(fake.seal_checkpoint(), Some(0));
+    assert_eq!(fake.seal_checkpoint(), None);
+
+    let transaction = client
+        .get_transaction_by_hash(&mint.hash.encode_hex_with_prefix())
+        .await?;
+    assert_eq!(transaction.hash, mint.hash);
+    assert_eq!(client.get_checkpoint_number().await?.number, 0);
+
+    let checkpoint = client.get_checkpoint_by_number(0, true).await?;
+    let CheckpointTransactions::Full(transactions) = checkpoint.transactions else {
+        panic!("checkpoint without full transactions");
+    };
+    assert_eq!(transactions.len(), 2);
+    assert_eq!(transactions[1].hash, mint.hash);
+
+    let balance = client
+        .get_associated_token_account(operator.address(), token)
+        .await?
+        .balance;
+    assert_eq!(balance, "100");
+
+    Ok(())
+}
+
+#[tokio::test]
+#[test_log::test]
+async fn bridge_and_mint_requires_the_bridge_authority_and_rejects_replays() -> Result<()> {
+    let (fake, client, operator) = spawn().await?;
+    let relayer = PrivateKeySigner::random();
+    let recipient = Address::repeat_byte(0x11);
+
+    let token = issue_token(&client, &operator).await?;
+    let payload = TokenBridgeAndMintPayload {
+        chain_id: DEFAULT_CHAIN_ID,
+        nonce: 0,
+        recipient,
+        value: U256::from(750u64),
+        token,
+        source_chain_id: 1,
+        source_tx_hash: keccak256("bridgeFrom").encode_hex_with_prefix(),
+        bridge_metadata: None,
+    };
+
+    let unauthorized = client
+        .bridge_and_mint(payload.clone(), &private_key(&relayer))
+        .await;
+    assert!(matches!(
+        unauthorized,
+        Err(OnemoneyError::BusinessLogic { .. })
+    ));
+
+    grant_bridge(&client, &operator, token, relayer.address()).await?;
+    client
+        .bridge_and_mint(payload.clone(), &private_key(&relayer))
+        .await?;
+    let replay = client
+        .bridge_and_mint(payload, &private_key(&relayer))
+        .await;
+    assert!(matches!(
+        replay,
+        Err(OnemoneyError::BusinessLogic { ref reason, .. })
+            if reason.contains("transaction already exists")
+    ));
+
+    assert_eq!(client.get_account_nonce(relayer.address()).await?.nonce, 1);
+    assert_eq!(
+        fake.ledger().balance(recipient, token),
+        Some(U256::from(750u64))
+    );
+
+    Ok(())
+}
+
+#[tokio::test]
+#[test_log::test]
+async fn burn_and_bridge_is_certified_with_its_bbnonce() -> Result<()> {
+    let (fake, client, operator) = spawn().await?;
+    let user = PrivateKeySigner::random();
+
+    let (mut ws, _) = tokio_tungstenite::connect_async(fake.ws_url().as_str()).await?;
+    ws.send(Message::Text(
+        json!({
+            "id": 1,
+            "method": "SUBSCRIBE",
+            "stream": { "name": "CERTIFIED_TRANSACTIONS", "full": true }
+        })
+        .to_string()
+        .into(),
+    ))
+    .await?;
+
+    let token = issue_token(&client, &operator).await?;
+    client
+        .mint_token(
+            TokenMintPayload {
+                chain_id: DEFAULT_CHAIN_ID,
+                nonce: 1,
+                recipient: user.address(),
+                value: U256::from(100u64),
+                token,
+            },
+            &private_key(&operator),
+        )
+        .await?;
+    let burn = client
+        .burn_and_bridge(
+            TokenBurnAndBridgePayload {
+                chain_id: DEFAULT_CHAIN_ID,
+                nonce: 0,
+                sender: user.address(),
+                value: U256::from(60u64),
+                token,
+                destination_chain_id: 1,
+                destination_address: Address::repeat_byte(0xAB).encode_hex_with_prefix(),
+                escrow_fee: U256::from(5u64),
+                bridge_metadata: None,
+                bridge_param: None,
+            },
+            &private_key(&user),
+        )
+        .await?;
+    assert_eq!(fake.seal_checkpoint(), Some(0));
+
+    let certified = tokio::time::timeout(Duration::from_secs(5), async {
+        while let Some(message) = ws.next().await {
+            if let Message::Text(text) = message? {
+                let certified: Value = serde_json::from_str(&text)?;
+                let envelope =
+                    &certified["result"]["certificate"]["V0"]["tx"]["UserTransaction"]["envelope"];
+                if envelope.get("TokenBurnAndBridge").is_some() {
+                    return Ok(certified);
+                }
+            }
+        }
+        Err(eyre!("certified transactions websocket closed"))
+    })
+    .await??;
+    assert_eq!(
+        certified["result"]["tx_hash"],
+        json!(burn.hash.encode_hex_with_prefix())
+    );
+
+    let receipt = client
+        .get_transaction_receipt_by_hash(&burn.hash.to_string())
+        .await?;
+    let bbnonce = receipt
+        .success_info
+        .and_then(|info| info.bridge_info)
+        .map(|bridge| bridge.bbnonce);
+    assert_eq!(bbnonce, Some(1));
+    assert_eq!(client.get_account_bbonce(user.address()).await?.bbnonce, 1);
+    assert_eq!(
+        fake.ledger().balance(user.address(), token),
+        Some(U256::from(35u64))
+    );
+
+    Ok(())
+}
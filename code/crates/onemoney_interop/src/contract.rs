@@ -47,6 +47,17 @@ sol!(
     )
 );
 
+sol!(
+    #[allow(clippy::too_many_arguments)]
+    #[sol(rpc, abi)]
+    #[derive(Debug)]
+    OMOFT,
+    concat!(
+        env!("CARGO_MANIFEST_DIR"),
+        "/../../solidity/out/OMOFT.sol/OMOFT.json"
+    )
+);
+
 pub async fn deploy_uups_like<P: Provider>(
     provider: &P,
     owner: Address,
@@ -54,12 +65,10 @@ pub async fn deploy_uups_like<P: Provider>(
     relayer: Address,
 ) -> Result<(Address, OMInterop::OMInteropInstance<&P>), Error> {
     // 1) Deploy the price oracle
-    let price_oracle_contract = PriceOracle::deploy(provider, owner, operator)
-        .await
-        .unwrap();
+    let price_oracle_contract = PriceOracle::deploy(provider, owner, operator).await?;
 
     // 2) Deploy the implementation (no constructor args)
-    let impl_instance = OMInterop::deploy(provider).await.unwrap();
+    let impl_instance = OMInterop::deploy(provider).await?;
     let impl_addr = *impl_instance.address();
 
     // 3) Encode initializer calldata
@@ -73,9 +82,7 @@ pub async fn deploy_uups_like<P: Provider>(
 
     // 4) Deploy ERC1967Proxy (constructor(address _logic, bytes _data))
     //    NOTE: pass constructor args as a single tuple
-    let proxy_instance = ERC1967Proxy::deploy(provider, impl_addr, init_data)
-        .await
-        .unwrap();
+    let proxy_instance = ERC1967Proxy::deploy(provider, impl_addr, init_data).await?;
     let proxy_addr = *proxy_instance.address();
 
     // 5) Bind the implementation ABI at the proxy address
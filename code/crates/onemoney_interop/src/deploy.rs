@@ -0,0 +1,260 @@
+//! Deployment of the full interop stack on the sidechain.
+//!
+//! [`deploy`] deploys the price oracle, the OMInterop implementation behind an ERC1967 proxy,
+//! the optional OMOFT tokens and the TxHashMapping contract, maps the tokens and hands the
+//! roles over to their accounts. The returned [`DeploymentManifest`] records where and when
+//! every contract was deployed.
+
+use alloy_contract::{CallBuilder, CallDecoder};
+use alloy_primitives::{Address, Bytes, B256};
+use alloy_provider::Provider;
+use alloy_sol_types::SolCall;
+use serde::{Deserialize, Serialize};
+use tracing::info;
+
+use crate::contract::{ERC1967Proxy, OMInterop, PriceOracle, TxHashMapping, OMOFT};
+use crate::error::Error;
+
+/// Version of the manifest format, bumped on incompatible changes.
+pub const MANIFEST_VERSION: u32 = 1;
+
+/// Accounts and tokens of a deployment.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Deployment {
+    /// Owner of the contracts, allowed to upgrade OMInterop and to change its roles.
+    pub owner: Address,
+    /// Operator of OMInterop and of the price oracle, mapping tokens and setting rate limits.
+    pub operator: Address,
+    /// Relayer account of OMInterop.
+    pub relayer: Address,
+    /// Account writing to TxHashMapping.
+    pub tx_mapping_relayer: Address,
+    /// LayerZero endpoint of the OMOFT tokens, required if any token is deployed.
+    pub lz_endpoint: Option<Address>,
+    pub tokens: Vec<TokenDeployment>,
+}
+
+/// 1Money token mapped to a sidechain token by the deployment.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TokenDeployment {
+    pub om_token: Address,
+    pub sidechain_token: SidechainToken,
+    /// Identifier of the `InteropProtocol` bridging the token.
+    pub interop_protocol: u8,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SidechainToken {
+    /// Token already deployed on the sidechain.
+    Existing(Address),
+    /// OMOFT token deployed behind an ERC1967 proxy.
+    Oft { name: String, symbol: String },
+}
+
+/// Addresses, deployment blocks and versions of a deployed stack.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DeploymentManifest {
+    /// Version of the manifest format, see [`MANIFEST_VERSION`].
+    pub version: u32,
+    pub chain_id: u64,
+    pub deployer: Address,
+    pub owner: Address,
+    pub operator: Address,
+    pub relayer: Address,
+    pub tx_mapping_relayer: Address,
+    /// Version reported by the deployed OMInterop implementation.
+    pub interop_version: String,
+    pub price_oracle: DeployedContract,
+    pub interop_implementation: DeployedContract,
+    /// Proxy of OMInterop, the address used by the relayer.
+    pub interop: DeployedContract,
+    pub tx_hash_mapping: DeployedContract,
+    pub tokens: Vec<DeployedToken>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DeployedContract {
+    pub address: Address,
+    pub block_number: u64,
+    pub transaction_hash: B256,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DeployedToken {
+    pub om_token: Address,
+    pub sc_token: Address,
+    pub interop_protocol: u8,
+    /// OMOFT contracts, if the sidechain token was deployed.
+    pub oft: Option<DeployedOft>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DeployedOft {
+    pub name: String,
+    pub symbol: String,
+    pub implementation: DeployedContract,
+    pub proxy: DeployedContract,
+}
+
+/// Deploys the stack described by `deployment` from the `deployer` account of `provider`.
+///
+/// OMInterop is initialized with the deployer as owner and operator so that it can map the
+/// tokens, then the operator role and the ownership are handed over. The deployer therefore
+/// holds no role at the end unless it is one of the configured accounts.
+pub async fn deploy<P: Provider>(
+    provider: &P,
+    deployer: Address,
+    deployment: &Deployment,
+) -> Result<DeploymentManifest, Error> {
+    let chain_id = provider.get_chain_id().await?;
+
+    let price_oracle = deploy_contract(
+        "PriceOracle",
+        PriceOracle::deploy_builder(provider, deployment.owner, deployment.operator),
+    )
+    .await?;
+
+    let interop_implementation = deploy_contract(
+        "OMInterop implementation",
+        OMInterop::deploy_builder(provider),
+    )
+    .await?;
+    let init_data: Bytes = OMInterop::initializeCall {
+        owner_: deployer,
+        operator_: deployer,
+        relayer_: deployment.relayer,
+        priceOracle_: price_oracle.address,
+    }
+    .abi_encode()
+    .into();
+    let interop = deploy_contract(
+        "OMInterop proxy",
+        ERC1967Proxy::deploy_builder(provider, interop_implementation.address, init_data),
+    )
+    .await?;
+    let interop_contract = OMInterop::new(interop.address, provider);
+
+    let mut tokens = Vec::with_capacity(deployment.tokens.len());
+    for token in &deployment.tokens {
+        let (sc_token, oft) = match &token.sidechain_token {
+            SidechainToken::Existing(sc_token) => (*sc_token, None),
+            SidechainToken::Oft { name, symbol } => {
+                let oft = deploy_oft(provider, deployment, interop.address, name, symbol).await?;
+                (oft.proxy.address, Some(oft))
+            }
+        };
+        send(interop_contract.mapTokenAddresses(token.om_token, sc_token, token.interop_protocol))
+            .await?;
+        info!(om_token = %token.om_token, %sc_token, "Mapped token");
+
+        tokens.push(DeployedToken {
+            om_token: token.om_token,
+            sc_token,
+            interop_protocol: token.interop_protocol,
+            oft,
+        });
+    }
+
+    if deployment.operator != deployer {
+        send(interop_contract.setOperator(deployment.operator)).await?;
+    }
+    if deployment.owner != deployer {
+        send(interop_contract.transferOwnership(deployment.owner)).await?;
+    }
+    let interop_version = interop_contract.version().call().await?;
+
+    let tx_hash_mapping = deploy_contract(
+        "TxHashMapping",
+        TxHashMapping::deploy_builder(provider, deployment.owner, deployment.tx_mapping_relayer),
+    )
+    .await?;
+
+    Ok(DeploymentManifest {
+        version: MANIFEST_VERSION,
+        chain_id,
+        deployer,
+        owner: deployment.owner,
+        operator: deployment.operator,
+        relayer: deployment.relayer,
+        tx_mapping_relayer: deployment.tx_mapping_relayer,
+        interop_version,
+        price_oracle,
+        interop_implementation,
+        interop,
+        tx_hash_mapping,
+        tokens,
+    })
+}
+
+async fn deploy_oft<P: Provider>(
+    provider: &P,
+    deployment: &Deployment,
+    interop: Address,
+    name: &str,
+    symbol: &str,
+) -> Result<DeployedOft, Error> {
+    let lz_endpoint = deployment.lz_endpoint.ok_or(Error::MissingLzEndpoint)?;
+
+    let implementation = deploy_contract(
+        "OMOFT implementation",
+        OMOFT::deploy_builder(provider, lz_endpoint),
+    )
+    .await?;
+    let init_data: Bytes = OMOFT::initializeCall {
+        name_: name.to_owned(),
+        symbol_: symbol.to_owned(),
+        omInterop_: interop,
+        delegate_: deployment.owner,
+    }
+    .abi_encode()
+    .into();
+    let proxy = deploy_contract(
+        "OMOFT proxy",
+        ERC1967Proxy::deploy_builder(provider, implementation.address, init_data),
+    )
+    .await?;
+
+    Ok(DeployedOft {
+        name: name.to_owned(),
+        symbol: symbol.to_owned(),
+        implementation,
+        proxy,
+    })
+}
+
+/// Sends the deployment transaction `builder` and waits for its receipt.
+pub(crate) async fn deploy_contract<P: Provider, D: CallDecoder>(
+    name: &str,
+    builder: CallBuilder<P, D>,
+) -> Result<DeployedContract, Error> {
+    let receipt = builder.send().await?.get_receipt().await?;
+    if !receipt.status() {
+        return Err(Error::Reverted(receipt.transaction_hash));
+    }
+    let address = receipt
+        .contract_address
+        .ok_or(Error::MissingContractAddress(receipt.transaction_hash))?;
+
+    let contract = DeployedContract {
+        address,
+        block_number: receipt.block_number.unwrap_or_default(),
+        transaction_hash: receipt.transaction_hash,
+    };
+    info!(
+        %address,
+        block_number = contract.block_number,
+        "Deployed {name}"
+    );
+    Ok(contract)
+}
+
+/// Sends `call` and waits for its receipt, returning the transaction hash.
+pub(crate) async fn send<P: Provider, D: CallDecoder>(
+    call: CallBuilder<P, D>,
+) -> Result<B256, Error> {
+    let receipt = call.send().await?.get_receipt().await?;
+    if !receipt.status() {
+        return Err(Error::Reverted(receipt.transaction_hash));
+    }
+    Ok(receipt.transaction_hash)
+}
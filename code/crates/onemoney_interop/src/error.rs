@@ -1,7 +1,49 @@
+use alloy_primitives::{Address, B256};
+
 #[derive(Debug, thiserror::Error)]
 pub enum Error {
     #[error("transport error: {0}")]
     Transport(#[from] alloy_transport::TransportError),
     #[error("event decode error: {0}")]
     Decode(#[from] alloy_sol_types::Error),
+    #[error("block subscription closed")]
+    SubscriptionClosed,
+    #[error("sidechain reorg deeper than the tracked blocks, block {block} was replaced")]
+    ReorgTooDeep { block: u64 },
+    #[error("contract call failed: {0}")]
+    Contract(#[from] alloy_contract::Error),
+    #[error("pending transaction failed: {0}")]
+    PendingTransaction(#[from] alloy_provider::PendingTransactionError),
+    #[error("transaction `{0}` reverted")]
+    Reverted(B256),
+    #[error("deployment transaction `{0}` did not create a contract")]
+    MissingContractAddress(B256),
+    #[error("an OFT token requires the LayerZero endpoint")]
+    MissingLzEndpoint,
+    #[error("the proxy already points to implementation `{0}`")]
+    SameImplementation(Address),
+    #[error("`{0}` is not a UUPS implementation of the ERC-1967 proxy")]
+    NotUups(Address),
+    #[error("implementation `{implementation}` reports the current version {version}")]
+    VersionUnchanged {
+        implementation: Address,
+        version: String,
+    },
+    #[error(
+        "implementation `{implementation}` reads another state from the proxy storage: {}",
+        .mismatches.join("; ")
+    )]
+    IncompatibleImplementation {
+        implementation: Address,
+        mismatches: Vec<String>,
+    },
+    #[error(
+        "upgrade `{transaction_hash}` did not preserve the proxy state, upgrade back to `{previous_implementation}`: {}",
+        .mismatches.join("; ")
+    )]
+    UpgradeStateMismatch {
+        transaction_hash: B256,
+        previous_implementation: Address,
+        mismatches: Vec<String>,
+    },
 }
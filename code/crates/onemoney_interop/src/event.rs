@@ -1,10 +1,14 @@
-use alloy_primitives::Address;
+use core::time::Duration;
+use std::collections::VecDeque;
+
+use alloy_primitives::{Address, B256};
 use alloy_provider::{Provider, ProviderBuilder, WsConnect};
-use alloy_rpc_types_eth::{Filter, Log as RpcLog};
+use alloy_rpc_types_eth::{BlockNumberOrTag, Filter, Log as RpcLog};
 use alloy_sol_types::SolEventInterface;
-use async_stream::try_stream;
+use async_stream::stream;
 use futures::stream::BoxStream;
 use futures::StreamExt;
+use tracing::warn;
 use url::Url;
 
 use crate::contract::OMInterop;
@@ -13,84 +17,259 @@ use crate::error::Error as OMInteropError;
 /// Convenience alias for decoded OMInterop logs.
 pub type OMInteropLog = RpcLog<OMInterop::OMInteropEvents>;
 
+/// Update yielded by [`event_stream`].
+#[derive(Debug, Clone)]
+pub enum EventUpdate {
+    /// The log is buried under the requested number of confirmations.
+    Confirmed(OMInteropLog),
+    /// A log previously yielded as confirmed was removed from the canonical chain by a reorg.
+    Retracted(OMInteropLog),
+}
+
 const MAX_BLOCK_RANGE: u64 = 100_000;
+const RECONNECT_BACKOFF: Duration = Duration::from_secs(1);
+const MAX_RECONNECT_BACKOFF: Duration = Duration::from_secs(30);
+/// Number of blocks behind the chain head for which reorgs are tracked.
+const REORG_WINDOW: u64 = 256;
 
 /// Creates an async stream of OMInterop events starting at `from_block`.
 ///
-/// The stream first yields any historical events since `from_block`, then
-/// continues polling the node for new events by subscribing to logs over WebSocket.
+/// The stream first yields any historical events since `from_block`, then follows the chain
+/// by subscribing to new blocks over WebSocket and fetching the logs of every new block. Logs
+/// are only yielded once `confirmations` blocks were built on top of their block.
+///
+/// The hashes of the processed blocks are tracked for the last [`REORG_WINDOW`] blocks. When
+/// a reorg replaces blocks whose logs were already yielded, these logs are yielded again as
+/// [`EventUpdate::Retracted`], newest first, before the logs of the new canonical blocks.
+/// A reorg deeper than the tracked window ends the stream with
+/// [`OMInteropError::ReorgTooDeep`].
+///
+/// Transport failures do not end the stream. The subscription is re-established with an
+/// exponential backoff and the logs emitted while disconnected are backfilled from the last
+/// processed block, so that no log is skipped or yielded twice. Since logs are always
+/// fetched from that block, a subscription silently re-established by the transport does
+/// not lose logs either. Only logs which cannot be decoded end the stream with an error.
 pub async fn event_stream(
     http_endpoint: Url,
     ws_endpoint: Url,
     contract: Address,
     from_block: u64,
-) -> BoxStream<'static, Result<OMInteropLog, OMInteropError>> {
-    try_stream! {
-        let ws = WsConnect::new(ws_endpoint);
+    confirmations: u64,
+) -> BoxStream<'static, Result<EventUpdate, OMInteropError>> {
+    stream! {
         let http_provider = ProviderBuilder::new().connect_http(http_endpoint);
-        let ws_provider = ProviderBuilder::new().connect_ws(ws).await?;
 
         let mut last_position = from_block
             .checked_sub(1)
             .map(|block| (block, u64::MAX));
+        let mut next_block = from_block;
+        // Hashes of the last block of every processed range and logs yielded as confirmed,
+        // both limited to the reorg window
+        let mut processed = VecDeque::<(u64, B256)>::new();
+        let mut emitted = VecDeque::<OMInteropLog>::new();
+        let mut backoff = RECONNECT_BACKOFF;
+
+        loop {
+            let error = 'connection: {
+                let ws = WsConnect::new(ws_endpoint.clone());
+                let ws_provider = match ProviderBuilder::new().connect_ws(ws).await {
+                    Ok(provider) => provider,
+                    Err(err) => break 'connection OMInteropError::from(err),
+                };
+
+                // Subscribe before backfilling so that no block falls between both
+                let mut new_blocks = match ws_provider.subscribe_blocks().await {
+                    Ok(subscription) => subscription.into_stream(),
+                    Err(err) => break 'connection err.into(),
+                };
+
+                let mut latest_block = match http_provider.get_block_number().await {
+                    Ok(block) => block,
+                    Err(err) => break 'connection err.into(),
+                };
+                backoff = RECONNECT_BACKOFF;
 
-        let live_filter = Filter::new().address(contract);
-        let mut live_stream = ws_provider
-            .subscribe_logs(&live_filter)
-            .await?
-            .into_stream();
+                loop {
+                    let fork_block = match find_fork_block(&http_provider, &processed).await {
+                        Ok(fork_block) => fork_block,
+                        Err(err @ OMInteropError::ReorgTooDeep { .. }) => {
+                            yield Err(err);
+                            return;
+                        }
+                        Err(err) => break 'connection err,
+                    };
 
-        let latest_block = http_provider
-            .get_block_number()
-            .await?;
+                    if let Some(fork_block) = fork_block {
+                        // Retract the logs of the replaced blocks, logs of blocks still part
+                        // of the canonical chain stay confirmed
+                        while let Some(log) = emitted.back() {
+                            let block = log.block_number.unwrap_or_default();
+                            if block <= fork_block {
+                                break;
+                            }
+                            match block_hash(&http_provider, block).await {
+                                Ok(hash) if hash.is_some() && hash == log.block_hash => break,
+                                Ok(_) => {}
+                                Err(err) => break 'connection err,
+                            }
+                            if let Some(log) = emitted.pop_back() {
+                                yield Ok(EventUpdate::Retracted(log));
+                            }
+                        }
 
-        // Keep a small overlap so recent logs still surface through the live stream.
-        // Five blocks is enough cushion for the reorg depths we expect.
-        let live_start = latest_block.saturating_add(5);
+                        warn!(fork_block, "Sidechain reorg detected, refetching OMInterop logs");
+                        processed.retain(|(block, _)| *block <= fork_block);
+                        next_block = fork_block + 1;
+                        last_position = emitted
+                            .back()
+                            .and_then(log_position)
+                            .max(Some((fork_block, u64::MAX)));
+                    }
 
-        let mut start = from_block;
+                    // Get chunks of 99_999 blocks to avoid error:
+                    // query exceeds block range 100_000
+                    let confirmed_block = latest_block.saturating_sub(confirmations);
+                    while next_block <= confirmed_block {
+                        let end = core::cmp::min(next_block + MAX_BLOCK_RANGE - 1, confirmed_block);
 
-        // Get chunks of 99_999 blocks to avoid error:
-        // query exceeds block range 100_000
-        while start <= live_start {
-            let end = core::cmp::min(start + MAX_BLOCK_RANGE - 1, live_start);
+                        let end_hash = match block_hash(&http_provider, end).await {
+                            Ok(Some(hash)) => hash,
+                            // The node does not serve the block yet, retried on the next head
+                            Ok(None) => break,
+                            Err(err) => break 'connection err,
+                        };
 
-            let history_filter = Filter::new()
-                .address(contract)
-                .select(start..=end);
+                        let decoded = match historical_logs(&http_provider, contract, next_block, end).await {
+                            Ok(decoded) => decoded,
+                            Err(err @ OMInteropError::Decode(_)) => {
+                                yield Err(err);
+                                return;
+                            }
+                            Err(err) => break 'connection err,
+                        };
 
-            let historical = http_provider.get_logs(&history_filter).await?;
+                        // Logs fetched while the chain reorganized are retried on the next head
+                        match is_canonical(&http_provider, &decoded, end, end_hash).await {
+                            Ok(true) => {}
+                            Ok(false) => break,
+                            Err(err) => break 'connection err,
+                        }
 
-            let mut decoded = historical
-                .into_iter()
-                .map(decode_event)
-                .collect::<Result<Vec<_>, _>>()?;
+                        for log in decoded {
+                            if should_emit(log_position(&log), &mut last_position) {
+                                emitted.push_back(log.clone());
+                                yield Ok(EventUpdate::Confirmed(log));
+                            }
+                        }
 
-            decoded.sort_by_key(|log| {
-                (
-                    log.block_number.unwrap_or(u64::MAX),
-                    log.log_index.unwrap_or(u64::MAX),
-                )
-            });
+                        processed.push_back((end, end_hash));
+                        next_block = end + 1;
+                    }
 
-            for log in decoded {
-                if let Some(position) = log_position(&log) {
-                    last_position = Some(position);
+                    let window_start = latest_block.saturating_sub(REORG_WINDOW);
+                    while processed.len() > 1 && processed[1].0 < window_start {
+                        processed.pop_front();
+                    }
+                    while emitted
+                        .front()
+                        .is_some_and(|log| log.block_number.unwrap_or_default() < window_start)
+                    {
+                        emitted.pop_front();
+                    }
+
+                    match new_blocks.next().await {
+                        Some(header) => latest_block = latest_block.max(header.number),
+                        None => break 'connection OMInteropError::SubscriptionClosed,
+                    }
                 }
-                yield log;
-            }
+            };
 
-            start = end + 1;
+            warn!(
+                %error,
+                next_block,
+                retry_in = ?backoff,
+                "OMInterop event subscription interrupted, resuming after reconnect",
+            );
+            tokio::time::sleep(backoff).await;
+            backoff = backoff.saturating_mul(2).min(MAX_RECONNECT_BACKOFF);
         }
+    }
+    .boxed()
+}
 
-        while let Some(log) = live_stream.next().await {
-            let decoded = decode_event(log)?;
-            if should_emit(log_position(&decoded), &mut last_position) {
-                yield decoded;
-            }
+/// Returns the newest processed block still part of the canonical chain if a reorg replaced
+/// the last processed block, `None` otherwise.
+async fn find_fork_block<P: Provider>(
+    provider: &P,
+    processed: &VecDeque<(u64, B256)>,
+) -> Result<Option<u64>, OMInteropError> {
+    for (position, (block, hash)) in processed.iter().rev().enumerate() {
+        if block_hash(provider, *block).await? == Some(*hash) {
+            return Ok((position > 0).then_some(*block));
         }
     }
-    .boxed()
+
+    match processed.front() {
+        Some((block, _)) => Err(OMInteropError::ReorgTooDeep { block: *block }),
+        None => Ok(None),
+    }
+}
+
+/// Whether `logs` and block `end` with hash `end_hash` are part of the canonical chain.
+async fn is_canonical<P: Provider>(
+    provider: &P,
+    logs: &[OMInteropLog],
+    end: u64,
+    end_hash: B256,
+) -> Result<bool, OMInteropError> {
+    let mut checked = None;
+    for log in logs {
+        let (Some(block), Some(hash)) = (log.block_number, log.block_hash) else {
+            continue;
+        };
+        if checked == Some((block, hash)) {
+            continue;
+        }
+        if block_hash(provider, block).await? != Some(hash) {
+            return Ok(false);
+        }
+        checked = Some((block, hash));
+    }
+
+    Ok(block_hash(provider, end).await? == Some(end_hash))
+}
+
+async fn block_hash<P: Provider>(provider: &P, block: u64) -> Result<Option<B256>, OMInteropError> {
+    let block = provider
+        .get_block_by_number(BlockNumberOrTag::Number(block))
+        .await?;
+    Ok(block.map(|block| block.header.hash))
+}
+
+/// Fetches the OMInterop logs of blocks `start..=end`, ordered by position.
+async fn historical_logs<P: Provider>(
+    provider: &P,
+    contract: Address,
+    start: u64,
+    end: u64,
+) -> Result<Vec<OMInteropLog>, OMInteropError> {
+    let history_filter = Filter::new().address(contract).select(start..=end);
+
+    let historical = provider.get_logs(&history_filter).await?;
+
+    let mut decoded = historical
+        .into_iter()
+        .map(decode_event)
+        .collect::<Result<Vec<_>, _>>()?;
+
+    decoded.sort_by_key(|log| {
+        (
+            log.block_number.unwrap_or(u64::MAX),
+            log.log_index.unwrap_or(u64::MAX),
+        )
+    });
+
+    Ok(decoded)
 }
 
 pub fn decode_event(log: RpcLog) -> Result<OMInteropLog, OMInteropError> {
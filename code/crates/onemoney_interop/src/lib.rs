@@ -1,3 +1,5 @@
 pub mod contract;
+pub mod deploy;
 pub mod error;
 pub mod event;
+pub mod upgrade;
@@ -0,0 +1,410 @@
+//! Upgrade of the OMInterop proxy to a new implementation.
+//!
+//! [`upgrade`] checks that the new implementation reads the current storage of the proxy the
+//! same way before calling `upgradeToAndCall`, then checks again once the upgrade landed. The
+//! state compared is the one read through the contract getters (roles, nonces and token
+//! bindings) together with the raw storage slots of the `om.interop.storage.OMInterop.v1`
+//! layout and of the ownership.
+
+use core::fmt;
+use std::collections::BTreeMap;
+
+use alloy_contract::{CallBuilder, CallDecoder};
+use alloy_primitives::{b256, keccak256, Address, Bytes, B256, U256};
+use alloy_provider::Provider;
+use alloy_rpc_types_eth::state::{StateOverride, StateOverridesBuilder};
+use alloy_sol_types::SolValue;
+use tracing::info;
+
+use crate::contract::OMInterop::{self, OMInteropInstance};
+use crate::deploy::{deploy_contract, send};
+use crate::error::Error;
+
+/// Storage location of the OMInterop layout.
+///
+/// The contract derives it as `keccak256("om.interop.storage.OMInterop.v1")` rather than with
+/// the ERC-7201 formula, so it must not be recomputed from the namespace.
+pub const INTEROP_STORAGE_LOCATION: B256 =
+    b256!("0x54e19e308831a2e4ed17bef827ef5de4fab5fb8e572624b958289b67579e87b8");
+
+/// ERC-7201 storage location of `OwnableUpgradeable`.
+pub const OWNABLE_STORAGE_LOCATION: B256 =
+    b256!("0x9016d09d72d40fdae2fd8ceac6b6234c7706214fd39c1cd1e609a0528c199300");
+
+/// ERC-1967 slot holding the implementation of the proxy.
+pub const IMPLEMENTATION_SLOT: B256 =
+    b256!("0x360894a13ba1a3210667c828492db98dca3e2076cc3735a920a3ca505d382bbc");
+
+/// Number of slots taken by the fields of the OMInterop layout, up to the price oracle.
+const LAYOUT_SLOTS: u64 = 12;
+/// Offsets in the layout of the mappings whose entries are watched.
+const TOKENS_BY_OM_OFFSET: u64 = 3;
+const TOKENS_BY_SIDECHAIN_OFFSET: u64 = 4;
+const LATEST_BB_NONCE_OFFSET: u64 = 5;
+/// Number of slots taken by a `TokenBinding`.
+const TOKEN_BINDING_SLOTS: u64 = 2;
+
+/// Upgrade of the proxy.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Upgrade {
+    /// Deployed implementation, the OMInterop contract of this build is deployed if unset.
+    pub implementation: Option<Address>,
+    /// Call made by the proxy on the new implementation, e.g. a reinitializer.
+    pub call_data: Bytes,
+    /// 1Money tokens whose bindings must be preserved.
+    pub tokens: Vec<Address>,
+    /// 1Money accounts whose latest processed nonce must be preserved.
+    pub accounts: Vec<Address>,
+}
+
+/// State of the proxy read through the getters of its implementation.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct InteropState {
+    pub version: String,
+    pub owner: Address,
+    pub operator: Address,
+    pub relayer: Address,
+    pub latest_inbound_nonce: u64,
+    pub latest_completed_checkpoint: u64,
+    pub tokens: Vec<TokenState>,
+    /// Latest processed `BurnAndBridge` nonce of every watched account.
+    pub processed_nonces: Vec<(Address, u64)>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TokenState {
+    pub om_token: Address,
+    pub sc_token: Address,
+    pub interop_protocol: u8,
+    pub exists: bool,
+    /// 1Money token of the binding of `sc_token`.
+    pub sidechain_om_token: Address,
+}
+
+/// Storage slot of the proxy changed by the upgrade call.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SlotChange {
+    pub slot: U256,
+    pub before: U256,
+    pub after: U256,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UpgradeReport {
+    pub previous_implementation: Address,
+    pub implementation: Address,
+    pub previous_version: String,
+    pub version: String,
+    pub transaction_hash: B256,
+    /// Watched slots changed by a non-empty upgrade call.
+    pub changed_slots: Vec<SlotChange>,
+}
+
+/// Upgrades the OMInterop `proxy` from the `owner` account of `provider`.
+///
+/// Nothing is sent if the new implementation is not a UUPS implementation, reports the same
+/// version or reads another state from the current storage. Once upgraded, the state must be
+/// preserved and, without upgrade call, the watched storage slots unchanged. A mismatch at
+/// that point is returned as [`Error::UpgradeStateMismatch`] naming the previous
+/// implementation to upgrade back to.
+pub async fn upgrade<P: Provider>(
+    provider: &P,
+    owner: Address,
+    proxy: Address,
+    upgrade: &Upgrade,
+) -> Result<UpgradeReport, Error> {
+    let interop = OMInterop::new(proxy, provider);
+    let previous_implementation = implementation_of(provider, proxy).await?;
+
+    let implementation = match upgrade.implementation {
+        Some(implementation) => implementation,
+        None => {
+            deploy_contract(
+                "OMInterop implementation",
+                OMInterop::deploy_builder(provider),
+            )
+            .await?
+            .address
+        }
+    };
+    if implementation == previous_implementation {
+        return Err(Error::SameImplementation(implementation));
+    }
+    let uuid = OMInterop::new(implementation, provider)
+        .proxiableUUID()
+        .call()
+        .await?;
+    if uuid != IMPLEMENTATION_SLOT {
+        return Err(Error::NotUups(implementation));
+    }
+
+    let before = read_state(&interop, None, upgrade).await?;
+    let slots = watched_slots(&before, &upgrade.accounts);
+    let storage_before = storage_snapshot(provider, proxy, &slots).await?;
+
+    // Read the current storage through the new implementation before upgrading.
+    let preview_override = StateOverridesBuilder::default()
+        .with_state_diff(proxy, [(IMPLEMENTATION_SLOT, implementation.into_word())])
+        .build();
+    let preview = read_state(&interop, Some(&preview_override), upgrade).await?;
+    if preview.version == before.version {
+        return Err(Error::VersionUnchanged {
+            implementation,
+            version: preview.version,
+        });
+    }
+    let mismatches = state_mismatches(&before, &preview);
+    if !mismatches.is_empty() {
+        return Err(Error::IncompatibleImplementation {
+            implementation,
+            mismatches,
+        });
+    }
+
+    let call = interop
+        .upgradeToAndCall(implementation, upgrade.call_data.clone())
+        .from(owner);
+    call.call().await?;
+    let transaction_hash = send(call).await?;
+    info!(%previous_implementation, %implementation, %transaction_hash, "Upgraded OMInterop");
+
+    let after = read_state(&interop, None, upgrade).await?;
+    let storage_after = storage_snapshot(provider, proxy, &slots).await?;
+    let changed_slots = changed_slots(&storage_before, &storage_after);
+
+    let mut mismatches = state_mismatches(&before, &after);
+    let current_implementation = implementation_of(provider, proxy).await?;
+    if current_implementation != implementation {
+        mismatches.push(format!(
+            "implementation is {current_implementation} instead of {implementation}"
+        ));
+    }
+    if after.version == before.version {
+        mismatches.push(format!("version is still {}", after.version));
+    }
+    if upgrade.call_data.is_empty() {
+        mismatches.extend(changed_slots.iter().map(ToString::to_string));
+    }
+    if !mismatches.is_empty() {
+        return Err(Error::UpgradeStateMismatch {
+            transaction_hash,
+            previous_implementation,
+            mismatches,
+        });
+    }
+
+    Ok(UpgradeReport {
+        previous_implementation,
+        implementation,
+        previous_version: before.version,
+        version: after.version,
+        transaction_hash,
+        changed_slots,
+    })
+}
+
+/// Returns the implementation the ERC-1967 `proxy` points to.
+pub async fn implementation_of<P: Provider>(
+    provider: &P,
+    proxy: Address,
+) -> Result<Address, Error> {
+    let slot = provider
+        .get_storage_at(proxy, IMPLEMENTATION_SLOT.into())
+        .await?;
+    Ok(Address::from_word(slot.into()))
+}
+
+/// Reads the state of the watched tokens and accounts of `upgrade`, with `state` overriding
+/// the chain state.
+async fn read_state<P: Provider>(
+    interop: &OMInteropInstance<&P>,
+    state: Option<&StateOverride>,
+    upgrade: &Upgrade,
+) -> Result<InteropState, Error> {
+    let mut tokens = Vec::with_capacity(upgrade.tokens.len());
+    for &om_token in &upgrade.tokens {
+        let binding = overridden(interop.getTokenBindingForOm(om_token), state)
+            .call()
+            .await?;
+        let sidechain_binding =
+            overridden(interop.getTokenBindingForSidechain(binding.scToken), state)
+                .call()
+                .await?;
+        tokens.push(TokenState {
+            om_token,
+            sc_token: binding.scToken,
+            interop_protocol: binding.interopProtoId,
+            exists: binding.exists,
+            sidechain_om_token: sidechain_binding.omToken,
+        });
+    }
+
+    let mut processed_nonces = Vec::with_capacity(upgrade.accounts.len());
+    for &account in &upgrade.accounts {
+        let nonce = overridden(interop.getLatestProcessedNonce(account), state)
+            .call()
+            .await?;
+        processed_nonces.push((account, nonce));
+    }
+
+    Ok(InteropState {
+        version: overridden(interop.version(), state).call().await?,
+        owner: overridden(interop.owner(), state).call().await?,
+        operator: overridden(interop.operator(), state).call().await?,
+        relayer: overridden(interop.relayer(), state).call().await?,
+        latest_inbound_nonce: overridden(interop.getLatestInboundNonce(), state)
+            .call()
+            .await?,
+        latest_completed_checkpoint: overridden(interop.getLatestCompletedCheckpoint(), state)
+            .call()
+            .await?,
+        tokens,
+        processed_nonces,
+    })
+}
+
+fn overridden<P: Provider, D: CallDecoder>(
+    call: CallBuilder<P, D>,
+    state: Option<&StateOverride>,
+) -> CallBuilder<P, D> {
+    match state {
+        Some(state) => call.state(state.clone()),
+        None => call,
+    }
+}
+
+/// Describes the differences of `after` from `before`, ignoring the version.
+pub fn state_mismatches(before: &InteropState, after: &InteropState) -> Vec<String> {
+    let mut mismatches = Vec::new();
+    let m = &mut mismatches;
+
+    check(m, "owner", before.owner, after.owner);
+    check(m, "operator", before.operator, after.operator);
+    check(m, "relayer", before.relayer, after.relayer);
+    check(
+        m,
+        "latest inbound nonce",
+        before.latest_inbound_nonce,
+        after.latest_inbound_nonce,
+    );
+    check(
+        m,
+        "latest completed checkpoint",
+        before.latest_completed_checkpoint,
+        after.latest_completed_checkpoint,
+    );
+    for (before, after) in before.tokens.iter().zip(&after.tokens) {
+        let token = before.om_token;
+        check(
+            m,
+            &format!("sidechain token of {token}"),
+            before.sc_token,
+            after.sc_token,
+        );
+        check(
+            m,
+            &format!("interop protocol of {token}"),
+            before.interop_protocol,
+            after.interop_protocol,
+        );
+        check(
+            m,
+            &format!("binding existence of {token}"),
+            before.exists,
+            after.exists,
+        );
+        check(
+            m,
+            &format!("1Money token bound to {}", before.sc_token),
+            before.sidechain_om_token,
+            after.sidechain_om_token,
+        );
+    }
+    for (&(account, before), &(_, after)) in
+        before.processed_nonces.iter().zip(&after.processed_nonces)
+    {
+        check(m, &format!("processed nonce of {account}"), before, after);
+    }
+
+    mismatches
+}
+
+fn check<T: PartialEq + fmt::Display>(
+    mismatches: &mut Vec<String>,
+    field: &str,
+    before: T,
+    after: T,
+) {
+    if before != after {
+        mismatches.push(format!("{field} changed from {before} to {after}"));
+    }
+}
+
+/// Returns the fields of the OMInterop layout, the ownership slot and the mapping entries of
+/// the tokens and accounts of `state`.
+fn watched_slots(state: &InteropState, accounts: &[Address]) -> Vec<U256> {
+    let base = U256::from_be_bytes(INTEROP_STORAGE_LOCATION.0);
+    let mut slots = (0..LAYOUT_SLOTS)
+        .map(|offset| base + U256::from(offset))
+        .collect::<Vec<_>>();
+    slots.push(U256::from_be_bytes(OWNABLE_STORAGE_LOCATION.0));
+
+    let binding_slots = |key: Address, offset: u64| {
+        let first = mapping_slot(key, base + U256::from(offset));
+        (0..TOKEN_BINDING_SLOTS).map(move |slot| first + U256::from(slot))
+    };
+    for token in &state.tokens {
+        slots.extend(binding_slots(token.om_token, TOKENS_BY_OM_OFFSET));
+        if token.exists {
+            slots.extend(binding_slots(token.sc_token, TOKENS_BY_SIDECHAIN_OFFSET));
+        }
+    }
+    slots.extend(
+        accounts
+            .iter()
+            .map(|&account| mapping_slot(account, base + U256::from(LATEST_BB_NONCE_OFFSET))),
+    );
+
+    slots
+}
+
+/// Returns the slot of the entry `key` of the mapping at `slot`.
+fn mapping_slot(key: Address, slot: U256) -> U256 {
+    U256::from_be_bytes(keccak256((key, slot).abi_encode()).0)
+}
+
+async fn storage_snapshot<P: Provider>(
+    provider: &P,
+    proxy: Address,
+    slots: &[U256],
+) -> Result<BTreeMap<U256, U256>, Error> {
+    let mut snapshot = BTreeMap::new();
+    for &slot in slots {
+        snapshot.insert(slot, provider.get_storage_at(proxy, slot).await?);
+    }
+    Ok(snapshot)
+}
+
+fn changed_slots(before: &BTreeMap<U256, U256>, after: &BTreeMap<U256, U256>) -> Vec<SlotChange> {
+    before
+        .iter()
+        .filter_map(|(&slot, &before)| {
+            let after = after.get(&slot).copied().unwrap_or_default();
+            (before != after).then_some(SlotChange {
+                slot,
+                before,
+                after,
+            })
+        })
+        .collect()
+}
+
+impl fmt::Display for SlotChange {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "slot {:#x} changed from {:#x} to {:#x}",
+            self.slot, self.before, self.after
+        )
+    }
+}
@@ -0,0 +1,105 @@
+use alloy_node_bindings::Anvil;
+use alloy_provider::ProviderBuilder;
+use alloy_signer_local::PrivateKeySigner;
+use onemoney_interop::contract::{OMInterop, TxHashMapping};
+use onemoney_interop::deploy::{
+    deploy, Deployment, SidechainToken, TokenDeployment, MANIFEST_VERSION,
+};
+use tracing::info;
+
+#[tokio::test]
+#[test_log::test]
+async fn deploy_hands_roles_over_and_maps_tokens() -> color_eyre::Result<()> {
+    let anvil = Anvil::new().try_spawn()?;
+    let keys = anvil.keys();
+    let addresses = anvil.addresses();
+
+    let deployer_wallet: PrivateKeySigner = keys[0].clone().into();
+    let deployer = deployer_wallet.address();
+    let provider = ProviderBuilder::new()
+        .wallet(deployer_wallet)
+        .connect_http(anvil.endpoint_url());
+
+    let deployment = Deployment {
+        owner: addresses[1],
+        operator: addresses[2],
+        relayer: addresses[3],
+        tx_mapping_relayer: addresses[4],
+        lz_endpoint: None,
+        tokens: vec![TokenDeployment {
+            om_token: addresses[5],
+            sidechain_token: SidechainToken::Existing(addresses[6]),
+            interop_protocol: 1,
+        }],
+    };
+
+    let manifest = deploy(&provider, deployer, &deployment).await?;
+    info!(?manifest, "deployed interop stack");
+
+    assert_eq!(manifest.version, MANIFEST_VERSION);
+    assert_eq!(manifest.chain_id, anvil.chain_id());
+    assert_eq!(manifest.deployer, deployer);
+    assert_eq!(manifest.interop_version, "v1.0.0");
+    assert!(manifest.interop.block_number > manifest.interop_implementation.block_number);
+    assert_eq!(manifest.tokens.len(), 1);
+    assert_eq!(manifest.tokens[0].sc_token, addresses[6]);
+    assert!(manifest.tokens[0].oft.is_none());
+
+    let interop = OMInterop::new(manifest.interop.address, &provider);
+    assert_eq!(interop.owner().call().await?, deployment.owner);
+    assert_eq!(interop.operator().call().await?, deployment.operator);
+    assert_eq!(interop.relayer().call().await?, deployment.relayer);
+    let binding = interop.getTokenBindingForOm(addresses[5]).call().await?;
+    assert!(binding.exists);
+    assert_eq!(binding.scToken, addresses[6]);
+    assert_eq!(binding.interopProtoId, 1);
+
+    let tx_mapping = TxHashMapping::new(manifest.tx_hash_mapping.address, &provider);
+    assert_eq!(tx_mapping.owner().call().await?, deployment.owner);
+    assert_eq!(
+        tx_mapping.relayer().call().await?,
+        deployment.tx_mapping_relayer
+    );
+
+    Ok(())
+}
+
+#[tokio::test]
+#[test_log::test]
+async fn deploy_requires_lz_endpoint_for_oft_tokens() -> color_eyre::Result<()> {
+    let anvil = Anvil::new().try_spawn()?;
+    let keys = anvil.keys();
+    let addresses = anvil.addresses();
+
+    let deployer_wallet: PrivateKeySigner = keys[0].clone().into();
+    let deployer = deployer_wallet.address();
+    let provider = ProviderBuilder::new()
+        .wallet(deployer_wallet)
+        .connect_http(anvil.endpoint_url());
+
+    let deployment = Deployment {
+        owner: deployer,
+        operator: deployer,
+        relayer: addresses[1],
+        tx_mapping_relayer: addresses[1],
+        lz_endpoint: None,
+        tokens: vec![TokenDeployment {
+            om_token: addresses[2],
+            sidechain_token: SidechainToken::Oft {
+                name: "Bridged Token".to_owned(),
+                symbol: "BTK".to_owned(),
+            },
+            interop_protocol: 0,
+        }],
+    };
+
+    let error = deploy(&provider, deployer, &deployment)
+        .await
+        .expect_err("OFT token deployed without LayerZero endpoint");
+    assert!(matches!(
+        error,
+        onemoney_interop::error::Error::MissingLzEndpoint
+    ));
+
+    Ok(())
+}
@@ -1,27 +1,112 @@
+use core::net::SocketAddr;
+use core::sync::atomic::{AtomicBool, Ordering};
 use core::time::Duration;
+use std::sync::{Arc, Mutex};
 
 use alloy_node_bindings::Anvil;
 use alloy_primitives::{keccak256, Address, Bytes, U256};
-use alloy_provider::ProviderBuilder;
+use alloy_provider::{Provider, ProviderBuilder};
 use alloy_signer_local::PrivateKeySigner;
 use futures::StreamExt;
 use onemoney_interop::contract::deploy_uups_like;
 use onemoney_interop::contract::OMInterop::{self, OMInteropEvents};
 use onemoney_interop::error::Error as OMInteropError;
-use onemoney_interop::event::{event_stream, OMInteropLog};
+use onemoney_interop::event::{event_stream, EventUpdate};
+use tokio::net::{TcpListener, TcpStream};
+use tokio::task::AbortHandle;
 use tracing::{debug, info};
+use url::Url;
 
-async fn next_event<T>(stream: &mut T) -> OMInteropEvents
+async fn next_update<T>(stream: &mut T) -> EventUpdate
 where
-    T: futures::Stream<Item = Result<OMInteropLog, OMInteropError>> + Unpin,
+    T: futures::Stream<Item = Result<EventUpdate, OMInteropError>> + Unpin,
 {
     tokio::time::timeout(Duration::from_secs(10), stream.next())
         .await
         .expect("timed out waiting for event")
         .expect("event stream ended unexpectedly")
         .expect("error in event stream")
-        .inner
-        .data
+}
+
+async fn next_event<T>(stream: &mut T) -> OMInteropEvents
+where
+    T: futures::Stream<Item = Result<EventUpdate, OMInteropError>> + Unpin,
+{
+    match next_update(stream).await {
+        EventUpdate::Confirmed(log) => log.inner.data,
+        EventUpdate::Retracted(log) => panic!("unexpected retracted event: {log:?}"),
+    }
+}
+
+async fn mine_blocks<P: Provider>(provider: &P, blocks: u64) -> color_eyre::Result<()> {
+    provider
+        .raw_request::<_, ()>("anvil_mine".into(), (U256::from(blocks),))
+        .await?;
+    Ok(())
+}
+
+/// TCP proxy in front of the node websocket whose connections can be cut to simulate outages.
+struct Proxy {
+    url: Url,
+    down: Arc<AtomicBool>,
+    connections: Arc<Mutex<Vec<AbortHandle>>>,
+}
+
+impl Proxy {
+    async fn spawn(target: SocketAddr) -> color_eyre::Result<Self> {
+        let listener = TcpListener::bind("127.0.0.1:0").await?;
+        let url = format!("ws://{}", listener.local_addr()?).parse()?;
+        let down = Arc::new(AtomicBool::new(false));
+        let connections = Arc::new(Mutex::new(Vec::new()));
+
+        tokio::spawn({
+            let down = down.clone();
+            let connections = connections.clone();
+            async move {
+                while let Ok((mut inbound, _)) = listener.accept().await {
+                    if down.load(Ordering::SeqCst) {
+                        continue;
+                    }
+                    let connection = tokio::spawn(async move {
+                        let mut outbound = TcpStream::connect(target).await?;
+                        tokio::io::copy_bidirectional(&mut inbound, &mut outbound).await
+                    });
+                    connections
+                        .lock()
+                        .expect("connections lock")
+                        .push(connection.abort_handle());
+                }
+            }
+        });
+
+        Ok(Self {
+            url,
+            down,
+            connections,
+        })
+    }
+
+    /// Drops every open connection and refuses new ones until [`Proxy::restore`].
+    fn cut(&self) {
+        self.down.store(true, Ordering::SeqCst);
+        for connection in self.connections.lock().expect("connections lock").drain(..) {
+            connection.abort();
+        }
+    }
+
+    fn restore(&self) {
+        self.down.store(false, Ordering::SeqCst);
+    }
+}
+
+/// Consumes the events already available on the stream.
+async fn drain_events<T>(stream: &mut T)
+where
+    T: futures::Stream<Item = Result<EventUpdate, OMInteropError>> + Unpin,
+{
+    while let Ok(Some(event)) = tokio::time::timeout(Duration::from_secs(1), stream.next()).await {
+        event.expect("error in event stream");
+    }
 }
 
 #[tokio::test]
@@ -84,7 +169,7 @@ async fn event_stream_captures_ominterop_events() -> color_eyre::Result<()> {
         .await?;
     debug!("mapTokenAddresses transaction confirmed");
 
-    let mut stream = event_stream(http_endpoint, ws_endpoint, contract_addr, 0).await;
+    let mut stream = event_stream(http_endpoint, ws_endpoint, contract_addr, 0, 0).await;
     debug!("subscribed to OMInterop event stream");
 
     let upgraded_contract = next_event(&mut stream).await;
@@ -294,3 +379,200 @@ async fn event_stream_captures_ominterop_events() -> color_eyre::Result<()> {
 
     Ok(())
 }
+
+#[tokio::test]
+#[test_log::test]
+async fn event_stream_backfills_events_missed_while_disconnected() -> color_eyre::Result<()> {
+    let anvil = Anvil::new().try_spawn()?;
+    let http_endpoint = anvil.endpoint_url();
+    let proxy = Proxy::spawn(SocketAddr::from(([127, 0, 0, 1], anvil.port()))).await?;
+
+    let keys = anvil.keys();
+    let addresses = anvil.addresses();
+    let owner_wallet: PrivateKeySigner = keys[0].clone().into();
+    let owner_addr = owner_wallet.address();
+    let operator_addr = addresses[1];
+    let relayer_addr = addresses[2];
+    let first_operator = addresses[3];
+    let second_operator = addresses[4];
+    let new_relayer = addresses[5];
+
+    let owner_provider = ProviderBuilder::new()
+        .wallet(owner_wallet)
+        .connect_http(http_endpoint.clone());
+    let contract = deploy_uups_like(&owner_provider, owner_addr, operator_addr, relayer_addr)
+        .await?
+        .1;
+    let contract_addr = *contract.address();
+    let owner_contract = OMInterop::new(contract_addr, owner_provider.clone());
+
+    let mut stream = event_stream(http_endpoint, proxy.url.clone(), contract_addr, 0, 0).await;
+    drain_events(&mut stream).await;
+
+    // Events emitted while the websocket is down are backfilled after reconnecting
+    proxy.cut();
+    for operator in [first_operator, second_operator] {
+        owner_contract
+            .setOperator(operator)
+            .send()
+            .await?
+            .get_receipt()
+            .await?;
+    }
+    tokio::time::sleep(Duration::from_secs(2)).await;
+    proxy.restore();
+
+    // The new block also wakes up a subscription silently re-established by the transport
+    owner_contract
+        .setRelayer(new_relayer)
+        .send()
+        .await?
+        .get_receipt()
+        .await?;
+
+    for operator in [first_operator, second_operator] {
+        match next_event(&mut stream).await {
+            OMInterop::OMInteropEvents::OperatorUpdated(event) => {
+                assert_eq!(event.newOperator, operator);
+            }
+            e => panic!("unexpected event after reconnect: {e:?}"),
+        }
+    }
+    match next_event(&mut stream).await {
+        OMInterop::OMInteropEvents::RelayerUpdated(event) => {
+            assert_eq!(event.newRelayer, new_relayer);
+        }
+        e => panic!("unexpected live event: {e:?}"),
+    }
+
+    assert!(
+        tokio::time::timeout(Duration::from_millis(500), stream.next())
+            .await
+            .ok()
+            .flatten()
+            .is_none(),
+        "expected no duplicated events"
+    );
+
+    Ok(())
+}
+
+#[tokio::test]
+#[test_log::test]
+async fn event_stream_waits_for_confirmations() -> color_eyre::Result<()> {
+    let anvil = Anvil::new().try_spawn()?;
+    let http_endpoint = anvil.endpoint_url();
+
+    let keys = anvil.keys();
+    let addresses = anvil.addresses();
+    let owner_wallet: PrivateKeySigner = keys[0].clone().into();
+    let owner_addr = owner_wallet.address();
+    let new_operator = addresses[3];
+
+    let owner_provider = ProviderBuilder::new()
+        .wallet(owner_wallet)
+        .connect_http(http_endpoint.clone());
+    let contract = deploy_uups_like(&owner_provider, owner_addr, addresses[1], addresses[2])
+        .await?
+        .1;
+    let contract_addr = *contract.address();
+    let owner_contract = OMInterop::new(contract_addr, owner_provider.clone());
+
+    let mut stream =
+        event_stream(http_endpoint, anvil.ws_endpoint_url(), contract_addr, 0, 2).await;
+    mine_blocks(&owner_provider, 2).await?;
+    drain_events(&mut stream).await;
+
+    owner_contract
+        .setOperator(new_operator)
+        .send()
+        .await?
+        .get_receipt()
+        .await?;
+    mine_blocks(&owner_provider, 1).await?;
+    assert!(
+        tokio::time::timeout(Duration::from_secs(1), stream.next())
+            .await
+            .is_err(),
+        "expected no event before the confirmation depth is reached"
+    );
+
+    mine_blocks(&owner_provider, 1).await?;
+    match next_event(&mut stream).await {
+        OMInterop::OMInteropEvents::OperatorUpdated(event) => {
+            assert_eq!(event.newOperator, new_operator);
+        }
+        e => panic!("unexpected confirmed event: {e:?}"),
+    }
+
+    Ok(())
+}
+
+#[tokio::test]
+#[test_log::test]
+async fn event_stream_retracts_events_removed_by_reorg() -> color_eyre::Result<()> {
+    let anvil = Anvil::new().try_spawn()?;
+    let http_endpoint = anvil.endpoint_url();
+
+    let keys = anvil.keys();
+    let addresses = anvil.addresses();
+    let owner_wallet: PrivateKeySigner = keys[0].clone().into();
+    let owner_addr = owner_wallet.address();
+    let new_operator = addresses[3];
+    let new_relayer = addresses[4];
+
+    let owner_provider = ProviderBuilder::new()
+        .wallet(owner_wallet)
+        .connect_http(http_endpoint.clone());
+    let contract = deploy_uups_like(&owner_provider, owner_addr, addresses[1], addresses[2])
+        .await?
+        .1;
+    let contract_addr = *contract.address();
+    let owner_contract = OMInterop::new(contract_addr, owner_provider.clone());
+
+    let mut stream =
+        event_stream(http_endpoint, anvil.ws_endpoint_url(), contract_addr, 0, 0).await;
+    drain_events(&mut stream).await;
+
+    let snapshot: U256 = owner_provider
+        .raw_request("evm_snapshot".into(), ())
+        .await?;
+    owner_contract
+        .setOperator(new_operator)
+        .send()
+        .await?
+        .get_receipt()
+        .await?;
+    let removed = match next_update(&mut stream).await {
+        EventUpdate::Confirmed(log) => log,
+        EventUpdate::Retracted(log) => panic!("unexpected retracted event: {log:?}"),
+    };
+
+    // Replace the block of the confirmed event by a block with another event
+    let reverted: bool = owner_provider
+        .raw_request("evm_revert".into(), (snapshot,))
+        .await?;
+    assert!(reverted);
+    owner_contract
+        .setRelayer(new_relayer)
+        .send()
+        .await?
+        .get_receipt()
+        .await?;
+
+    match next_update(&mut stream).await {
+        EventUpdate::Retracted(log) => {
+            assert_eq!(log.transaction_hash, removed.transaction_hash);
+            assert_eq!(log.block_number, removed.block_number);
+        }
+        EventUpdate::Confirmed(log) => panic!("expected a retraction, got {log:?}"),
+    }
+    match next_event(&mut stream).await {
+        OMInterop::OMInteropEvents::RelayerUpdated(event) => {
+            assert_eq!(event.newRelayer, new_relayer);
+        }
+        e => panic!("unexpected event after reorg: {e:?}"),
+    }
+
+    Ok(())
+}
@@ -0,0 +1,181 @@
+use alloy_node_bindings::Anvil;
+use alloy_primitives::{keccak256, Address, Bytes, B256, U256};
+use alloy_provider::ProviderBuilder;
+use alloy_signer_local::PrivateKeySigner;
+use alloy_sol_types::sol;
+use onemoney_interop::contract::OMInterop;
+use onemoney_interop::deploy::{deploy, Deployment, SidechainToken, TokenDeployment};
+use onemoney_interop::error::Error as OMInteropError;
+use onemoney_interop::upgrade::{
+    implementation_of, upgrade, Upgrade, IMPLEMENTATION_SLOT, INTEROP_STORAGE_LOCATION,
+    OWNABLE_STORAGE_LOCATION,
+};
+use tracing::info;
+
+sol!(
+    #[sol(rpc, abi)]
+    OMInteropV2,
+    concat!(
+        env!("CARGO_MANIFEST_DIR"),
+        "/../../solidity/out/OMInteropV2.sol/OMInteropV2.json"
+    )
+);
+
+#[test]
+fn storage_locations_match_their_derivation() {
+    assert_eq!(
+        INTEROP_STORAGE_LOCATION,
+        keccak256("om.interop.storage.OMInterop.v1")
+    );
+    assert_eq!(
+        U256::from_be_bytes(IMPLEMENTATION_SLOT.0),
+        U256::from_be_bytes(keccak256("eip1967.proxy.implementation").0) - U256::from(1)
+    );
+    let ownable = U256::from_be_bytes(keccak256("openzeppelin.storage.Ownable").0) - U256::from(1);
+    let ownable = keccak256(B256::from(ownable));
+    assert_eq!(
+        U256::from_be_bytes(OWNABLE_STORAGE_LOCATION.0),
+        U256::from_be_bytes(ownable.0) & !U256::from(0xff)
+    );
+}
+
+#[tokio::test]
+#[test_log::test]
+async fn upgrade_to_v2_preserves_state() -> color_eyre::Result<()> {
+    let anvil = Anvil::new().try_spawn()?;
+    let addresses = anvil.addresses();
+
+    let owner_wallet: PrivateKeySigner = anvil.keys()[0].clone().into();
+    let owner = owner_wallet.address();
+    let provider = ProviderBuilder::new()
+        .wallet(owner_wallet)
+        .connect_http(anvil.endpoint_url());
+
+    let om_token = addresses[1];
+    let user = addresses[2];
+    let manifest = deploy(
+        &provider,
+        owner,
+        &Deployment {
+            owner,
+            operator: owner,
+            relayer: owner,
+            tx_mapping_relayer: owner,
+            lz_endpoint: None,
+            tokens: vec![TokenDeployment {
+                om_token,
+                sidechain_token: SidechainToken::Existing(addresses[3]),
+                interop_protocol: 1,
+            }],
+        },
+    )
+    .await?;
+    let proxy = manifest.interop.address;
+
+    OMInterop::new(proxy, &provider)
+        .bridgeTo(
+            user,
+            0,
+            addresses[4],
+            U256::from(10u64),
+            1,
+            U256::from(2u64),
+            om_token,
+            3,
+            Bytes::new(),
+            keccak256("burnandbridgeTxHash"),
+        )
+        .send()
+        .await?
+        .get_receipt()
+        .await?;
+
+    let v2 = OMInteropV2::deploy(&provider).await?;
+    let report = upgrade(
+        &provider,
+        owner,
+        proxy,
+        &Upgrade {
+            implementation: Some(*v2.address()),
+            call_data: Bytes::new(),
+            tokens: vec![om_token],
+            accounts: vec![user],
+        },
+    )
+    .await?;
+    info!(?report, "upgraded OMInterop");
+
+    assert_eq!(
+        report.previous_implementation,
+        manifest.interop_implementation.address
+    );
+    assert_eq!(report.implementation, *v2.address());
+    assert_eq!(report.previous_version, "v1.0.0");
+    assert_eq!(report.version, "v2.0.0");
+    assert!(report.changed_slots.is_empty());
+    assert_eq!(implementation_of(&provider, proxy).await?, *v2.address());
+
+    let interop = OMInterop::new(proxy, &provider);
+    assert_eq!(interop.getLatestInboundNonce().call().await?, 1);
+    assert_eq!(interop.getLatestProcessedNonce(user).call().await?, 1);
+
+    Ok(())
+}
+
+#[tokio::test]
+#[test_log::test]
+async fn upgrade_rejects_implementation_with_same_version() -> color_eyre::Result<()> {
+    let anvil = Anvil::new().try_spawn()?;
+
+    let owner_wallet: PrivateKeySigner = anvil.keys()[0].clone().into();
+    let owner = owner_wallet.address();
+    let provider = ProviderBuilder::new()
+        .wallet(owner_wallet)
+        .connect_http(anvil.endpoint_url());
+
+    let manifest = deploy(
+        &provider,
+        owner,
+        &Deployment {
+            owner,
+            operator: owner,
+            relayer: anvil.addresses()[1],
+            tx_mapping_relayer: anvil.addresses()[1],
+            lz_endpoint: None,
+            tokens: Vec::new(),
+        },
+    )
+    .await?;
+    let proxy = manifest.interop.address;
+
+    let error = upgrade(&provider, owner, proxy, &Upgrade::default())
+        .await
+        .expect_err("upgrade to an implementation of the same version");
+    assert!(matches!(
+        error,
+        OMInteropError::VersionUnchanged { ref version, .. } if version == "v1.0.0"
+    ));
+    assert_eq!(
+        implementation_of(&provider, proxy).await?,
+        manifest.interop_implementation.address
+    );
+
+    let error = upgrade(
+        &provider,
+        owner,
+        proxy,
+        &Upgrade {
+            implementation: Some(Address::ZERO),
+            ..Upgrade::default()
+        },
+    )
+    .await
+    .expect_err("upgrade to an account without code");
+    info!(%error, "rejected implementation without code");
+    assert_eq!(
+        implementation_of(&provider, proxy).await?,
+        manifest.interop_implementation.address
+    );
+
+    Ok(())
+}
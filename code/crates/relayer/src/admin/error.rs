@@ -0,0 +1,79 @@
+use alloy_primitives::B256;
+use onemoney_interop::contract::OMInterop::OMInteropErrors;
+use thiserror::Error;
+
+#[derive(Debug, Error)]
+pub enum Error {
+    #[error(
+        "No admin key configured, set the admin private key, key file or address on the remote signer"
+    )]
+    MissingKey,
+    #[error("Only one of the admin private key, key file and address may be configured")]
+    ConflictingKeys,
+    #[error("The admin address requires a remote signer")]
+    MissingRemoteSigner,
+    #[error(transparent)]
+    Signer(#[from] crate::signer::error::Error),
+    #[error("Contract call failed: {0}")]
+    Contract(#[from] alloy_contract::Error),
+    #[error("Pending transaction failed: {0}")]
+    PendingTransaction(#[from] alloy_provider::PendingTransactionError),
+    #[error("OMInterop reverted: {}", describe_revert(.0))]
+    Reverted(OMInteropErrors),
+    #[error("OMInterop reverted: {0}")]
+    RevertedWithReason(String),
+    #[error("Transaction `{0}` reverted")]
+    TransactionReverted(B256),
+    #[error("Upgrade failed: {0}")]
+    Upgrade(onemoney_interop::error::Error),
+}
+
+/// Describes the revert reason `error` of the interop contract.
+fn describe_revert(error: &OMInteropErrors) -> String {
+    match error {
+        OMInteropErrors::Unauthorized(_) => {
+            "the sender is not the operator or the relayer of the contract".to_owned()
+        }
+        OMInteropErrors::OwnableUnauthorizedAccount(e) => {
+            format!("`{}` is not the owner of the contract", e.account)
+        }
+        OMInteropErrors::InvalidAddress(_) => "the zero address is not allowed".to_owned(),
+        OMInteropErrors::InvalidAmount(_) => "the amount must be positive".to_owned(),
+        OMInteropErrors::UnknownToken(e) => format!("token `{}` is not mapped", e.token),
+        OMInteropErrors::InvalidNonce(e) => format!(
+            "nonce {} does not follow the latest processed nonce, expected {}",
+            e.provided, e.expected
+        ),
+        OMInteropErrors::InboundNonceOverflow(_) => "the inbound nonce overflowed".to_owned(),
+        OMInteropErrors::InboundNonceUnavailable(_) => {
+            "no inbound nonce was recorded yet".to_owned()
+        }
+        OMInteropErrors::CheckpointAlreadyCompleted(e) => {
+            format!("checkpoint {} is already completed", e.checkpointId)
+        }
+        OMInteropErrors::CheckpointCompletedAndPruned(e) => {
+            format!("checkpoint {} is completed and pruned", e.checkpointId)
+        }
+        OMInteropErrors::CheckpointCompleted(e) => {
+            format!("checkpoint {} is completed", e.checkpointId)
+        }
+        OMInteropErrors::CheckpointAlreadyRegistered(e) => {
+            format!("checkpoint {} is already registered", e.checkpointId)
+        }
+        OMInteropErrors::InvalidChainId(_) => {
+            "the destination chain id is not supported".to_owned()
+        }
+        OMInteropErrors::UnknownInteropProto(e) => {
+            format!("unknown interop protocol {}", e.interopProtoId)
+        }
+        OMInteropErrors::RateLimitExceeded(_) => {
+            "the rate limit of the token is exceeded".to_owned()
+        }
+        OMInteropErrors::EscrowFeeTooLow(e) => format!(
+            "escrow fee {} is below the required {}",
+            e.provided, e.required
+        ),
+        OMInteropErrors::InvalidBridgeData(_) => "invalid bridge data".to_owned(),
+        other => format!("{other:?}"),
+    }
+}
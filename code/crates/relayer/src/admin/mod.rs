@@ -0,0 +1,467 @@
+//! Administration of the interop contract by its owner and operator.
+//!
+//! Writes are signed by the admin account, which is distinct from the relayer accounts and
+//! only loaded by the `admin` subcommands. Reverts are decoded from the contract errors into
+//! readable messages.
+
+use core::fmt;
+use core::time::Duration;
+use std::path::PathBuf;
+
+use alloy_contract::{CallBuilder, CallDecoder};
+use alloy_primitives::{Address, Bytes, B256, U256};
+use alloy_provider::{Provider, ProviderBuilder};
+use alloy_signer_local::PrivateKeySigner;
+use alloy_sol_types::Revert;
+use onemoney_interop::contract::OMInterop::{self, OMInteropErrors};
+use onemoney_interop::error::Error as InteropError;
+use onemoney_interop::upgrade::{upgrade, Upgrade, UpgradeReport};
+use url::Url;
+
+pub mod error;
+
+use error::Error as AdminError;
+
+use crate::config::Config;
+use crate::signer::remote::RemoteSigner;
+use crate::signer::{read_key_file, RelayerSigner};
+
+/// Account of the owner or operator of the interop contract signing the `admin` writes, only
+/// one of the key, key file and address may be set
+#[derive(clap::Args, Clone, Debug, Default)]
+pub struct AdminSignerConfig {
+    /// Private key of the owner or operator account, prefer a key file
+    #[arg(long, env = "ADMIN_PRIVATE_KEY", hide_env_values = true)]
+    pub admin_private_key: Option<PrivateKeySigner>,
+    /// Path of a file holding the hex encoded private key of the owner or operator account
+    #[arg(long, env = "ADMIN_KEY_FILE")]
+    pub admin_key_file: Option<PathBuf>,
+    /// Address on the remote signer of the owner or operator account
+    #[arg(long, env = "ADMIN_ADDRESS")]
+    pub admin_address: Option<Address>,
+}
+
+impl AdminSignerConfig {
+    /// Loads the admin signer, from the remote signer at `remote_signer_url` if only its
+    /// address is set.
+    pub fn load(&self, remote_signer_url: Option<&Url>) -> Result<RelayerSigner, AdminError> {
+        match (
+            &self.admin_private_key,
+            &self.admin_key_file,
+            self.admin_address,
+        ) {
+            (Some(signer), None, None) => Ok(RelayerSigner::Local(signer.clone())),
+            (None, Some(path), None) => Ok(RelayerSigner::Local(read_key_file(path)?)),
+            (None, None, Some(address)) => {
+                let url = remote_signer_url.ok_or(AdminError::MissingRemoteSigner)?;
+                Ok(RelayerSigner::Remote(RemoteSigner::new(
+                    url.clone(),
+                    address,
+                )))
+            }
+            (None, None, None) => Err(AdminError::MissingKey),
+            _ => Err(AdminError::ConflictingKeys),
+        }
+    }
+}
+
+/// Cross-chain protocol bridging a token mapping.
+#[derive(clap::ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum InteropProtocol {
+    LayerZero,
+    /// Test protocol, not available in production.
+    Mock,
+}
+
+impl InteropProtocol {
+    /// Identifier of the protocol in the `InteropProtocol` enum of the contract.
+    pub const fn id(self) -> u8 {
+        match self {
+            Self::LayerZero => 0,
+            Self::Mock => 1,
+        }
+    }
+
+    pub const fn from_id(id: u8) -> Option<Self> {
+        match id {
+            0 => Some(Self::LayerZero),
+            1 => Some(Self::Mock),
+            _ => None,
+        }
+    }
+
+    const fn as_str(self) -> &'static str {
+        match self {
+            Self::LayerZero => "LayerZero",
+            Self::Mock => "Mock",
+        }
+    }
+}
+
+#[derive(clap::Subcommand, Clone, Debug)]
+pub enum AdminCommand {
+    /// Map a 1Money token to its sidechain token, as the operator
+    MapTokenAddresses {
+        /// Address of the token on 1Money
+        #[arg(long)]
+        om_token: Address,
+        /// Address of the token on the sidechain
+        #[arg(long)]
+        sc_token: Address,
+        /// Protocol bridging the token
+        #[arg(long, value_enum)]
+        protocol: InteropProtocol,
+    },
+    /// Set the rate limit of the transfers of a 1Money token, as the operator
+    SetRateLimit {
+        /// Address of the token on 1Money
+        #[arg(long)]
+        token: Address,
+        /// Amount which may be transferred per window
+        #[arg(long)]
+        limit: U256,
+        /// Window of the rate limit (human-friendly, e.g. 1h), `0s` removes the rate limit
+        #[arg(long, value_parser = humantime::parse_duration)]
+        window: Duration,
+    },
+    /// Set the operator account, as the owner
+    SetOperator { operator: Address },
+    /// Set the relayer account, as the owner
+    SetRelayer { relayer: Address },
+    /// Set the price oracle contract, as the owner
+    SetPriceOracle { price_oracle: Address },
+    /// Upgrade the interop contract to a new implementation preserving its state, as the owner
+    Upgrade {
+        /// Deployed implementation, the OMInterop contract of this build is deployed otherwise
+        #[arg(long)]
+        implementation: Option<Address>,
+        /// Call made by the proxy on the new implementation, e.g. a reinitializer
+        #[arg(long, default_value = "0x")]
+        call_data: Bytes,
+        /// 1Money token whose binding must be preserved, may be repeated
+        #[arg(long = "token")]
+        tokens: Vec<Address>,
+        /// 1Money account whose latest processed nonce must be preserved, may be repeated
+        #[arg(long = "account")]
+        accounts: Vec<Address>,
+    },
+    /// Show the sidechain token mapped to a 1Money token
+    TokenBinding { om_token: Address },
+    /// Show how many transfers of a checkpoint were certified and completed
+    CheckpointTally { checkpoint_id: u64 },
+    /// Show the latest `BurnAndBridge` nonce processed for a 1Money account
+    LatestProcessedNonce { account: Address },
+}
+
+/// Result of an `admin` subcommand.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Outcome {
+    Transaction {
+        hash: B256,
+        block_number: Option<u64>,
+    },
+    TokenBinding {
+        om_token: Address,
+        sc_token: Address,
+        protocol_id: u8,
+        exists: bool,
+    },
+    CheckpointTally {
+        checkpoint_id: u64,
+        certified: u32,
+        completed: u32,
+    },
+    LatestProcessedNonce {
+        account: Address,
+        nonce: u64,
+    },
+    Upgraded(UpgradeReport),
+}
+
+/// Runs the admin `command` against the interop contract of `config`.
+///
+/// Writes are signed by the account of `signer`, only loaded for writes, and wait for their
+/// receipt.
+pub async fn run_admin_command(
+    config: &Config,
+    signer: &AdminSignerConfig,
+    command: AdminCommand,
+) -> Result<Outcome, AdminError> {
+    let provider = ProviderBuilder::new().connect_http(config.side_chain_http_url.clone());
+    let reader = OMInterop::new(config.interop_contract_address, &provider);
+    let wallet = || -> Result<_, AdminError> {
+        let signer = signer.load(config.signer.remote_signer_url.as_ref())?;
+        let provider = ProviderBuilder::new()
+            .wallet(signer.wallet())
+            .connect_http(config.side_chain_http_url.clone());
+        Ok((signer.address(), provider))
+    };
+    let writer = || -> Result<_, AdminError> {
+        let (_, provider) = wallet()?;
+        Ok(OMInterop::new(config.interop_contract_address, provider))
+    };
+
+    match command {
+        AdminCommand::MapTokenAddresses {
+            om_token,
+            sc_token,
+            protocol,
+        } => {
+            let contract = writer()?;
+            send(contract.mapTokenAddresses(om_token, sc_token, protocol.id())).await
+        }
+        AdminCommand::SetRateLimit {
+            token,
+            limit,
+            window,
+        } => {
+            let contract = writer()?;
+            send(contract.setRateLimit(token, limit, U256::from(window.as_secs()))).await
+        }
+        AdminCommand::SetOperator { operator } => {
+            let contract = writer()?;
+            send(contract.setOperator(operator)).await
+        }
+        AdminCommand::SetRelayer { relayer } => {
+            let contract = writer()?;
+            send(contract.setRelayer(relayer)).await
+        }
+        AdminCommand::SetPriceOracle { price_oracle } => {
+            let contract = writer()?;
+            send(contract.setPriceOracle(price_oracle)).await
+        }
+        AdminCommand::Upgrade {
+            implementation,
+            call_data,
+            tokens,
+            accounts,
+        } => {
+            let (owner, provider) = wallet()?;
+            let upgrade_request = Upgrade {
+                implementation,
+                call_data,
+                tokens,
+                accounts,
+            };
+            upgrade(
+                &provider,
+                owner,
+                config.interop_contract_address,
+                &upgrade_request,
+            )
+            .await
+            .map(Outcome::Upgraded)
+            .map_err(|e| match e {
+                InteropError::Contract(e) => decode_revert(e),
+                e => AdminError::Upgrade(e),
+            })
+        }
+        AdminCommand::TokenBinding { om_token } => {
+            let binding = reader
+                .getTokenBindingForOm(om_token)
+                .call()
+                .await
+                .map_err(decode_revert)?;
+            Ok(Outcome::TokenBinding {
+                om_token,
+                sc_token: binding.scToken,
+                protocol_id: binding.interopProtoId,
+                exists: binding.exists,
+            })
+        }
+        AdminCommand::CheckpointTally { checkpoint_id } => {
+            let tally = reader
+                .getCheckpointTally(checkpoint_id)
+                .call()
+                .await
+                .map_err(decode_revert)?;
+            Ok(Outcome::CheckpointTally {
+                checkpoint_id,
+                certified: tally.certified,
+                completed: tally.completed,
+            })
+        }
+        AdminCommand::LatestProcessedNonce { account } => {
+            let nonce = reader
+                .getLatestProcessedNonce(account)
+                .call()
+                .await
+                .map_err(decode_revert)?;
+            Ok(Outcome::LatestProcessedNonce { account, nonce })
+        }
+    }
+}
+
+/// Submits `call` and waits for its receipt.
+async fn send<P: Provider, D: CallDecoder>(call: CallBuilder<P, D>) -> Result<Outcome, AdminError> {
+    let receipt = call
+        .send()
+        .await
+        .map_err(decode_revert)?
+        .get_receipt()
+        .await?;
+    if !receipt.status() {
+        return Err(AdminError::TransactionReverted(receipt.transaction_hash));
+    }
+
+    Ok(Outcome::Transaction {
+        hash: receipt.transaction_hash,
+        block_number: receipt.block_number,
+    })
+}
+
+/// Decodes the revert reason of a failed call, either a contract error or a `require` message.
+fn decode_revert(e: alloy_contract::Error) -> AdminError {
+    if let Some(revert) = e.as_decoded_error::<Revert>() {
+        return AdminError::RevertedWithReason(revert.reason);
+    }
+    e.try_decode_into_interface_error::<OMInteropErrors>()
+        .map_or_else(AdminError::from, AdminError::Reverted)
+}
+
+impl fmt::Display for Outcome {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Transaction { hash, block_number } => {
+                write!(f, "Transaction {hash} confirmed")?;
+                if let Some(block_number) = block_number {
+                    write!(f, " in block {block_number}")?;
+                }
+                Ok(())
+            }
+            Self::TokenBinding {
+                om_token,
+                exists: false,
+                ..
+            } => write!(f, "{om_token} is not mapped"),
+            Self::TokenBinding {
+                om_token,
+                sc_token,
+                protocol_id,
+                exists: true,
+            } => {
+                write!(f, "{om_token} is mapped to sidechain token {sc_token} over ")?;
+                match InteropProtocol::from_id(*protocol_id) {
+                    Some(protocol) => f.write_str(protocol.as_str()),
+                    None => write!(f, "unknown protocol {protocol_id}"),
+                }
+            }
+            Self::CheckpointTally {
+                checkpoint_id,
+                certified,
+                completed,
+            } => write!(
+                f,
+                "Checkpoint {checkpoint_id}: {completed} of {certified} certified transfers completed"
+            ),
+            Self::LatestProcessedNonce { account, nonce } => {
+                write!(f, "Latest processed nonce of {account}: {nonce}")
+            }
+            Self::Upgraded(report) => {
+                write!(
+                    f,
+                    "Upgraded OMInterop from {} ({}) to {} ({}) in transaction {}",
+                    report.previous_version,
+                    report.previous_implementation,
+                    report.version,
+                    report.implementation,
+                    report.transaction_hash
+                )?;
+                if !report.changed_slots.is_empty() {
+                    f.write_str("\nStorage written by the upgrade call:")?;
+                }
+                for change in &report.changed_slots {
+                    write!(f, "\n  {change}")?;
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloy_primitives::address;
+    use onemoney_interop::contract::OMInterop::{InvalidNonce, UnknownToken};
+    use onemoney_interop::upgrade::SlotChange;
+
+    use super::*;
+
+    const TOKEN: Address = address!("0x1111111111111111111111111111111111111111");
+
+    #[test]
+    fn reverts_are_readable() {
+        let error =
+            AdminError::Reverted(OMInteropErrors::UnknownToken(UnknownToken { token: TOKEN }));
+        assert_eq!(
+            error.to_string(),
+            format!("OMInterop reverted: token `{TOKEN}` is not mapped")
+        );
+
+        let error = AdminError::Reverted(OMInteropErrors::InvalidNonce(InvalidNonce {
+            provided: 3,
+            expected: 2,
+        }));
+        assert_eq!(
+            error.to_string(),
+            "OMInterop reverted: nonce 3 does not follow the latest processed nonce, expected 2"
+        );
+    }
+
+    #[test]
+    fn token_bindings_name_their_protocol() {
+        let binding = |protocol_id, exists| Outcome::TokenBinding {
+            om_token: TOKEN,
+            sc_token: Address::ZERO,
+            protocol_id,
+            exists,
+        };
+
+        assert_eq!(
+            binding(0, true).to_string(),
+            format!(
+                "{TOKEN} is mapped to sidechain token {} over LayerZero",
+                Address::ZERO
+            )
+        );
+        assert!(binding(7, true)
+            .to_string()
+            .ends_with("over unknown protocol 7"));
+        assert_eq!(
+            binding(0, false).to_string(),
+            format!("{TOKEN} is not mapped")
+        );
+    }
+
+    #[test]
+    fn upgrades_list_the_slots_changed_by_the_call() {
+        let upgraded = |changed_slots| {
+            Outcome::Upgraded(UpgradeReport {
+                previous_implementation: Address::ZERO,
+                implementation: TOKEN,
+                previous_version: "v1.0.0".to_owned(),
+                version: "v2.0.0".to_owned(),
+                transaction_hash: B256::ZERO,
+                changed_slots,
+            })
+        };
+
+        assert_eq!(
+            upgraded(Vec::new()).to_string(),
+            format!(
+                "Upgraded OMInterop from v1.0.0 ({}) to v2.0.0 ({TOKEN}) in transaction {}",
+                Address::ZERO,
+                B256::ZERO
+            )
+        );
+        let output = upgraded(vec![SlotChange {
+            slot: U256::from(2),
+            before: U256::ZERO,
+            after: U256::from(1),
+        }])
+        .to_string();
+        assert!(output.ends_with(
+            "\nStorage written by the upgrade call:\n  slot 0x2 changed from 0x0 to 0x1"
+        ));
+    }
+}
@@ -0,0 +1,17 @@
+use clap::Parser;
+
+#[tokio::main]
+async fn main() -> color_eyre::Result<()> {
+    tracing_subscriber::fmt::init();
+    color_eyre::install()?;
+    let cli = relayer::deploy::DeployCli::parse();
+    let output = cli.output.clone();
+    let manifest = cli.run().await?;
+    println!(
+        "Deployed OMInterop at {} and TxHashMapping at {}, manifest written to {}",
+        manifest.interop.address,
+        manifest.tx_hash_mapping.address,
+        output.display()
+    );
+    Ok(())
+}
@@ -1,30 +1,75 @@
+use core::future::Future;
+use core::pin::pin;
 use core::time::Duration;
+use std::path::PathBuf;
 
+use alloy_primitives::B256;
+use clap::{ArgMatches, CommandFactory, FromArgMatches};
 use futures::future::{try_join, try_join5};
 use futures::TryFutureExt;
 use humantime::format_duration;
 use tracing::info;
 
-use crate::config::Config;
+use crate::admin::{run_admin_command, AdminCommand, AdminSignerConfig};
+use crate::config::deployment::{
+    apply_manifest, deployment_manifest_path, load_manifest, DEPLOYMENT_MANIFEST_ENV,
+};
+use crate::config::error::Error as ConfigError;
+use crate::config::file::{config_file_path, render_effective_config, ConfigFile, CONFIG_FILE_ENV};
+use crate::config::{Config, ValidatorPowerConfig};
+use crate::context::RelayerContext;
 use crate::error::Error as CliError;
+use crate::health::{Flow, Health};
 use crate::incoming::recovery::{
-    get_latest_incomplete_block_number, recover_incomplete_deposit_hash_mapping,
-    relay_incoming_events_from_blocks,
+    recover_incomplete_deposit_hash_mapping, relay_incoming_events_from_blocks, resume_block_number,
 };
 use crate::incoming::relay_incoming_events;
 use crate::outgoing::recovery::{
-    get_earliest_incomplete_checkpoint_number, recover_incomplete_withdrawals_hash_mapping,
+    recover_incomplete_refunds_hash_mapping, recover_incomplete_withdrawals_hash_mapping,
+    resume_checkpoint_number,
 };
 use crate::outgoing::stream::{relay_outgoing_events, relay_outgoing_events_from_checkpoints};
+use crate::poa::power::PowerPolicy;
 use crate::poa::relay_poa_events;
+use crate::server::spawn_server;
+use crate::shutdown::Shutdown;
+use crate::store::StateStore;
+use crate::supervisor::Supervisor;
+use crate::trace::error::Error as TraceError;
+use crate::trace::trace_transfer;
 
 #[derive(clap::Parser)]
 #[command(version, about, long_about = None)]
 pub struct Cli {
+    /// Path of a TOML or YAML configuration file, overridden by environment variables and flags
+    #[arg(long = "config", env = CONFIG_FILE_ENV, global = true)]
+    pub config_file: Option<PathBuf>,
+    /// Path of a deployment manifest written by `relayer-deploy`, overridden by the
+    /// configuration file
+    #[arg(long = "deployment", env = DEPLOYMENT_MANIFEST_ENV, global = true)]
+    pub deployment_manifest: Option<PathBuf>,
+    /// Time given to in-flight transfers to finish after SIGINT or SIGTERM before exiting
+    #[arg(
+        long,
+        env = "RELAYER_SHUTDOWN_TIMEOUT",
+        value_parser = humantime::parse_duration,
+        default_value = "30s",
+        global = true
+    )]
+    pub shutdown_timeout: Duration,
     #[command(flatten)]
     pub config: Config,
     #[command(subcommand)]
     pub command: Commands,
+    #[arg(skip)]
+    layers: Option<Layers>,
+}
+
+/// Parsed layers of the configuration, kept to print the effective configuration.
+struct Layers {
+    command: clap::Command,
+    matches: ArgMatches,
+    file: Option<ConfigFile>,
 }
 
 #[derive(clap::Subcommand)]
@@ -38,6 +83,8 @@ pub enum Commands {
             help = "Polling interval for fetching epochs (human-friendly, e.g. 10s, 1m)"
         )]
         poll_interval: Duration,
+        #[command(flatten)]
+        validator_power: ValidatorPowerConfig,
     },
     /// Relay sidechain interoperability events into 1Money
     Sidechain {
@@ -127,179 +174,431 @@ pub enum Commands {
             help = "Interval of transaction clearing on the sidechain (human-friendly, e.g. 10s, 1m). Default to 10s."
         )]
         sidechain_clearing_poll_interval: Duration,
+        #[command(flatten)]
+        validator_power: ValidatorPowerConfig,
+    },
+    /// Show the cross-chain lifecycle of a transfer from any of its transaction hashes
+    Trace {
+        /// Hash of the `bridgeFrom`, `BridgeAndMint`, `BurnAndBridge`, `bridgeTo` or refund
+        /// transaction
+        hash: B256,
+        /// Print the trace as JSON
+        #[arg(long)]
+        json: bool,
+    },
+    /// Administer the interop contract as its owner or operator
+    Admin {
+        #[command(flatten)]
+        signer: AdminSignerConfig,
+        #[command(subcommand)]
+        command: AdminCommand,
     },
+    /// Inspect the relayer configuration
+    Config {
+        #[command(subcommand)]
+        command: ConfigCommand,
+    },
+}
+
+#[derive(clap::Subcommand)]
+pub enum ConfigCommand {
+    /// Print the effective configuration merged from the file, the environment and the flags
+    Print,
 }
 
 impl Cli {
+    /// Parses the command line with the configuration file given by `--config` or
+    /// `RELAYER_CONFIG` layered below environment variables and flags, and the deployment
+    /// manifest given by `--deployment` or `RELAYER_DEPLOYMENT` layered below the file.
+    ///
+    /// Exits the process on invalid arguments, like [`clap::Parser::parse`].
+    pub fn parse_layered() -> Result<Self, ConfigError> {
+        let args = std::env::args_os().collect::<Vec<_>>();
+        let file = config_file_path(&args)
+            .map(|path| ConfigFile::load(&path))
+            .transpose()?;
+        let manifest = deployment_manifest_path(&args)
+            .map(|path| load_manifest(&path))
+            .transpose()?;
+
+        let mut command = Self::command();
+        if let Some(manifest) = &manifest {
+            command = apply_manifest(manifest, command);
+        }
+        if let Some(file) = &file {
+            command = file.apply(command)?;
+        }
+        let matches = command.clone().get_matches_from(args);
+
+        let mut cli = Self::from_arg_matches(&matches).unwrap_or_else(|e| e.exit());
+        cli.layers = Some(Layers {
+            command,
+            matches,
+            file,
+        });
+        Ok(cli)
+    }
+
     pub async fn run(self) -> Result<(), CliError> {
-        let Self { config, command } = self;
+        let Self {
+            config,
+            command,
+            layers,
+            shutdown_timeout,
+            ..
+        } = self;
 
-        let sidechain_relayer_nonce = config.sidechain_relayer_nonce().await?;
+        if let Commands::Config {
+            command: ConfigCommand::Print,
+        } = command
+        {
+            let Layers {
+                command,
+                matches,
+                file,
+            } = layers.unwrap_or_else(|| Layers {
+                command: Self::command(),
+                matches: Self::command().get_matches(),
+                file: None,
+            });
+            print!(
+                "{}",
+                render_effective_config(&command, &matches, file.as_ref())
+            );
+            return Ok(());
+        }
 
-        match command {
-            Commands::ProofOfAuthority { poll_interval } => {
-                info!(
-                    poll_interval = %format_duration(poll_interval),
-                    from = %config.one_money_node_url,
-                    to = %config.side_chain_http_url,
-                    "Relaying POA events",
+        if let Commands::Trace { hash, json } = command {
+            let onemoney = config.onemoney_api()?;
+            let trace = trace_transfer(&config, onemoney.as_ref(), hash).await?;
+            if json {
+                println!(
+                    "{}",
+                    serde_json::to_string_pretty(&trace).map_err(TraceError::from)?
                 );
-                relay_poa_events(&config, sidechain_relayer_nonce.clone(), poll_interval).await?;
+            } else {
+                print!("{trace}");
             }
-            Commands::Sidechain {
-                from_block,
-                start_checkpoint_hash_mapping_recovery,
-                clearing_poll_interval,
-            } => {
-                recover_incomplete_deposit_hash_mapping(
-                    &config,
-                    sidechain_relayer_nonce.clone(),
-                    start_checkpoint_hash_mapping_recovery,
-                )
-                .await?;
-                let from_block = if let Some(block_number) = from_block {
-                    block_number
-                } else {
-                    get_latest_incomplete_block_number(&config).await?
-                };
-                info!(
-                    ?config.interop_contract_address,
-                    from_block,
-                    "Clearing SC events from {} to {}",
-                    config.side_chain_http_url,
-                    config.one_money_node_url
-                );
-                info!(
-                    %config.interop_contract_address,
+            return Ok(());
+        }
+
+        if let Commands::Admin { signer, command } = command {
+            println!("{}", run_admin_command(&config, &signer, command).await?);
+            return Ok(());
+        }
+
+        let store = config.state_store()?;
+        let shutdown = Shutdown::new();
+        shutdown.listen_for_signals();
+        let onemoney = config.onemoney_api()?;
+        let health = Health::new(&config, &store, onemoney.clone());
+        if let Some(metrics_addr) = config.metrics_addr {
+            spawn_server(metrics_addr, health.clone()).await?;
+        }
+        let supervisor = Supervisor::new(&config, &health, &shutdown);
+
+        let signers = config.signers()?;
+        let ctx = RelayerContext {
+            config: config.clone(),
+            tx_manager: config
+                .sidechain_tx_manager(&signers, onemoney.clone())
+                .await?,
+            signer: signers.relayer,
+            store: store.clone(),
+            onemoney,
+        };
+
+        let flows = async {
+            match command {
+                Commands::ProofOfAuthority {
+                    poll_interval,
+                    validator_power,
+                } => {
+                    let power_policy = PowerPolicy::load(&validator_power)?;
+                    info!(
+                        poll_interval = %format_duration(poll_interval),
+                        from = %config.one_money_node_url,
+                        to = %config.side_chain_http_url,
+                        "Relaying POA events",
+                    );
+                    health.set_recovered();
+                    supervisor
+                        .run(Flow::Poa, |_| {
+                            relay_poa_events(&ctx, poll_interval, &power_policy, &shutdown)
+                        })
+                        .await?;
+                }
+                Commands::Sidechain {
                     from_block,
-                    clearing_poll_interval = %format_duration(clearing_poll_interval),
-                    from = %config.side_chain_http_url,
-                    to = %config.one_money_node_url,
-                    "Relaying SC events",
-                );
-                try_join(
-                    relay_incoming_events(&config, sidechain_relayer_nonce.clone(), from_block),
-                    relay_incoming_events_from_blocks(
+                    start_checkpoint_hash_mapping_recovery,
+                    clearing_poll_interval,
+                } => {
+                    recover_incomplete_deposit_hash_mapping(
+                        &ctx,
+                        start_checkpoint_hash_mapping_recovery,
+                    )
+                    .await?;
+                    let from_block = if let Some(block_number) = from_block {
+                        block_number
+                    } else {
+                        resume_block_number(&ctx).await?
+                    };
+                    info!(
+                        ?config.interop_contract_address,
                         from_block,
-                        &config,
-                        sidechain_relayer_nonce.clone(),
-                        clearing_poll_interval,
-                    ),
-                )
-                .await?;
-            }
-            Commands::Onemoney {
-                start_checkpoint,
-                clearing_poll_interval,
-                start_checkpoint_hash_mapping_recovery,
-                start_block_hash_mapping_recovery,
-            } => {
-                recover_incomplete_withdrawals_hash_mapping(
-                    &config,
-                    sidechain_relayer_nonce.clone(),
+                        "Clearing SC events from {} to {}",
+                        config.side_chain_http_url,
+                        config.one_money_node_url
+                    );
+                    info!(
+                        %config.interop_contract_address,
+                        from_block,
+                        clearing_poll_interval = %format_duration(clearing_poll_interval),
+                        from = %config.side_chain_http_url,
+                        to = %config.one_money_node_url,
+                        "Relaying SC events",
+                    );
+                    health.set_recovered();
+                    try_join(
+                        supervisor.run(Flow::IncomingStream, |restarted| {
+                            relay_incoming_events(
+                                &ctx,
+                                flow_start_block(
+                                    store
+                                        .snapshot()
+                                        .sidechain_cursor
+                                        .map(|cursor| cursor.block_number),
+                                    from_block,
+                                    restarted,
+                                ),
+                                &shutdown,
+                            )
+                        }),
+                        supervisor.run(Flow::IncomingClearing, |restarted| {
+                            relay_incoming_events_from_blocks(
+                                flow_start_block(
+                                    store.snapshot().sidechain_clearing_cursor,
+                                    from_block,
+                                    restarted,
+                                ),
+                                &ctx,
+                                clearing_poll_interval,
+                                &shutdown,
+                            )
+                        }),
+                    )
+                    .await?;
+                }
+                Commands::Onemoney {
+                    start_checkpoint,
+                    clearing_poll_interval,
                     start_checkpoint_hash_mapping_recovery,
                     start_block_hash_mapping_recovery,
-                )
-                .await?;
-                let start_checkpoint = if let Some(start_checkpoint) = start_checkpoint {
-                    start_checkpoint
-                } else {
-                    get_earliest_incomplete_checkpoint_number(&config).await?
-                };
-                info!(
-                    start_checkpoint,
-                    "Clearing 1Money events from {} to {}",
-                    config.one_money_node_url,
-                    config.side_chain_http_url
-                );
-                info!(
-                    start_checkpoint,
-                    clearing_poll_interval = %format_duration(clearing_poll_interval),
-                    from = %config.one_money_node_url,
-                    to = %config.side_chain_http_url,
-                    "Relaying 1Money events",
-                );
-                try_join(
-                    relay_outgoing_events(&config, sidechain_relayer_nonce.clone()),
-                    relay_outgoing_events_from_checkpoints(
-                        &config,
-                        sidechain_relayer_nonce.clone(),
+                } => {
+                    recover_incomplete_withdrawals_hash_mapping(
+                        &ctx,
+                        start_block_hash_mapping_recovery,
+                    )
+                    .await?;
+                    recover_incomplete_refunds_hash_mapping(
+                        &ctx,
+                        start_checkpoint_hash_mapping_recovery,
+                        start_block_hash_mapping_recovery,
+                    )
+                    .await?;
+                    let start_checkpoint = if let Some(start_checkpoint) = start_checkpoint {
+                        start_checkpoint
+                    } else {
+                        resume_checkpoint_number(&ctx).await?
+                    };
+                    info!(
                         start_checkpoint,
-                        clearing_poll_interval,
-                    ),
-                )
-                .await?;
-            }
+                        "Clearing 1Money events from {} to {}",
+                        config.one_money_node_url,
+                        config.side_chain_http_url
+                    );
+                    info!(
+                        start_checkpoint,
+                        clearing_poll_interval = %format_duration(clearing_poll_interval),
+                        from = %config.one_money_node_url,
+                        to = %config.side_chain_http_url,
+                        "Relaying 1Money events",
+                    );
+                    health.set_recovered();
+                    try_join(
+                        supervisor.run(Flow::OutgoingStream, |_| {
+                            relay_outgoing_events(&ctx, &shutdown)
+                        }),
+                        supervisor.run(Flow::OutgoingClearing, |restarted| {
+                            relay_outgoing_events_from_checkpoints(
+                                &ctx,
+                                flow_start_checkpoint(&store, start_checkpoint, restarted),
+                                clearing_poll_interval,
+                                &shutdown,
+                            )
+                        }),
+                    )
+                    .await?;
+                }
 
-            Commands::All {
-                poa_poll_interval,
-                from_block,
-                start_checkpoint,
-                one_money_clearing_poll_interval,
-                start_checkpoint_hash_mapping_recovery,
-                start_block_hash_mapping_recovery,
-                sidechain_clearing_poll_interval,
-            } => {
-                recover_incomplete_deposit_hash_mapping(
-                    &config,
-                    sidechain_relayer_nonce.clone(),
-                    start_checkpoint_hash_mapping_recovery,
-                )
-                .await?;
-                recover_incomplete_withdrawals_hash_mapping(
-                    &config,
-                    sidechain_relayer_nonce.clone(),
+                Commands::All {
+                    poa_poll_interval,
+                    from_block,
+                    start_checkpoint,
+                    one_money_clearing_poll_interval,
                     start_checkpoint_hash_mapping_recovery,
                     start_block_hash_mapping_recovery,
-                )
-                .await?;
-                let start_checkpoint = if let Some(start_checkpoint) = start_checkpoint {
-                    start_checkpoint
-                } else {
-                    get_earliest_incomplete_checkpoint_number(&config).await?
-                };
+                    sidechain_clearing_poll_interval,
+                    validator_power,
+                } => {
+                    let power_policy = PowerPolicy::load(&validator_power)?;
+                    recover_incomplete_deposit_hash_mapping(
+                        &ctx,
+                        start_checkpoint_hash_mapping_recovery,
+                    )
+                    .await?;
+                    recover_incomplete_withdrawals_hash_mapping(
+                        &ctx,
+                        start_block_hash_mapping_recovery,
+                    )
+                    .await?;
+                    recover_incomplete_refunds_hash_mapping(
+                        &ctx,
+                        start_checkpoint_hash_mapping_recovery,
+                        start_block_hash_mapping_recovery,
+                    )
+                    .await?;
+                    let start_checkpoint = if let Some(start_checkpoint) = start_checkpoint {
+                        start_checkpoint
+                    } else {
+                        resume_checkpoint_number(&ctx).await?
+                    };
 
-                let from_block = if let Some(block_number) = from_block {
-                    block_number
-                } else {
-                    get_latest_incomplete_block_number(&config).await?
-                };
+                    let from_block = if let Some(block_number) = from_block {
+                        block_number
+                    } else {
+                        resume_block_number(&ctx).await?
+                    };
 
-                info!(
-                    start_checkpoint,
-                    from_block,
-                    poa_poll_interval = %format_duration(poa_poll_interval),
-                    sidechain_clearing_poll_interval = %format_duration(sidechain_clearing_poll_interval),
-                    one_money_clearing_poll_interval = %format_duration(one_money_clearing_poll_interval),
-                    onemoney_url = %config.one_money_node_url,
-                    sidechain_url = %config.side_chain_http_url,
-                    "Relaying all flows",
-                );
-                try_join5(
-                    relay_poa_events(&config, sidechain_relayer_nonce.clone(), poa_poll_interval)
-                        .map_err(CliError::from),
-                    relay_incoming_events(&config, sidechain_relayer_nonce.clone(), from_block)
-                        .map_err(CliError::from),
-                    relay_incoming_events_from_blocks(
-                        from_block,
-                        &config,
-                        sidechain_relayer_nonce.clone(),
-                        sidechain_clearing_poll_interval,
-                    )
-                    .map_err(CliError::from),
-                    relay_outgoing_events(&config, sidechain_relayer_nonce.clone())
-                        .map_err(CliError::from),
-                    relay_outgoing_events_from_checkpoints(
-                        &config,
-                        sidechain_relayer_nonce.clone(),
+                    info!(
                         start_checkpoint,
-                        one_money_clearing_poll_interval,
+                        from_block,
+                        poa_poll_interval = %format_duration(poa_poll_interval),
+                        sidechain_clearing_poll_interval = %format_duration(sidechain_clearing_poll_interval),
+                        one_money_clearing_poll_interval = %format_duration(one_money_clearing_poll_interval),
+                        onemoney_url = %config.one_money_node_url,
+                        sidechain_url = %config.side_chain_http_url,
+                        "Relaying all flows",
+                    );
+                    health.set_recovered();
+                    try_join5(
+                        supervisor
+                            .run(Flow::Poa, |_| {
+                                relay_poa_events(&ctx, poa_poll_interval, &power_policy, &shutdown)
+                            })
+                            .map_err(CliError::from),
+                        supervisor
+                            .run(Flow::IncomingStream, |restarted| {
+                                relay_incoming_events(
+                                    &ctx,
+                                    flow_start_block(
+                                        store
+                                            .snapshot()
+                                            .sidechain_cursor
+                                            .map(|cursor| cursor.block_number),
+                                        from_block,
+                                        restarted,
+                                    ),
+                                    &shutdown,
+                                )
+                            })
+                            .map_err(CliError::from),
+                        supervisor
+                            .run(Flow::IncomingClearing, |restarted| {
+                                relay_incoming_events_from_blocks(
+                                    flow_start_block(
+                                        store.snapshot().sidechain_clearing_cursor,
+                                        from_block,
+                                        restarted,
+                                    ),
+                                    &ctx,
+                                    sidechain_clearing_poll_interval,
+                                    &shutdown,
+                                )
+                            })
+                            .map_err(CliError::from),
+                        supervisor
+                            .run(Flow::OutgoingStream, |_| {
+                                relay_outgoing_events(&ctx, &shutdown)
+                            })
+                            .map_err(CliError::from),
+                        supervisor
+                            .run(Flow::OutgoingClearing, |restarted| {
+                                relay_outgoing_events_from_checkpoints(
+                                    &ctx,
+                                    flow_start_checkpoint(&store, start_checkpoint, restarted),
+                                    one_money_clearing_poll_interval,
+                                    &shutdown,
+                                )
+                            })
+                            .map_err(CliError::from),
                     )
-                    .map_err(CliError::from),
-                )
-                .await?;
+                    .await?;
+                }
+                // Handled before connecting to the chains
+                Commands::Config { .. } | Commands::Trace { .. } | Commands::Admin { .. } => {}
             }
+            Ok::<_, CliError>(())
+        };
+
+        let drained = drain_on_shutdown(flows, &shutdown, shutdown_timeout).await;
+        store.flush().await?;
+        drained?;
+        if shutdown.is_requested() {
+            info!("Relayer stopped, all in-flight transfers drained");
         }
         Ok(())
     }
 }
+
+/// Block from which an incoming flow starts, past the block of its own `cursor` on a restart.
+fn flow_start_block(cursor: Option<u64>, from_block: u64, restarted: bool) -> u64 {
+    match cursor {
+        Some(block_number) if restarted => block_number.max(from_block),
+        _ => from_block,
+    }
+}
+
+/// Checkpoint from which the outgoing clearing flow starts, past the checkpoints already
+/// cleared on a restart.
+fn flow_start_checkpoint(store: &StateStore, start_checkpoint: u64, restarted: bool) -> u64 {
+    match store.snapshot().checkpoint_cursor {
+        Some(checkpoint) if restarted => (checkpoint + 1).max(start_checkpoint),
+        _ => start_checkpoint,
+    }
+}
+
+/// Runs `flows` until they return. Once a shutdown is requested the flows stop pulling new
+/// events and are given `timeout` to finish the transfers in progress.
+async fn drain_on_shutdown<F>(
+    flows: F,
+    shutdown: &Shutdown,
+    timeout: Duration,
+) -> Result<(), CliError>
+where
+    F: Future<Output = Result<(), CliError>>,
+{
+    let mut flows = pin!(flows);
+    tokio::select! {
+        result = &mut flows => result,
+        () = shutdown.requested() => {
+            info!(
+                timeout = %format_duration(timeout),
+                "Shutting down, waiting for in-flight transfers",
+            );
+            tokio::time::timeout(timeout, flows)
+                .await
+                .map_err(|_| CliError::ShutdownTimeout(timeout))?
+        }
+    }
+}
@@ -0,0 +1,192 @@
+//! Deployment manifest written by `relayer-deploy`, layered below the configuration file.
+//!
+//! The manifest given with `--deployment` or `RELAYER_DEPLOYMENT` sets the contract
+//! addresses, and starts the hash mapping recovery at the deployment block of the interop
+//! contract. Like the configuration file, its values are installed as flag defaults.
+
+use std::ffi::OsString;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use clap::Command;
+use onemoney_interop::deploy::{DeploymentManifest, MANIFEST_VERSION};
+
+use super::file::{flag_path, with_default};
+use crate::config::error::Error as ConfigError;
+
+/// Environment variable holding the path of the deployment manifest.
+pub const DEPLOYMENT_MANIFEST_ENV: &str = "RELAYER_DEPLOYMENT";
+
+/// Flag of the subcommands starting the hash mapping recovery at a sidechain block.
+const RECOVERY_START_BLOCK_ARG: &str = "start_block_hash_mapping_recovery";
+
+/// Returns the manifest given with `--deployment` in `args` or in `RELAYER_DEPLOYMENT`.
+pub fn deployment_manifest_path(args: &[OsString]) -> Option<PathBuf> {
+    flag_path(args, "--deployment", DEPLOYMENT_MANIFEST_ENV)
+}
+
+/// Loads the JSON deployment manifest at `path`.
+pub fn load_manifest(path: &Path) -> Result<DeploymentManifest, ConfigError> {
+    let contents = fs::read_to_string(path).map_err(|source| ConfigError::ReadManifest {
+        path: path.to_owned(),
+        source,
+    })?;
+    let manifest = serde_json::from_str::<DeploymentManifest>(&contents).map_err(|source| {
+        ConfigError::ParseManifest {
+            path: path.to_owned(),
+            source,
+        }
+    })?;
+    if manifest.version != MANIFEST_VERSION {
+        return Err(ConfigError::UnsupportedManifestVersion {
+            path: path.to_owned(),
+            version: manifest.version,
+            expected: MANIFEST_VERSION,
+        });
+    }
+
+    Ok(manifest)
+}
+
+/// Installs the values of `manifest` as flag defaults of `command`.
+pub fn apply_manifest(manifest: &DeploymentManifest, command: Command) -> Command {
+    let interop = manifest.interop.address.to_string();
+    let tx_mapping = manifest.tx_hash_mapping.address.to_string();
+    let start_block = manifest.interop.block_number.to_string();
+
+    let recovering = command
+        .get_subcommands()
+        .filter(|subcommand| {
+            subcommand
+                .get_arguments()
+                .any(|arg| arg.get_id() == RECOVERY_START_BLOCK_ARG)
+        })
+        .map(|subcommand| subcommand.get_name().to_owned())
+        .collect::<Vec<_>>();
+
+    let command = command
+        .mut_arg("interop_contract_address", |arg| {
+            with_default(arg, &interop)
+        })
+        .mut_arg("tx_mapping_contract_address", |arg| {
+            with_default(arg, &tx_mapping)
+        });
+    recovering.iter().fold(command, |command, name| {
+        command.mut_subcommand(name, |subcommand| {
+            subcommand.mut_arg(RECOVERY_START_BLOCK_ARG, |arg| {
+                with_default(arg, &start_block)
+            })
+        })
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use alloy_primitives::{address, Address, B256};
+    use clap::{value_parser, Arg};
+    use onemoney_interop::deploy::DeployedContract;
+
+    use super::*;
+
+    const INTEROP: Address = address!("0x1111111111111111111111111111111111111111");
+    const TX_MAPPING: Address = address!("0x2222222222222222222222222222222222222222");
+
+    fn contract(address: Address, block_number: u64) -> DeployedContract {
+        DeployedContract {
+            address,
+            block_number,
+            transaction_hash: B256::ZERO,
+        }
+    }
+
+    fn manifest() -> DeploymentManifest {
+        DeploymentManifest {
+            version: MANIFEST_VERSION,
+            chain_id: 31337,
+            deployer: Address::ZERO,
+            owner: Address::ZERO,
+            operator: Address::ZERO,
+            relayer: Address::ZERO,
+            tx_mapping_relayer: Address::ZERO,
+            interop_version: "v1.0.0".to_owned(),
+            price_oracle: contract(Address::ZERO, 10),
+            interop_implementation: contract(Address::ZERO, 11),
+            interop: contract(INTEROP, 12),
+            tx_hash_mapping: contract(TX_MAPPING, 13),
+            tokens: Vec::new(),
+        }
+    }
+
+    fn command() -> Command {
+        Command::new("relayer")
+            .arg(
+                Arg::new("interop_contract_address")
+                    .long("interop-contract-address")
+                    .required(true),
+            )
+            .arg(
+                Arg::new("tx_mapping_contract_address")
+                    .long("tx-mapping-contract-address")
+                    .required(true),
+            )
+            .subcommand(
+                Command::new("onemoney").arg(
+                    Arg::new(RECOVERY_START_BLOCK_ARG)
+                        .long("start-block-hash-mapping-recovery")
+                        .value_parser(value_parser!(u64)),
+                ),
+            )
+            .subcommand(Command::new("proof-of-authority"))
+    }
+
+    #[test]
+    fn manifest_sets_defaults_overridden_by_cli() {
+        let command = apply_manifest(&manifest(), command());
+
+        let matches = command.clone().get_matches_from(["relayer", "onemoney"]);
+        assert_eq!(
+            matches.get_one::<String>("interop_contract_address"),
+            Some(&INTEROP.to_string())
+        );
+        let (_, onemoney) = matches.subcommand().expect("subcommand is parsed");
+        assert_eq!(onemoney.get_one::<u64>(RECOVERY_START_BLOCK_ARG), Some(&12));
+
+        let matches = command.get_matches_from([
+            "relayer",
+            "--tx-mapping-contract-address",
+            "0x3333333333333333333333333333333333333333",
+            "proof-of-authority",
+        ]);
+        assert_eq!(
+            matches.get_one::<String>("tx_mapping_contract_address"),
+            Some(&"0x3333333333333333333333333333333333333333".to_owned())
+        );
+    }
+
+    #[test]
+    fn manifest_round_trips_through_json() {
+        let dir = tempfile::tempdir().expect("temporary directory is created");
+        let path = dir.path().join("deployment.json");
+        fs::write(
+            &path,
+            serde_json::to_string_pretty(&manifest()).expect("manifest is serializable"),
+        )
+        .expect("manifest is written");
+        assert_eq!(
+            load_manifest(&path).expect("manifest is loaded"),
+            manifest()
+        );
+
+        let mut outdated = manifest();
+        outdated.version = MANIFEST_VERSION + 1;
+        fs::write(
+            &path,
+            serde_json::to_string(&outdated).expect("manifest is serializable"),
+        )
+        .expect("manifest is written");
+        assert!(matches!(
+            load_manifest(&path),
+            Err(ConfigError::UnsupportedManifestVersion { .. })
+        ));
+    }
+}
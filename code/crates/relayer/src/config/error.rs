@@ -1,7 +1,50 @@
+use std::path::PathBuf;
+
 use thiserror::Error;
 
 #[derive(Debug, Error)]
 pub enum Error {
     #[error(transparent)]
     RelayerNonce(#[from] alloy_transport::RpcError<alloy_transport::TransportErrorKind>),
+    #[error(transparent)]
+    StateStore(#[from] crate::store::error::Error),
+    #[error(transparent)]
+    Signer(#[from] crate::signer::error::Error),
+    #[error("Invalid 1Money node: {0}")]
+    Onemoney(#[from] onemoney_protocol::Error),
+    #[error("Failed to read config file `{path}`: {source}")]
+    ReadFile {
+        path: PathBuf,
+        source: std::io::Error,
+    },
+    #[error("Failed to parse config file `{path}`: {reason}")]
+    ParseFile { path: PathBuf, reason: String },
+    #[error("Unsupported config file `{path}`, expected a `.toml`, `.yaml` or `.yml` extension")]
+    UnsupportedFormat { path: PathBuf },
+    #[error("Unknown setting `{key}` in config file `{path}`")]
+    UnknownKey { path: PathBuf, key: String },
+    #[error("Unknown section `[{section}]` in config file `{path}`")]
+    UnknownSection { path: PathBuf, section: String },
+    #[error("Invalid value for `{key}` in config file `{path}`: {reason}")]
+    InvalidValue {
+        path: PathBuf,
+        key: String,
+        reason: String,
+    },
+    #[error("Failed to read deployment manifest `{path}`: {source}")]
+    ReadManifest {
+        path: PathBuf,
+        source: std::io::Error,
+    },
+    #[error("Failed to parse deployment manifest `{path}`: {source}")]
+    ParseManifest {
+        path: PathBuf,
+        source: serde_json::Error,
+    },
+    #[error("Unsupported version {version} of deployment manifest `{path}`, expected {expected}")]
+    UnsupportedManifestVersion {
+        path: PathBuf,
+        version: u32,
+        expected: u32,
+    },
 }
@@ -0,0 +1,406 @@
+//! Configuration file layered below environment variables and command line flags.
+//!
+//! Top-level keys set the global flags of [`Config`](super::Config) while tables named after a
+//! subcommand set the flags of that subcommand. Keys are flag names, in kebab or snake case.
+//! File values are installed as the defaults of their flags, so environment variables and
+//! command line flags take precedence over them.
+
+use std::collections::BTreeMap;
+use std::ffi::{OsStr, OsString};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use clap::parser::ValueSource;
+use clap::{Arg, ArgMatches, Command};
+use serde_json::Value;
+
+use crate::config::error::Error as ConfigError;
+
+/// Environment variable holding the path of the configuration file.
+pub const CONFIG_FILE_ENV: &str = "RELAYER_CONFIG";
+
+/// Flags which are never read from the configuration file.
+const RESERVED_ARGS: [&str; 4] = ["config_file", "deployment_manifest", "help", "version"];
+
+/// Flags whose values are redacted when printing the configuration.
+const SECRET_ARGS: [&str; 4] = [
+    "relayer_private_key",
+    "validator_manager_private_key",
+    "tx_mapping_private_key",
+    "admin_private_key",
+];
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ConfigFile {
+    path: PathBuf,
+    global: BTreeMap<String, String>,
+    commands: BTreeMap<String, BTreeMap<String, String>>,
+}
+
+impl ConfigFile {
+    /// Loads the TOML or YAML file at `path`, based on its extension.
+    pub fn load(path: &Path) -> Result<Self, ConfigError> {
+        let contents = fs::read_to_string(path).map_err(|source| ConfigError::ReadFile {
+            path: path.to_owned(),
+            source,
+        })?;
+        let parse_error = |reason: String| ConfigError::ParseFile {
+            path: path.to_owned(),
+            reason,
+        };
+
+        let value = match path.extension().and_then(OsStr::to_str) {
+            Some("toml") => {
+                toml::from_str::<Value>(&contents).map_err(|e| parse_error(e.to_string()))?
+            }
+            Some("yaml" | "yml") => {
+                serde_yaml::from_str::<Value>(&contents).map_err(|e| parse_error(e.to_string()))?
+            }
+            _ => {
+                return Err(ConfigError::UnsupportedFormat {
+                    path: path.to_owned(),
+                })
+            }
+        };
+
+        Self::from_value(path, value)
+    }
+
+    fn from_value(path: &Path, value: Value) -> Result<Self, ConfigError> {
+        let mut file = Self {
+            path: path.to_owned(),
+            ..Self::default()
+        };
+
+        let entries = match value {
+            Value::Object(entries) => entries,
+            // An empty YAML document
+            Value::Null => return Ok(file),
+            _ => {
+                return Err(ConfigError::ParseFile {
+                    path: path.to_owned(),
+                    reason: "expected a table of settings".to_owned(),
+                })
+            }
+        };
+
+        for (key, value) in entries {
+            if let Value::Object(section) = value {
+                let mut values = BTreeMap::new();
+                for (key, value) in section {
+                    values.insert(arg_id(&key), file.scalar(&key, value)?);
+                }
+                file.commands.insert(key.replace('_', "-"), values);
+            } else {
+                let value = file.scalar(&key, value)?;
+                file.global.insert(arg_id(&key), value);
+            }
+        }
+
+        Ok(file)
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Validates the file against `command` and installs its values as flag defaults.
+    pub fn apply(&self, mut command: Command) -> Result<Command, ConfigError> {
+        for (key, value) in &self.global {
+            self.check_value(&command, key, value)?;
+            command = command.mut_arg(key, |arg| with_default(arg, value));
+        }
+
+        for (name, values) in &self.commands {
+            let subcommand =
+                command
+                    .find_subcommand(name)
+                    .ok_or_else(|| ConfigError::UnknownSection {
+                        path: self.path.clone(),
+                        section: name.clone(),
+                    })?;
+            for (key, value) in values {
+                self.check_value(subcommand, key, value)?;
+            }
+            command = command.mut_subcommand(name, |subcommand| {
+                values.iter().fold(subcommand, |subcommand, (key, value)| {
+                    subcommand.mut_arg(key, |arg| with_default(arg, value))
+                })
+            });
+        }
+
+        Ok(command)
+    }
+
+    fn scalar(&self, key: &str, value: Value) -> Result<String, ConfigError> {
+        match value {
+            Value::String(value) => Ok(value),
+            Value::Number(value) => Ok(value.to_string()),
+            Value::Bool(value) => Ok(value.to_string()),
+            _ => Err(ConfigError::InvalidValue {
+                path: self.path.clone(),
+                key: key.to_owned(),
+                reason: "expected a string, a number or a boolean".to_owned(),
+            }),
+        }
+    }
+
+    /// Checks that `key` is a flag of `command` and that `value` parses as its value.
+    fn check_value(&self, command: &Command, key: &str, value: &str) -> Result<(), ConfigError> {
+        let arg = command
+            .get_arguments()
+            .find(|arg| arg.get_id() == key && !RESERVED_ARGS.contains(&key))
+            .ok_or_else(|| ConfigError::UnknownKey {
+                path: self.path.clone(),
+                key: key.to_owned(),
+            })?;
+
+        Command::new("config")
+            .no_binary_name(true)
+            .arg(Arg::new("value").value_parser(arg.get_value_parser().clone()))
+            .try_get_matches_from([value])
+            .map(drop)
+            .map_err(|err| ConfigError::InvalidValue {
+                path: self.path.clone(),
+                key: key.to_owned(),
+                reason: core::error::Error::source(&err)
+                    .map_or_else(|| err.kind().to_string(), ToString::to_string),
+            })
+    }
+
+    fn contains(&self, subcommand: Option<&str>, key: &str) -> bool {
+        match subcommand {
+            Some(name) => self
+                .commands
+                .get(name)
+                .is_some_and(|values| values.contains_key(key)),
+            None => self.global.contains_key(key),
+        }
+    }
+}
+
+/// Returns the configuration file given with `--config` in `args` or in `RELAYER_CONFIG`.
+pub fn config_file_path(args: &[OsString]) -> Option<PathBuf> {
+    flag_path(args, "--config", CONFIG_FILE_ENV)
+}
+
+/// Returns the path given with `flag` in `args` or in the environment variable `env`.
+pub(super) fn flag_path(args: &[OsString], flag: &str, env: &str) -> Option<PathBuf> {
+    let prefix = format!("{flag}=");
+    let mut args = args.iter().skip(1);
+    while let Some(arg) = args.next() {
+        if arg == "--" {
+            break;
+        }
+        if arg == flag {
+            return args.next().map(PathBuf::from);
+        }
+        if let Some(path) = arg.to_str().and_then(|arg| arg.strip_prefix(&prefix)) {
+            return Some(PathBuf::from(path));
+        }
+    }
+
+    std::env::var_os(env).map(PathBuf::from)
+}
+
+/// Renders the effective configuration as TOML, with secrets redacted.
+///
+/// Each value is annotated with its source. `command` must have the configuration file
+/// applied and `matches` must result from parsing it.
+pub fn render_effective_config(
+    command: &Command,
+    matches: &ArgMatches,
+    file: Option<&ConfigFile>,
+) -> String {
+    let mut output = String::from("# Effective relayer configuration\n");
+    if let Some(file) = file {
+        output.push_str(&format!("# Configuration file: {}\n", file.path.display()));
+    }
+
+    for arg in settings(command) {
+        let id = arg.get_id().as_str();
+        let Some(raw) = matches.get_raw(id) else {
+            continue;
+        };
+        let source = match matches.value_source(id) {
+            Some(ValueSource::CommandLine) => "cli",
+            Some(ValueSource::EnvVariable) => "env",
+            _ if file.is_some_and(|file| file.contains(None, id)) => "file",
+            _ => "default",
+        };
+        let value = raw
+            .map(OsStr::to_string_lossy)
+            .collect::<Vec<_>>()
+            .join(",");
+        output.push_str(&render_line(id, &value, source));
+    }
+
+    for subcommand in command.get_subcommands() {
+        let name = subcommand.get_name();
+        let lines = settings(subcommand)
+            .filter_map(|arg| {
+                let id = arg.get_id().as_str();
+                let value = arg.get_default_values().first()?.to_string_lossy();
+                let source = if file.is_some_and(|file| file.contains(Some(name), id)) {
+                    "file"
+                } else {
+                    "default"
+                };
+                Some(render_line(id, &value, source))
+            })
+            .collect::<String>();
+        if !lines.is_empty() {
+            output.push_str(&format!("\n[{name}]\n{lines}"));
+        }
+    }
+
+    output
+}
+
+fn settings(command: &Command) -> impl Iterator<Item = &Arg> {
+    command
+        .get_arguments()
+        .filter(|arg| !RESERVED_ARGS.contains(&arg.get_id().as_str()))
+}
+
+fn render_line(id: &str, value: &str, source: &str) -> String {
+    let value = if SECRET_ARGS.contains(&id) {
+        "<redacted>"
+    } else {
+        value
+    };
+    format!("{id} = {} # {source}\n", toml::Value::from(value))
+}
+
+pub(super) fn with_default(arg: Arg, value: &str) -> Arg {
+    arg.default_value(value.to_owned()).required(false)
+}
+
+fn arg_id(key: &str) -> String {
+    key.replace('-', "_")
+}
+
+#[cfg(test)]
+mod tests {
+    use core::time::Duration;
+
+    use clap::{value_parser, ArgAction};
+
+    use super::*;
+
+    fn command() -> Command {
+        Command::new("relayer")
+            .arg(
+                Arg::new("one_money_node_url")
+                    .long("one-money-node-url")
+                    .default_value("http://127.0.0.1:18555"),
+            )
+            .arg(
+                Arg::new("relayer_private_key")
+                    .long("relayer-private-key")
+                    .required(true),
+            )
+            .arg(
+                Arg::new("max_block_lag")
+                    .long("max-block-lag")
+                    .value_parser(value_parser!(u64))
+                    .action(ArgAction::Set),
+            )
+            .subcommand(
+                Command::new("proof-of-authority").arg(
+                    Arg::new("poll_interval")
+                        .long("poll-interval")
+                        .value_parser(humantime::parse_duration)
+                        .default_value("10s"),
+                ),
+            )
+    }
+
+    fn parse(contents: &str) -> Result<ConfigFile, ConfigError> {
+        let value = toml::from_str::<Value>(contents).expect("valid toml");
+        ConfigFile::from_value(Path::new("relayer.toml"), value)
+    }
+
+    #[test]
+    fn file_values_are_overridden_by_cli() {
+        let file = parse(
+            r#"
+            one-money-node-url = "http://node:18555"
+            relayer_private_key = "0x01"
+            max-block-lag = 5
+
+            [proof_of_authority]
+            poll-interval = "1m"
+            "#,
+        )
+        .expect("valid file");
+        let command = file.apply(command()).expect("applied file");
+
+        let matches = command
+            .clone()
+            .try_get_matches_from(["relayer", "--max-block-lag", "7", "proof-of-authority"])
+            .expect("valid arguments");
+        assert_eq!(
+            matches
+                .get_one::<String>("one_money_node_url")
+                .map(String::as_str),
+            Some("http://node:18555")
+        );
+        assert_eq!(matches.get_one::<u64>("max_block_lag"), Some(&7));
+        assert_eq!(
+            matches
+                .subcommand_matches("proof-of-authority")
+                .and_then(|matches| matches.get_one::<Duration>("poll_interval")),
+            Some(&Duration::from_secs(60))
+        );
+
+        let rendered = render_effective_config(&command, &matches, Some(&file));
+        assert!(rendered.contains("one_money_node_url = \"http://node:18555\" # file\n"));
+        assert!(rendered.contains("relayer_private_key = \"<redacted>\" # file\n"));
+        assert!(rendered.contains("max_block_lag = \"7\" # cli\n"));
+        assert!(rendered.contains("[proof-of-authority]\npoll_interval = \"1m\" # file\n"));
+        assert!(!rendered.contains("0x01"));
+    }
+
+    #[test]
+    fn unknown_settings_are_rejected() {
+        let file = parse("unknown = 1").expect("valid file");
+        assert!(matches!(
+            file.apply(command()),
+            Err(ConfigError::UnknownKey { key, .. }) if key == "unknown"
+        ));
+
+        let file = parse("[unknown]\npoll-interval = \"1s\"").expect("valid file");
+        assert!(matches!(
+            file.apply(command()),
+            Err(ConfigError::UnknownSection { section, .. }) if section == "unknown"
+        ));
+    }
+
+    #[test]
+    fn invalid_values_are_rejected() {
+        let file = parse("max-block-lag = \"many\"").expect("valid file");
+        assert!(matches!(
+            file.apply(command()),
+            Err(ConfigError::InvalidValue { key, .. }) if key == "max_block_lag"
+        ));
+
+        assert!(matches!(
+            parse("max-block-lag = [1, 2]"),
+            Err(ConfigError::InvalidValue { key, .. }) if key == "max-block-lag"
+        ));
+    }
+
+    #[test]
+    fn config_file_path_is_read_from_args() {
+        let args = |args: &[&str]| args.iter().map(OsString::from).collect::<Vec<_>>();
+        assert_eq!(
+            config_file_path(&args(&["relayer", "--config", "relayer.toml", "all"])),
+            Some(PathBuf::from("relayer.toml"))
+        );
+        assert_eq!(
+            config_file_path(&args(&["relayer", "all", "--config=relayer.yaml"])),
+            Some(PathBuf::from("relayer.yaml"))
+        );
+    }
+}
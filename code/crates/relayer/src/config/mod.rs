@@ -1,15 +1,24 @@
-use core::sync::atomic::AtomicU64;
+use core::net::SocketAddr;
+use core::time::Duration;
+use std::path::PathBuf;
 use std::sync::Arc;
 
 use alloy_primitives::Address;
-use alloy_provider::{Provider, ProviderBuilder};
 use alloy_signer_local::PrivateKeySigner;
+use onemoney_protocol::client::http::Client;
+use tracing::{info, warn};
 use url::Url;
+pub mod deployment;
 pub mod error;
+pub mod file;
 
 use error::Error as ConfigError;
 
-pub type RelayerNonce = Arc<AtomicU64>;
+use crate::dry_run::DryRun;
+use crate::onemoney::api::OneMoneyApi;
+use crate::sidechain::tx_manager::TxManager;
+use crate::signer::Signers;
+use crate::store::StateStore;
 
 #[derive(clap::Args, Clone)]
 pub struct Config {
@@ -19,30 +28,322 @@ pub struct Config {
     /// URL of the 1Money Websocket to connect to
     #[arg(long, env = "OM_WS_URL", default_value = "ws://127.0.0.1:18555")]
     pub one_money_ws_url: Url,
+    #[command(flatten)]
+    pub one_money_ws: OnemoneyWsConfig,
     /// HTTP URL of the sidechain node to connect to
     #[arg(long, env = "SC_HTTP_URL", default_value = "http://127.0.0.1:8645")]
     pub side_chain_http_url: Url,
     /// WebSocket URL of the sidechain node to connect to
     #[arg(long, env = "SC_WS_URL", default_value = "ws://127.0.0.1:8646")]
     pub side_chain_ws_url: Url,
+    /// Number of blocks built on top of a sidechain block before its events are relayed
+    #[arg(long, env = "SC_CONFIRMATIONS", default_value_t = 12)]
+    pub side_chain_confirmations: u64,
     /// Address of the interop contract
     #[arg(long, env = "INTEROP_CONTRACT_ADDRESS")]
     pub interop_contract_address: Address,
     /// Address of the tx mapping contract
     #[arg(long, env = "TX_MAPPING_CONTRACT_ADDRESS")]
     pub tx_mapping_contract_address: Address,
-    /// Private key of the relayer account
-    #[arg(long, env = "RELAYER_PRIVATE_KEY")]
-    pub relayer_private_key: PrivateKeySigner,
+    #[command(flatten)]
+    pub signer: SignerConfig,
+    /// Path of the local state store used to resume after a restart
+    #[arg(long, env = "RELAYER_STATE_PATH")]
+    pub state_path: Option<PathBuf>,
+    /// Address on which the metrics and health endpoints are served, disabled if not set
+    #[arg(long, env = "RELAYER_METRICS_ADDR")]
+    pub metrics_addr: Option<SocketAddr>,
+    #[command(flatten)]
+    pub health: HealthConfig,
+    #[command(flatten)]
+    pub sidechain_tx: SidechainTxConfig,
+    #[command(flatten)]
+    pub supervisor: SupervisorConfig,
+    #[command(flatten)]
+    pub dry_run: DryRunConfig,
+}
+
+/// Source of the relayer key, exactly one of the key, key file, keystore and remote signer
+/// address must be set
+#[derive(clap::Args, Clone, Debug, Default)]
+pub struct SignerConfig {
+    /// Private key of the relayer account, prefer a key file or a keystore
+    #[arg(long, env = "RELAYER_PRIVATE_KEY", hide_env_values = true)]
+    pub relayer_private_key: Option<PrivateKeySigner>,
+    /// Path of a file holding the hex encoded private key of the relayer account
+    #[arg(long, env = "RELAYER_KEY_FILE")]
+    pub relayer_key_file: Option<PathBuf>,
+    /// Path of an Ethereum JSON keystore holding the relayer account
+    #[arg(long, env = "RELAYER_KEYSTORE")]
+    pub relayer_keystore: Option<PathBuf>,
+    /// Path of a file holding the keystore password, prompted for on startup if not set
+    #[arg(long, env = "RELAYER_KEYSTORE_PASSWORD_FILE")]
+    pub relayer_keystore_password_file: Option<PathBuf>,
+    /// URL of a remote signer exposing the Web3Signer API which holds the relayer or role keys
+    #[arg(long, env = "RELAYER_REMOTE_SIGNER_URL")]
+    pub remote_signer_url: Option<Url>,
+    /// Address of the relayer account on the remote signer, selects the remote signer for the
+    /// relayer key
+    #[arg(long, env = "RELAYER_ADDRESS")]
+    pub relayer_address: Option<Address>,
+    /// Private key of the account updating the validator set, defaults to the relayer key
+    #[arg(long, env = "VALIDATOR_MANAGER_PRIVATE_KEY", hide_env_values = true)]
+    pub validator_manager_private_key: Option<PrivateKeySigner>,
+    /// Path of a file holding the private key of the account updating the validator set
+    #[arg(long, env = "VALIDATOR_MANAGER_KEY_FILE")]
+    pub validator_manager_key_file: Option<PathBuf>,
+    /// Address on the remote signer of the account updating the validator set
+    #[arg(long, env = "VALIDATOR_MANAGER_ADDRESS")]
+    pub validator_manager_address: Option<Address>,
+    /// Private key of the account writing to the tx mapping contract, defaults to the relayer
+    /// key
+    #[arg(long, env = "TX_MAPPING_PRIVATE_KEY", hide_env_values = true)]
+    pub tx_mapping_private_key: Option<PrivateKeySigner>,
+    /// Path of a file holding the private key of the account writing to the tx mapping contract
+    #[arg(long, env = "TX_MAPPING_KEY_FILE")]
+    pub tx_mapping_key_file: Option<PathBuf>,
+    /// Address on the remote signer of the account writing to the tx mapping contract
+    #[arg(long, env = "TX_MAPPING_ADDRESS")]
+    pub tx_mapping_address: Option<Address>,
+}
+
+/// Settings of the subscription to the 1Money certified transactions
+#[derive(clap::Args, Clone, Debug)]
+pub struct OnemoneyWsConfig {
+    /// Time without any message after which the 1Money websocket is considered dead
+    #[arg(
+        long,
+        env = "OM_WS_HEARTBEAT_TIMEOUT",
+        value_parser = humantime::parse_duration,
+        default_value = "30s"
+    )]
+    pub one_money_ws_heartbeat_timeout: Duration,
+    /// Delay before reconnecting to the 1Money websocket, doubled after every failed attempt
+    #[arg(
+        long,
+        env = "OM_WS_RECONNECT_BACKOFF",
+        value_parser = humantime::parse_duration,
+        default_value = "1s"
+    )]
+    pub one_money_ws_reconnect_backoff: Duration,
+    /// Maximum delay between two reconnections to the 1Money websocket
+    #[arg(
+        long,
+        env = "OM_WS_MAX_RECONNECT_BACKOFF",
+        value_parser = humantime::parse_duration,
+        default_value = "30s"
+    )]
+    pub one_money_ws_max_reconnect_backoff: Duration,
+}
+
+/// Settings of the transactions submitted to the sidechain
+#[derive(clap::Args, Clone, Debug)]
+pub struct SidechainTxConfig {
+    /// Time a sidechain transaction may stay pending before it is replaced with higher fees
+    #[arg(
+        long,
+        env = "SC_TX_STUCK_TIMEOUT",
+        value_parser = humantime::parse_duration,
+        default_value = "30s"
+    )]
+    pub tx_stuck_timeout: Duration,
+    /// Percentage by which the fees of a stuck transaction are increased when it is replaced
+    #[arg(
+        long,
+        env = "SC_TX_FEE_BUMP_PERCENT",
+        default_value_t = 20,
+        value_parser = clap::value_parser!(u64).range(10..)
+    )]
+    pub tx_fee_bump_percent: u64,
+    /// Maximum fee per gas in wei a sidechain transaction may pay
+    #[arg(long, env = "SC_TX_MAX_FEE_PER_GAS", default_value_t = 500_000_000_000)]
+    pub tx_max_fee_per_gas: u128,
+    /// Time a sidechain transaction may stay pending at the fee ceiling before its submission
+    /// fails
+    #[arg(
+        long,
+        env = "SC_TX_FEE_CEILING_TIMEOUT",
+        value_parser = humantime::parse_duration,
+        default_value = "10m"
+    )]
+    pub tx_fee_ceiling_timeout: Duration,
+}
+
+/// Thresholds above which the relayer is reported as not ready
+#[derive(clap::Args, Clone, Debug)]
+pub struct HealthConfig {
+    /// Maximum lag in blocks between the sidechain head and the last processed block
+    #[arg(long, env = "RELAYER_MAX_BLOCK_LAG", default_value_t = 100)]
+    pub max_block_lag: u64,
+    /// Maximum lag in checkpoints between the latest 1Money checkpoint and the last cleared one
+    #[arg(long, env = "RELAYER_MAX_CHECKPOINT_LAG", default_value_t = 100)]
+    pub max_checkpoint_lag: u64,
+}
+
+/// Restart policy of the relayer flows
+#[derive(clap::Args, Clone, Debug)]
+pub struct SupervisorConfig {
+    /// Number of consecutive failures of a flow after which the relayer exits
+    #[arg(long, env = "RELAYER_FLOW_MAX_RESTARTS", default_value_t = 5)]
+    pub flow_max_restarts: u32,
+    /// Delay before the first restart of a failed flow, doubled after every further failure
+    #[arg(
+        long,
+        env = "RELAYER_FLOW_RESTART_BACKOFF",
+        value_parser = humantime::parse_duration,
+        default_value = "1s"
+    )]
+    pub flow_restart_backoff: Duration,
+    /// Maximum delay between two restarts of a failed flow
+    #[arg(
+        long,
+        env = "RELAYER_FLOW_MAX_RESTART_BACKOFF",
+        value_parser = humantime::parse_duration,
+        default_value = "1m"
+    )]
+    pub flow_max_restart_backoff: Duration,
+}
+
+/// Simulation of the relayer writes
+#[derive(clap::Args, Clone, Debug)]
+pub struct DryRunConfig {
+    /// Simulate every write instead of submitting it and log its intended effect
+    #[arg(long, env = "RELAYER_DRY_RUN")]
+    pub dry_run: bool,
+    /// Simulate every write and report the ones not landed on chain by another relayer,
+    /// implies `--dry-run`
+    #[arg(long, env = "RELAYER_SHADOW")]
+    pub shadow: bool,
+    /// Time given to the other relayer to land a write before it is reported as a divergence
+    #[arg(
+        long,
+        env = "RELAYER_SHADOW_GRACE_PERIOD",
+        value_parser = humantime::parse_duration,
+        default_value = "2m"
+    )]
+    pub shadow_grace_period: Duration,
+}
+
+/// Voting power of the 1Money validators on the sidechain
+#[derive(clap::Args, Clone, Debug)]
+pub struct ValidatorPowerConfig {
+    /// Voting power of the validators whose power is given neither by the power file nor by
+    /// the epoch
+    #[arg(
+        long,
+        env = "RELAYER_DEFAULT_VALIDATOR_POWER",
+        value_parser = clap::value_parser!(u64).range(1..),
+        default_value_t = 100
+    )]
+    pub default_validator_power: u64,
+    /// JSON file mapping validator addresses to their voting power, taking precedence over the
+    /// power given by the epoch
+    #[arg(long, env = "RELAYER_VALIDATOR_POWER_FILE")]
+    pub validator_power_file: Option<PathBuf>,
+}
+
+impl DryRunConfig {
+    /// Whether writes are simulated instead of submitted.
+    pub const fn is_enabled(&self) -> bool {
+        self.dry_run || self.shadow
+    }
+}
+
+impl Default for OnemoneyWsConfig {
+    fn default() -> Self {
+        Self {
+            one_money_ws_heartbeat_timeout: Duration::from_secs(30),
+            one_money_ws_reconnect_backoff: Duration::from_secs(1),
+            one_money_ws_max_reconnect_backoff: Duration::from_secs(30),
+        }
+    }
+}
+
+impl Default for HealthConfig {
+    fn default() -> Self {
+        Self {
+            max_block_lag: 100,
+            max_checkpoint_lag: 100,
+        }
+    }
+}
+
+impl Default for SidechainTxConfig {
+    fn default() -> Self {
+        Self {
+            tx_stuck_timeout: Duration::from_secs(30),
+            tx_fee_bump_percent: 20,
+            tx_max_fee_per_gas: 500_000_000_000,
+            tx_fee_ceiling_timeout: Duration::from_secs(600),
+        }
+    }
+}
+
+impl Default for SupervisorConfig {
+    fn default() -> Self {
+        Self {
+            flow_max_restarts: 5,
+            flow_restart_backoff: Duration::from_secs(1),
+            flow_max_restart_backoff: Duration::from_secs(60),
+        }
+    }
+}
+
+impl Default for DryRunConfig {
+    fn default() -> Self {
+        Self {
+            dry_run: false,
+            shadow: false,
+            shadow_grace_period: Duration::from_secs(120),
+        }
+    }
 }
 
 impl Config {
-    /// Builds a shared relayer nonce by querying the sidechain for the latest.
-    pub async fn sidechain_relayer_nonce(&self) -> Result<RelayerNonce, ConfigError> {
-        let nonce = ProviderBuilder::new()
-            .connect_http(self.side_chain_http_url.clone())
-            .get_transaction_count(self.relayer_private_key.address())
-            .await?;
-        Ok(Arc::new(AtomicU64::new(nonce)))
+    /// Loads the signers of the relayer account and of every role from the configured keys.
+    pub fn signers(&self) -> Result<Signers, ConfigError> {
+        Ok(Signers::load(&self.signer)?)
+    }
+
+    /// Spawns the transaction manager owning the relayer accounts on the sidechain, checking
+    /// the shadowed 1Money writes on `onemoney`.
+    pub async fn sidechain_tx_manager(
+        &self,
+        signers: &Signers,
+        onemoney: Arc<dyn OneMoneyApi>,
+    ) -> Result<TxManager, ConfigError> {
+        let tx_manager = TxManager::spawn(
+            self.side_chain_http_url.clone(),
+            signers,
+            self.sidechain_tx.clone(),
+            DryRun::new(self, onemoney),
+        )
+        .await?;
+        Ok(tx_manager)
+    }
+
+    /// Creates the client of the 1Money node at `one_money_node_url`.
+    pub fn onemoney_api(&self) -> Result<Arc<dyn OneMoneyApi>, ConfigError> {
+        Ok(Arc::new(Client::custom(
+            self.one_money_node_url.to_string(),
+        )?))
+    }
+
+    /// Opens the state store at `state_path`, or an in-memory store if no path is configured.
+    ///
+    /// In dry-run mode the store is always kept in memory, so that simulated transfers are not
+    /// skipped once the relayer submits its writes.
+    pub fn state_store(&self) -> Result<StateStore, ConfigError> {
+        if self.dry_run.is_enabled() {
+            info!("Dry-run mode, relayer state is kept in memory");
+            return Ok(StateStore::in_memory());
+        }
+        match &self.state_path {
+            Some(path) => Ok(StateStore::open(path)?),
+            None => {
+                warn!("No state store path configured, relayer state will not survive restarts");
+                Ok(StateStore::in_memory())
+            }
+        }
     }
 }
@@ -0,0 +1,21 @@
+//! Clients and state shared by the relayer flows.
+
+use std::sync::Arc;
+
+use crate::config::Config;
+use crate::onemoney::api::OneMoneyApi;
+use crate::sidechain::tx_manager::TxManager;
+use crate::signer::RelayerSigner;
+use crate::store::StateStore;
+
+/// Clients and state built once on startup and shared by the incoming and outgoing flows.
+#[derive(Clone)]
+pub struct RelayerContext {
+    pub config: Config,
+    /// Relayer account signing the 1Money transactions.
+    pub signer: RelayerSigner,
+    pub tx_manager: TxManager,
+    pub store: StateStore,
+    /// 1Money node the flows read from and submit to.
+    pub onemoney: Arc<dyn OneMoneyApi>,
+}
@@ -0,0 +1,18 @@
+use std::path::PathBuf;
+
+use thiserror::Error;
+
+#[derive(Debug, Error)]
+pub enum Error {
+    #[error(transparent)]
+    Signer(#[from] crate::admin::error::Error),
+    #[error("Deployment failed: {0}")]
+    Deploy(#[from] onemoney_interop::error::Error),
+    #[error("Failed to encode the deployment manifest: {0}")]
+    Json(#[from] serde_json::Error),
+    #[error("Failed to write deployment manifest `{path}`: {source}")]
+    WriteManifest {
+        path: PathBuf,
+        source: std::io::Error,
+    },
+}
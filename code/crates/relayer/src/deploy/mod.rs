@@ -0,0 +1,201 @@
+//! Command line of `relayer-deploy`, deploying the interop stack on the sidechain.
+//!
+//! The contracts are deployed from the admin account by
+//! [`onemoney_interop::deploy::deploy`] and the resulting manifest is written as JSON. The
+//! relayer loads it with `--deployment`.
+
+use std::fs;
+use std::path::PathBuf;
+
+use alloy_primitives::Address;
+use alloy_provider::ProviderBuilder;
+use onemoney_interop::deploy::{
+    deploy, Deployment, DeploymentManifest, SidechainToken, TokenDeployment,
+};
+use url::Url;
+
+pub mod error;
+
+use error::Error as DeployError;
+
+use crate::admin::{AdminSignerConfig, InteropProtocol};
+
+/// Deploy the interop stack on the sidechain and write its deployment manifest
+#[derive(clap::Parser)]
+#[command(version, about, long_about = None)]
+pub struct DeployCli {
+    /// HTTP URL of the sidechain node to deploy to
+    #[arg(long, env = "SC_HTTP_URL", default_value = "http://127.0.0.1:8645")]
+    pub side_chain_http_url: Url,
+    /// URL of a remote signer exposing the Web3Signer API which holds the admin key
+    #[arg(long, env = "RELAYER_REMOTE_SIGNER_URL")]
+    pub remote_signer_url: Option<Url>,
+    /// Account deploying the contracts
+    #[command(flatten)]
+    pub signer: AdminSignerConfig,
+    /// Owner of the contracts
+    #[arg(long)]
+    pub owner: Address,
+    /// Operator of the interop contract and of the price oracle
+    #[arg(long)]
+    pub operator: Address,
+    /// Relayer account of the interop contract
+    #[arg(long)]
+    pub relayer: Address,
+    /// Account writing to the tx mapping contract, defaults to the relayer account
+    #[arg(long)]
+    pub tx_mapping_relayer: Option<Address>,
+    /// LayerZero endpoint of the OFT tokens, required with `--oft-token`
+    #[arg(long)]
+    pub lz_endpoint: Option<Address>,
+    /// Token already deployed on the sidechain, as `<OM_TOKEN>:<SC_TOKEN>`
+    #[arg(long = "token", value_parser = parse_token)]
+    pub tokens: Vec<TokenSpec>,
+    /// OFT token deployed for a 1Money token, as `<OM_TOKEN>:<NAME>:<SYMBOL>`
+    #[arg(long = "oft-token", value_parser = parse_oft_token)]
+    pub oft_tokens: Vec<TokenSpec>,
+    /// Protocol bridging the tokens
+    #[arg(long, value_enum, default_value = "layer-zero")]
+    pub interop_protocol: InteropProtocol,
+    /// Path of the written deployment manifest
+    #[arg(long, default_value = "deployment.json")]
+    pub output: PathBuf,
+}
+
+/// 1Money token given on the command line with its sidechain token.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TokenSpec {
+    pub om_token: Address,
+    pub sidechain_token: SidechainToken,
+}
+
+impl DeployCli {
+    /// Deploys the stack from the admin account and writes its manifest to `output`.
+    pub async fn run(self) -> Result<DeploymentManifest, DeployError> {
+        let signer = self.signer.load(self.remote_signer_url.as_ref())?;
+        let provider = ProviderBuilder::new()
+            .wallet(signer.wallet())
+            .connect_http(self.side_chain_http_url.clone());
+
+        let manifest = deploy(&provider, signer.address(), &self.deployment()).await?;
+
+        let encoded = serde_json::to_string_pretty(&manifest)?;
+        fs::write(&self.output, encoded).map_err(|source| DeployError::WriteManifest {
+            path: self.output.clone(),
+            source,
+        })?;
+        Ok(manifest)
+    }
+
+    fn deployment(&self) -> Deployment {
+        let tokens = self
+            .tokens
+            .iter()
+            .chain(&self.oft_tokens)
+            .map(|token| TokenDeployment {
+                om_token: token.om_token,
+                sidechain_token: token.sidechain_token.clone(),
+                interop_protocol: self.interop_protocol.id(),
+            })
+            .collect();
+
+        Deployment {
+            owner: self.owner,
+            operator: self.operator,
+            relayer: self.relayer,
+            tx_mapping_relayer: self.tx_mapping_relayer.unwrap_or(self.relayer),
+            lz_endpoint: self.lz_endpoint,
+            tokens,
+        }
+    }
+}
+
+fn parse_token(value: &str) -> Result<TokenSpec, String> {
+    let (om_token, sc_token) = value
+        .split_once(':')
+        .ok_or_else(|| "expected `<OM_TOKEN>:<SC_TOKEN>`".to_owned())?;
+    Ok(TokenSpec {
+        om_token: parse_address(om_token)?,
+        sidechain_token: SidechainToken::Existing(parse_address(sc_token)?),
+    })
+}
+
+fn parse_oft_token(value: &str) -> Result<TokenSpec, String> {
+    let mut parts = value.splitn(3, ':');
+    let (Some(om_token), Some(name), Some(symbol)) = (parts.next(), parts.next(), parts.next())
+    else {
+        return Err("expected `<OM_TOKEN>:<NAME>:<SYMBOL>`".to_owned());
+    };
+    Ok(TokenSpec {
+        om_token: parse_address(om_token)?,
+        sidechain_token: SidechainToken::Oft {
+            name: name.to_owned(),
+            symbol: symbol.to_owned(),
+        },
+    })
+}
+
+fn parse_address(value: &str) -> Result<Address, String> {
+    value
+        .parse()
+        .map_err(|e| format!("invalid address `{value}`: {e}"))
+}
+
+#[cfg(test)]
+mod tests {
+    use alloy_primitives::address;
+    use clap::Parser;
+
+    use super::*;
+
+    const OM_TOKEN: Address = address!("0x1111111111111111111111111111111111111111");
+    const SC_TOKEN: Address = address!("0x2222222222222222222222222222222222222222");
+    const RELAYER: Address = address!("0x3333333333333333333333333333333333333333");
+
+    #[test]
+    fn tokens_are_parsed_from_the_command_line() {
+        let cli = DeployCli::parse_from([
+            "relayer-deploy".to_owned(),
+            "--owner".to_owned(),
+            Address::ZERO.to_string(),
+            "--operator".to_owned(),
+            Address::ZERO.to_string(),
+            "--relayer".to_owned(),
+            RELAYER.to_string(),
+            "--token".to_owned(),
+            format!("{OM_TOKEN}:{SC_TOKEN}"),
+            "--oft-token".to_owned(),
+            format!("{SC_TOKEN}:Bridged Token:BTK"),
+            "--interop-protocol".to_owned(),
+            "mock".to_owned(),
+        ]);
+        let deployment = cli.deployment();
+
+        assert_eq!(deployment.tx_mapping_relayer, RELAYER);
+        assert_eq!(
+            deployment.tokens,
+            vec![
+                TokenDeployment {
+                    om_token: OM_TOKEN,
+                    sidechain_token: SidechainToken::Existing(SC_TOKEN),
+                    interop_protocol: 1,
+                },
+                TokenDeployment {
+                    om_token: SC_TOKEN,
+                    sidechain_token: SidechainToken::Oft {
+                        name: "Bridged Token".to_owned(),
+                        symbol: "BTK".to_owned(),
+                    },
+                    interop_protocol: 1,
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn malformed_tokens_are_rejected() {
+        assert!(parse_token(&OM_TOKEN.to_string()).is_err());
+        assert!(parse_token(&format!("{OM_TOKEN}:0x12")).is_err());
+        assert!(parse_oft_token(&format!("{OM_TOKEN}:Bridged Token")).is_err());
+    }
+}
@@ -0,0 +1,9 @@
+#[derive(Debug, thiserror::Error)]
+pub enum Error {
+    #[error("Failed to query the sidechain contracts: {0}")]
+    Contract(#[from] alloy_contract::Error),
+    #[error("Failed to query the sidechain: {0}")]
+    Rpc(#[from] alloy_transport::RpcError<alloy_transport::TransportErrorKind>),
+    #[error("Failed to query 1Money: {0}")]
+    Onemoney(#[from] onemoney_protocol::error::Error),
+}
@@ -0,0 +1,548 @@
+//! Dry-run and shadow modes of the relayer.
+//!
+//! In dry-run mode no write leaves the relayer. Sidechain transactions are executed with
+//! `eth_call` against the latest block and 1Money transactions are only logged. The flows
+//! continue with placeholder hashes derived from the simulated writes.
+//!
+//! The shadow mode runs next to the production relayer. Every simulated write is checked
+//! again after `--shadow-grace-period`, and writes which the production relayer did not land,
+//! or landed with other values, are reported as divergences.
+
+use core::time::Duration;
+use std::sync::Arc;
+
+use alloy_consensus::{Eip658Value, Receipt, ReceiptEnvelope, ReceiptWithBloom};
+use alloy_primitives::{keccak256, Address, Bloom, B256, U256};
+use alloy_provider::{Provider, ProviderBuilder};
+use alloy_rpc_types_eth::{TransactionReceipt, TransactionRequest};
+use alloy_sol_types::SolInterface;
+use onemoney_interop::contract::OMInterop::OMInteropCalls;
+use onemoney_interop::contract::TxHashMapping::TxHashMappingCalls;
+use onemoney_interop::contract::{OMInterop, TxHashMapping};
+use onemoney_protocol::{PaymentPayload, TokenBridgeAndMintPayload, TxPayload};
+use serde::Serialize;
+use tracing::{debug, info, warn};
+use url::Url;
+
+pub mod error;
+
+use error::Error as DryRunError;
+
+use crate::config::Config;
+use crate::metrics::metrics;
+use crate::onemoney::api::OneMoneyApi;
+use crate::sidechain::error::TransactionError;
+use crate::signer::Role;
+
+/// Handle simulating the relayer writes, set when `--dry-run` or `--shadow` is enabled.
+#[derive(Clone)]
+pub struct DryRun {
+    side_chain_http_url: Url,
+    /// 1Money node the shadowed writes are checked on.
+    onemoney: Arc<dyn OneMoneyApi>,
+    interop_contract_address: Address,
+    tx_mapping_contract_address: Address,
+    /// Grace period of the shadow checks, `None` without `--shadow`.
+    shadow_grace_period: Option<Duration>,
+}
+
+/// 1Money transaction intended by the relayer, compared with the transaction linked to its
+/// source hash in `TxHashMapping`.
+#[derive(Debug, Clone, Copy)]
+enum OnemoneyWrite {
+    /// `TokenBridgeAndMint` relaying a sidechain `bridgeFrom`.
+    Deposit {
+        recipient: Address,
+        value: U256,
+        token: Address,
+    },
+    /// `TokenTransfer` refunding a `BurnAndBridge`.
+    Refund {
+        recipient: Address,
+        value: U256,
+        token: Address,
+    },
+}
+
+impl OnemoneyWrite {
+    const fn as_str(self) -> &'static str {
+        match self {
+            Self::Deposit { .. } => "deposit",
+            Self::Refund { .. } => "refund",
+        }
+    }
+
+    /// Whether the landed transaction `data` has the intended effect.
+    fn matches(self, data: &TxPayload) -> bool {
+        let (landed_recipient, landed_value, landed_token) = match (self, data) {
+            (
+                Self::Deposit { .. },
+                TxPayload::TokenBridgeAndMint {
+                    recipient,
+                    value,
+                    token,
+                    ..
+                },
+            ) => (recipient, value, Some(token)),
+            (
+                Self::Refund { .. },
+                TxPayload::TokenTransfer {
+                    recipient,
+                    value,
+                    token,
+                },
+            ) => (recipient, value, token.as_ref()),
+            _ => return false,
+        };
+        let (Self::Deposit {
+            recipient,
+            value,
+            token,
+        }
+        | Self::Refund {
+            recipient,
+            value,
+            token,
+        }) = self;
+        *landed_recipient == recipient
+            && *landed_value == value.to_string()
+            && landed_token == Some(&token)
+    }
+}
+
+/// Sidechain write intended by the relayer, identified by the contract state it changes.
+#[derive(Debug, Clone, Copy)]
+enum SidechainWrite {
+    /// `registerDeposit` of a `bridgeFrom` hash.
+    RegisterDeposit(B256),
+    /// `registerWithdrawal` of a `BurnAndBridge` hash.
+    RegisterWithdrawal(B256),
+    /// `linkDepositHashes` of a `bridgeFrom` hash.
+    LinkDeposit(B256),
+    /// `linkWithdrawalHashes` of a `BurnAndBridge` hash.
+    LinkWithdrawal(B256),
+    /// `linkRefundHashes` of a `BurnAndBridge` hash.
+    LinkRefund(B256),
+    /// `bridgeTo` of the `BurnAndBridge` of `from` with nonce `bb_nonce`.
+    BridgeTo { from: Address, bb_nonce: u64 },
+    /// `updateCheckpointInfo` of a checkpoint.
+    CheckpointInfo { checkpoint_id: u64 },
+    /// Any other write, e.g. the validator manager updates.
+    Other,
+}
+
+impl SidechainWrite {
+    const fn as_str(self) -> &'static str {
+        match self {
+            Self::RegisterDeposit(_) => "register_deposit",
+            Self::RegisterWithdrawal(_) => "register_withdrawal",
+            Self::LinkDeposit(_) => "link_deposit",
+            Self::LinkWithdrawal(_) => "link_withdrawal",
+            Self::LinkRefund(_) => "link_refund",
+            Self::BridgeTo { .. } => "bridge_to",
+            Self::CheckpointInfo { .. } => "checkpoint_info",
+            Self::Other => "sidechain",
+        }
+    }
+}
+
+impl DryRun {
+    /// Returns the dry-run handle if `--dry-run` or `--shadow` is enabled.
+    pub fn new(config: &Config, onemoney: Arc<dyn OneMoneyApi>) -> Option<Self> {
+        if !config.dry_run.is_enabled() {
+            return None;
+        }
+        warn!(
+            shadow = config.dry_run.shadow,
+            "Dry-run mode, relayer writes are simulated and never submitted"
+        );
+
+        Some(Self {
+            side_chain_http_url: config.side_chain_http_url.clone(),
+            onemoney,
+            interop_contract_address: config.interop_contract_address,
+            tx_mapping_contract_address: config.tx_mapping_contract_address,
+            shadow_grace_period: config
+                .dry_run
+                .shadow
+                .then_some(config.dry_run.shadow_grace_period),
+        })
+    }
+
+    /// Executes `request` from `from` with `eth_call` instead of submitting it.
+    ///
+    /// A simulation which reverts fails like the submission would. Otherwise the returned
+    /// receipt carries a placeholder hash and no block.
+    pub async fn simulate_transaction(
+        &self,
+        role: Role,
+        from: Address,
+        request: TransactionRequest,
+    ) -> Result<TransactionReceipt, TransactionError> {
+        let provider = ProviderBuilder::new().connect_http(self.side_chain_http_url.clone());
+        let request = request.from(from);
+        provider
+            .call(request.clone())
+            .await
+            .map_err(|e| TransactionError::Send(e.into()))?;
+
+        let to = request.to.and_then(|to| to.to().copied());
+        let input = request.input.input().cloned().unwrap_or_default();
+        let placeholder_hash = keccak256(
+            [
+                from.as_slice(),
+                to.unwrap_or_default().as_slice(),
+                input.as_ref(),
+            ]
+            .concat(),
+        );
+        info!(
+            %role,
+            %from,
+            ?to,
+            %input,
+            %placeholder_hash,
+            "Dry-run: simulated sidechain transaction"
+        );
+
+        if let Some(grace_period) = self.shadow_grace_period {
+            let write = self.sidechain_write(to, &input);
+            tokio::spawn(check_sidechain_write(
+                self.clone(),
+                provider,
+                role,
+                write,
+                request,
+                placeholder_hash,
+                grace_period,
+            ));
+        }
+
+        Ok(simulated_receipt(placeholder_hash, from, to))
+    }
+
+    /// Decodes the write made by a transaction to `to` with `input`.
+    fn sidechain_write(&self, to: Option<Address>, input: &[u8]) -> SidechainWrite {
+        if to == Some(self.tx_mapping_contract_address) {
+            match TxHashMappingCalls::abi_decode(input) {
+                Ok(TxHashMappingCalls::registerDeposit(call)) => {
+                    return SidechainWrite::RegisterDeposit(call.bridgeFromTxHash);
+                }
+                Ok(TxHashMappingCalls::registerWithdrawal(call)) => {
+                    return SidechainWrite::RegisterWithdrawal(call.burnAndBridgeTxHash);
+                }
+                Ok(TxHashMappingCalls::linkDepositHashes(call)) => {
+                    return SidechainWrite::LinkDeposit(call.bridgeFromTxHash);
+                }
+                Ok(TxHashMappingCalls::linkWithdrawalHashes(call)) => {
+                    return SidechainWrite::LinkWithdrawal(call.burnAndBridgeTxHash);
+                }
+                Ok(TxHashMappingCalls::linkRefundHashes(call)) => {
+                    return SidechainWrite::LinkRefund(call.burnAndBridgeTxHash);
+                }
+                _ => {}
+            }
+        } else if to == Some(self.interop_contract_address) {
+            match OMInteropCalls::abi_decode(input) {
+                Ok(OMInteropCalls::bridgeTo(call)) => {
+                    return SidechainWrite::BridgeTo {
+                        from: call.from,
+                        bb_nonce: call.bbNonce,
+                    };
+                }
+                Ok(OMInteropCalls::updateCheckpointInfo(call)) => {
+                    return SidechainWrite::CheckpointInfo {
+                        checkpoint_id: call.checkpointId,
+                    };
+                }
+                _ => {}
+            }
+        }
+        SidechainWrite::Other
+    }
+
+    /// Logs the `TokenBridgeAndMint` transaction relaying the `bridgeFrom` with hash
+    /// `source_hash` instead of submitting it, and returns its placeholder hash.
+    pub fn bridge_and_mint(&self, payload: &TokenBridgeAndMintPayload, source_hash: B256) -> B256 {
+        let write = OnemoneyWrite::Deposit {
+            recipient: payload.recipient,
+            value: payload.value,
+            token: payload.token,
+        };
+        self.onemoney_write(write, payload, source_hash)
+    }
+
+    /// Logs the refund `Payment` of the `BurnAndBridge` with hash `source_hash` instead of
+    /// submitting it, and returns its placeholder hash.
+    pub fn send_payment(&self, payload: &PaymentPayload, source_hash: B256) -> B256 {
+        let write = OnemoneyWrite::Refund {
+            recipient: payload.recipient,
+            value: payload.value,
+            token: payload.token,
+        };
+        self.onemoney_write(write, payload, source_hash)
+    }
+
+    fn onemoney_write<P: Serialize>(
+        &self,
+        write: OnemoneyWrite,
+        payload: &P,
+        source_hash: B256,
+    ) -> B256 {
+        let encoded = serde_json::to_string(payload).unwrap_or_default();
+        let placeholder_hash = keccak256(&encoded);
+        info!(
+            kind = write.as_str(),
+            %source_hash,
+            payload = %encoded,
+            %placeholder_hash,
+            "Dry-run: skipped 1Money transaction"
+        );
+
+        if let Some(grace_period) = self.shadow_grace_period {
+            tokio::spawn(check_onemoney_write(
+                self.clone(),
+                write,
+                source_hash,
+                grace_period,
+            ));
+        }
+
+        placeholder_hash
+    }
+}
+
+/// Compares the sidechain write intended by `request` with the contract state once the grace
+/// period is over, and reports it as a divergence if the production relayer did not land it.
+async fn check_sidechain_write<P: Provider>(
+    dry_run: DryRun,
+    provider: P,
+    role: Role,
+    write: SidechainWrite,
+    request: TransactionRequest,
+    placeholder_hash: B256,
+    grace_period: Duration,
+) {
+    tokio::time::sleep(grace_period).await;
+
+    let kind = write.as_str();
+    match landed_sidechain_write(&dry_run, &provider, write, request).await {
+        Ok(true) => {
+            debug!(%role, kind, %placeholder_hash, "Shadow: sidechain transaction landed");
+        }
+        Ok(false) => {
+            metrics().shadow_divergence(kind);
+            warn!(
+                %role,
+                kind,
+                %placeholder_hash,
+                intended = ?write,
+                "Shadow divergence: simulated sidechain transaction did not land"
+            );
+        }
+        Err(e) => {
+            warn!(
+                %role,
+                kind,
+                %placeholder_hash,
+                error = %e,
+                "Failed to check shadowed sidechain transaction"
+            );
+        }
+    }
+}
+
+/// Whether the contract state shows `write` as landed.
+///
+/// Writes without a state to compare, i.e. the validator manager updates, are executed again
+/// with `eth_call` and count as landed if the contract now rejects them.
+async fn landed_sidechain_write<P: Provider>(
+    dry_run: &DryRun,
+    provider: &P,
+    write: SidechainWrite,
+    request: TransactionRequest,
+) -> Result<bool, DryRunError> {
+    let mapping_contract = TxHashMapping::new(dry_run.tx_mapping_contract_address, provider);
+    let interop_contract = OMInterop::new(dry_run.interop_contract_address, provider);
+
+    let landed = match write {
+        SidechainWrite::RegisterDeposit(hash) => {
+            mapping_contract.depositExists(hash).call().await?
+        }
+        SidechainWrite::RegisterWithdrawal(hash) => {
+            mapping_contract.withdrawalExists(hash).call().await?
+        }
+        SidechainWrite::LinkDeposit(hash) => !mapping_contract
+            .getLinkedDeposit(hash)
+            .call()
+            .await?
+            .is_zero(),
+        SidechainWrite::LinkWithdrawal(hash) => !mapping_contract
+            .getLinkedWithdrawal(hash)
+            .call()
+            .await?
+            .is_zero(),
+        SidechainWrite::LinkRefund(hash) => !mapping_contract
+            .getLinkedRefund(hash)
+            .call()
+            .await?
+            .is_zero(),
+        SidechainWrite::BridgeTo { from, bb_nonce } => {
+            interop_contract
+                .getLatestProcessedNonce(from)
+                .call()
+                .await?
+                > bb_nonce
+        }
+        SidechainWrite::CheckpointInfo { checkpoint_id } => {
+            let tally = interop_contract
+                .getCheckpointTally(checkpoint_id)
+                .call()
+                .await?;
+            // Completed checkpoints are pruned, along with their tally
+            tally.certified != 0
+                || interop_contract
+                    .getLatestCompletedCheckpoint()
+                    .call()
+                    .await?
+                    >= checkpoint_id
+        }
+        SidechainWrite::Other => match provider.call(request).await {
+            Ok(_) => false,
+            Err(e) if e.as_error_resp().is_some() => true,
+            Err(e) => return Err(e.into()),
+        },
+    };
+    Ok(landed)
+}
+
+/// Compares the 1Money write intended for `source_hash` with the transaction the production
+/// relayer linked to it in `TxHashMapping` once the grace period is over.
+async fn check_onemoney_write(
+    dry_run: DryRun,
+    write: OnemoneyWrite,
+    source_hash: B256,
+    grace_period: Duration,
+) {
+    tokio::time::sleep(grace_period).await;
+
+    let kind = write.as_str();
+    match landed_onemoney_write(&dry_run, write, source_hash).await {
+        Ok(Some(data)) if write.matches(&data) => {
+            debug!(kind, %source_hash, "Shadow: 1Money transaction landed");
+        }
+        Ok(Some(data)) => {
+            metrics().shadow_divergence(kind);
+            warn!(
+                kind,
+                %source_hash,
+                intended = ?write,
+                landed = ?data,
+                "Shadow divergence: 1Money transaction landed with other values"
+            );
+        }
+        Ok(None) => {
+            metrics().shadow_divergence(kind);
+            warn!(
+                kind,
+                %source_hash,
+                intended = ?write,
+                "Shadow divergence: 1Money transaction did not land"
+            );
+        }
+        Err(e) => {
+            warn!(kind, %source_hash, error = %e, "Failed to check shadowed 1Money transaction");
+        }
+    }
+}
+
+/// Fetches the 1Money transaction linked to `source_hash` in `TxHashMapping`, if any.
+async fn landed_onemoney_write(
+    dry_run: &DryRun,
+    write: OnemoneyWrite,
+    source_hash: B256,
+) -> Result<Option<TxPayload>, DryRunError> {
+    let provider = ProviderBuilder::new().connect_http(dry_run.side_chain_http_url.clone());
+    let mapping_contract = TxHashMapping::new(dry_run.tx_mapping_contract_address, provider);
+
+    let linked_hash = match write {
+        OnemoneyWrite::Deposit { .. } => {
+            mapping_contract
+                .getDepositByBridgeFrom(source_hash)
+                .call()
+                .await?
+                .linked
+        }
+        OnemoneyWrite::Refund { .. } => {
+            mapping_contract.getLinkedRefund(source_hash).call().await?
+        }
+    };
+    if linked_hash.is_zero() {
+        return Ok(None);
+    }
+
+    let transaction = dry_run.onemoney.transaction_by_hash(linked_hash).await?;
+    Ok(Some(transaction.data))
+}
+
+fn simulated_receipt(
+    transaction_hash: B256,
+    from: Address,
+    to: Option<Address>,
+) -> TransactionReceipt {
+    TransactionReceipt {
+        inner: ReceiptEnvelope::Eip1559(ReceiptWithBloom {
+            receipt: Receipt {
+                status: Eip658Value::Eip658(true),
+                cumulative_gas_used: 0,
+                logs: Vec::new(),
+            },
+            logs_bloom: Bloom::ZERO,
+        }),
+        transaction_hash,
+        transaction_index: None,
+        block_hash: None,
+        block_number: None,
+        gas_used: 0,
+        effective_gas_price: 0,
+        blob_gas_used: None,
+        blob_gas_price: None,
+        from,
+        to,
+        contract_address: None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloy_primitives::address;
+
+    use super::*;
+
+    const RECIPIENT: Address = address!("0x1111111111111111111111111111111111111111");
+    const TOKEN: Address = address!("0x2222222222222222222222222222222222222222");
+
+    #[test]
+    fn landed_writes_are_compared_with_intended_ones() {
+        let write = OnemoneyWrite::Refund {
+            recipient: RECIPIENT,
+            value: U256::from(100),
+            token: TOKEN,
+        };
+        let landed = |value: &str| TxPayload::TokenTransfer {
+            value: value.to_owned(),
+            recipient: RECIPIENT,
+            token: Some(TOKEN),
+        };
+
+        assert!(write.matches(&landed("100")));
+        assert!(!write.matches(&landed("99")));
+
+        let deposit = OnemoneyWrite::Deposit {
+            recipient: RECIPIENT,
+            value: U256::from(100),
+            token: TOKEN,
+        };
+        assert!(!deposit.matches(&landed("100")));
+    }
+}
@@ -1,3 +1,5 @@
+use core::time::Duration;
+
 use thiserror::Error;
 
 #[derive(Debug, Error)]
@@ -11,7 +13,17 @@ pub enum Error {
     #[error(transparent)]
     Poa(#[from] crate::poa::error::Error),
     #[error(transparent)]
+    Server(#[from] crate::server::error::Error),
+    #[error(transparent)]
     Incoming(#[from] crate::incoming::error::Error),
     #[error(transparent)]
     Outgoing(#[from] crate::outgoing::error::Error),
+    #[error(transparent)]
+    Admin(#[from] crate::admin::error::Error),
+    #[error(transparent)]
+    Trace(#[from] crate::trace::error::Error),
+    #[error(transparent)]
+    Store(#[from] crate::store::error::Error),
+    #[error("In-flight transfers did not finish within the shutdown timeout of {}", humantime::format_duration(*.0))]
+    ShutdownTimeout(Duration),
 }
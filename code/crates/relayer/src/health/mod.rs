@@ -0,0 +1,398 @@
+//! Liveness and readiness of the relayer flows.
+//!
+//! `/healthz` reports whether every running flow is alive, `/readyz` additionally requires
+//! the startup recovery to be finished and the flows to be caught up with both chains.
+
+use core::fmt::Display;
+use core::future::Future;
+use core::sync::atomic::{AtomicBool, Ordering};
+use std::collections::BTreeMap;
+use std::sync::{Arc, Mutex, PoisonError};
+
+use alloy_provider::{Provider, ProviderBuilder};
+use axum::extract::State;
+use axum::http::StatusCode;
+use axum::response::IntoResponse;
+use axum::routing::get;
+use axum::{Json, Router};
+use serde::Serialize;
+use tracing::warn;
+
+use crate::config::Config;
+use crate::onemoney::api::OneMoneyApi;
+use crate::store::StateStore;
+
+/// Relayer flow reported by the health endpoints.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Flow {
+    /// Validator set updates from 1Money epochs.
+    Poa,
+    /// Live stream of sidechain events.
+    IncomingStream,
+    /// Periodic clearing of sidechain blocks.
+    IncomingClearing,
+    /// Live stream of 1Money certified transactions.
+    OutgoingStream,
+    /// Periodic clearing of 1Money checkpoints.
+    OutgoingClearing,
+}
+
+impl Flow {
+    pub const fn as_str(self) -> &'static str {
+        match self {
+            Self::Poa => "poa",
+            Self::IncomingStream => "incoming_stream",
+            Self::IncomingClearing => "incoming_clearing",
+            Self::OutgoingStream => "outgoing_stream",
+            Self::OutgoingClearing => "outgoing_clearing",
+        }
+    }
+
+    fn follows_sidechain(self) -> bool {
+        matches!(self, Self::IncomingStream | Self::IncomingClearing)
+    }
+
+    fn follows_checkpoints(self) -> bool {
+        matches!(self, Self::OutgoingClearing)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(tag = "status", rename_all = "snake_case")]
+pub enum FlowStatus {
+    Running,
+    /// The flow failed with a recoverable error and is restarted after a backoff.
+    Restarting {
+        error: String,
+    },
+    /// The flow returned without an error.
+    Stopped,
+    Failed {
+        error: String,
+    },
+}
+
+/// Distance between a chain head and the last position processed by the relayer.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct Lag {
+    /// `None` if either position is unknown.
+    pub current: Option<u64>,
+    pub max: u64,
+}
+
+impl Lag {
+    fn within_threshold(self) -> bool {
+        self.current.is_some_and(|current| current <= self.max)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct HealthReport {
+    pub healthy: bool,
+    pub ready: bool,
+    /// Whether the startup recovery has finished.
+    pub recovered: bool,
+    pub flows: BTreeMap<Flow, FlowStatus>,
+    /// Number of times each flow was restarted after a failure.
+    pub restarts: BTreeMap<Flow, u32>,
+    /// Lag between the sidechain head and the last scanned block, if an incoming flow runs.
+    pub sidechain_block_lag: Option<Lag>,
+    /// Lag between the latest 1Money checkpoint and the last cleared checkpoint, if the
+    /// outgoing clearing flow runs.
+    pub checkpoint_lag: Option<Lag>,
+}
+
+impl HealthReport {
+    fn new(
+        recovered: bool,
+        flows: BTreeMap<Flow, FlowStatus>,
+        restarts: BTreeMap<Flow, u32>,
+        sidechain_block_lag: Option<Lag>,
+        checkpoint_lag: Option<Lag>,
+    ) -> Self {
+        // A flow waiting to be restarted is still alive but not making progress
+        let healthy = flows
+            .values()
+            .all(|status| matches!(status, FlowStatus::Running | FlowStatus::Restarting { .. }));
+        let ready = flows
+            .values()
+            .all(|status| matches!(status, FlowStatus::Running))
+            && recovered
+            && sidechain_block_lag.is_none_or(Lag::within_threshold)
+            && checkpoint_lag.is_none_or(Lag::within_threshold);
+
+        Self {
+            healthy,
+            ready,
+            recovered,
+            flows,
+            restarts,
+            sidechain_block_lag,
+            checkpoint_lag,
+        }
+    }
+}
+
+/// Shared handle tracking the status of the relayer flows.
+#[derive(Clone)]
+pub struct Health {
+    inner: Arc<Inner>,
+}
+
+struct Inner {
+    config: Config,
+    store: StateStore,
+    onemoney: Arc<dyn OneMoneyApi>,
+    recovered: AtomicBool,
+    flows: Mutex<BTreeMap<Flow, FlowStatus>>,
+    restarts: Mutex<BTreeMap<Flow, u32>>,
+}
+
+impl Health {
+    /// Creates the health of the flows run with `config`, reading the latest checkpoint from
+    /// `onemoney`.
+    pub fn new(config: &Config, store: &StateStore, onemoney: Arc<dyn OneMoneyApi>) -> Self {
+        Self {
+            inner: Arc::new(Inner {
+                config: config.clone(),
+                store: store.clone(),
+                onemoney,
+                recovered: AtomicBool::new(false),
+                flows: Mutex::new(BTreeMap::new()),
+                restarts: Mutex::new(BTreeMap::new()),
+            }),
+        }
+    }
+
+    /// Marks the startup recovery as finished.
+    pub fn set_recovered(&self) {
+        self.inner.recovered.store(true, Ordering::Relaxed);
+    }
+
+    /// Runs `flow_future`, reporting `flow` as running until it returns.
+    pub async fn track<T, E, F>(&self, flow: Flow, flow_future: F) -> Result<T, E>
+    where
+        E: Display,
+        F: Future<Output = Result<T, E>>,
+    {
+        self.set_status(flow, FlowStatus::Running);
+        let result = flow_future.await;
+        self.set_status(
+            flow,
+            match &result {
+                Ok(_) => FlowStatus::Stopped,
+                Err(err) => FlowStatus::Failed {
+                    error: err.to_string(),
+                },
+            },
+        );
+        result
+    }
+
+    /// Reports `flow` as waiting to be restarted after failing with `error`.
+    ///
+    /// Returns the number of times the flow was restarted, including this restart.
+    pub fn record_restart(&self, flow: Flow, error: &impl Display) -> u32 {
+        self.set_status(
+            flow,
+            FlowStatus::Restarting {
+                error: error.to_string(),
+            },
+        );
+        let mut restarts = self
+            .inner
+            .restarts
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner);
+        let count = restarts.entry(flow).or_default();
+        *count += 1;
+        *count
+    }
+
+    /// Builds the current report, querying both chains for their latest positions.
+    pub async fn report(&self) -> HealthReport {
+        let recovered = self.inner.recovered.load(Ordering::Relaxed);
+        let flows = self
+            .inner
+            .flows
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .clone();
+        let restarts = self
+            .inner
+            .restarts
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .clone();
+        let config = &self.inner.config;
+        let state = self.inner.store.snapshot();
+
+        let sidechain_block_lag = if flows.keys().any(|flow| flow.follows_sidechain()) {
+            let head = self.sidechain_head().await;
+            Some(Lag {
+                current: head
+                    .zip(state.sidechain_scanned_block())
+                    .map(|(head, scanned)| head.saturating_sub(scanned)),
+                max: config.health.max_block_lag,
+            })
+        } else {
+            None
+        };
+        let checkpoint_lag = if flows.keys().any(|flow| flow.follows_checkpoints()) {
+            let head = self.latest_checkpoint().await;
+            Some(Lag {
+                current: head
+                    .zip(state.checkpoint_cursor)
+                    .map(|(head, cursor)| head.saturating_sub(cursor)),
+                max: config.health.max_checkpoint_lag,
+            })
+        } else {
+            None
+        };
+
+        HealthReport::new(
+            recovered,
+            flows,
+            restarts,
+            sidechain_block_lag,
+            checkpoint_lag,
+        )
+    }
+
+    fn set_status(&self, flow: Flow, status: FlowStatus) {
+        self.inner
+            .flows
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .insert(flow, status);
+    }
+
+    async fn sidechain_head(&self) -> Option<u64> {
+        ProviderBuilder::new()
+            .connect_http(self.inner.config.side_chain_http_url.clone())
+            .get_block_number()
+            .await
+            .inspect_err(|err| warn!(%err, "Failed to query sidechain head for health report"))
+            .ok()
+    }
+
+    async fn latest_checkpoint(&self) -> Option<u64> {
+        self.inner
+            .onemoney
+            .latest_checkpoint_number()
+            .await
+            .inspect_err(|err| warn!(%err, "Failed to query 1Money checkpoint for health report"))
+            .ok()
+    }
+}
+
+async fn healthz(State(health): State<Health>) -> impl IntoResponse {
+    let report = health.report().await;
+    (status_code(report.healthy), Json(report))
+}
+
+async fn readyz(State(health): State<Health>) -> impl IntoResponse {
+    let report = health.report().await;
+    (status_code(report.ready), Json(report))
+}
+
+fn status_code(ok: bool) -> StatusCode {
+    if ok {
+        StatusCode::OK
+    } else {
+        StatusCode::SERVICE_UNAVAILABLE
+    }
+}
+
+/// Routes serving the health report on `/healthz` and `/readyz`.
+pub fn router(health: Health) -> Router {
+    Router::new()
+        .route("/healthz", get(healthz))
+        .route("/readyz", get(readyz))
+        .with_state(health)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn running(flows: &[Flow]) -> BTreeMap<Flow, FlowStatus> {
+        flows
+            .iter()
+            .map(|flow| (*flow, FlowStatus::Running))
+            .collect()
+    }
+
+    const fn lag(current: Option<u64>) -> Option<Lag> {
+        Some(Lag { current, max: 10 })
+    }
+
+    #[test]
+    fn ready_once_recovered_and_caught_up() {
+        let flows = running(&[Flow::IncomingStream, Flow::OutgoingClearing]);
+
+        let report = HealthReport::new(
+            false,
+            flows.clone(),
+            BTreeMap::new(),
+            lag(Some(0)),
+            lag(Some(10)),
+        );
+        assert!(report.healthy);
+        assert!(!report.ready);
+
+        let report = HealthReport::new(true, flows, BTreeMap::new(), lag(Some(0)), lag(Some(10)));
+        assert!(report.healthy);
+        assert!(report.ready);
+    }
+
+    #[test]
+    fn not_ready_when_lagging() {
+        let flows = running(&[Flow::IncomingStream, Flow::OutgoingClearing]);
+
+        let report = HealthReport::new(
+            true,
+            flows.clone(),
+            BTreeMap::new(),
+            lag(Some(11)),
+            lag(Some(0)),
+        );
+        assert!(report.healthy);
+        assert!(!report.ready);
+
+        let report = HealthReport::new(true, flows, BTreeMap::new(), lag(Some(0)), lag(None));
+        assert!(!report.ready);
+    }
+
+    #[test]
+    fn unhealthy_when_a_flow_failed() {
+        let mut flows = running(&[Flow::Poa]);
+        flows.insert(
+            Flow::OutgoingStream,
+            FlowStatus::Failed {
+                error: "websocket closed".to_owned(),
+            },
+        );
+
+        let report = HealthReport::new(true, flows, BTreeMap::new(), None, None);
+        assert!(!report.healthy);
+        assert!(!report.ready);
+    }
+
+    #[test]
+    fn healthy_but_not_ready_while_a_flow_restarts() {
+        let mut flows = running(&[Flow::Poa]);
+        flows.insert(
+            Flow::OutgoingStream,
+            FlowStatus::Restarting {
+                error: "websocket closed".to_owned(),
+            },
+        );
+        let restarts = BTreeMap::from([(Flow::OutgoingStream, 1)]);
+
+        let report = HealthReport::new(true, flows, restarts, None, None);
+        assert!(report.healthy);
+        assert!(!report.ready);
+    }
+}
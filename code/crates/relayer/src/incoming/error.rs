@@ -18,12 +18,18 @@ pub enum Error {
     Contract(#[from] alloy_contract::Error),
     #[error("Pending transaction failed: {0}")]
     PendingTransaction(#[from] alloy_provider::PendingTransactionError),
+    #[error(transparent)]
+    Transaction(#[from] crate::sidechain::error::TransactionError),
     #[error("Contract reverted: {0:?}")]
     MappingContractReverted(onemoney_interop::contract::TxHashMapping::TxHashMappingErrors),
     #[error(transparent)]
     RpcTransport(#[from] alloy_transport::RpcError<alloy_transport::TransportErrorKind>),
     #[error("Contract reverted: {0:?}")]
     ContractReverted(onemoney_interop::contract::OMInterop::OMInteropErrors),
+    #[error(transparent)]
+    Store(#[from] crate::store::error::Error),
+    #[error(transparent)]
+    Signer(#[from] crate::signer::error::Error),
     #[error("Generic error: {0}")]
     Generic(String),
 }
@@ -1,54 +1,55 @@
-use core::sync::atomic::Ordering;
-
 use alloy_primitives::hex::ToHexExt;
 use alloy_primitives::{Address, B256};
 use alloy_provider::ProviderBuilder;
-use alloy_signer_local::PrivateKeySigner;
 use onemoney_interop::contract::OMInterop::{OMInteropReceived, OMInteropSent};
 use onemoney_interop::contract::TxHashMapping;
-use onemoney_protocol::client::http::Client;
 use onemoney_protocol::error::Error as OnemoneyError;
-use onemoney_protocol::responses::TransactionResponse;
 use onemoney_protocol::{PaymentPayload, TokenBridgeAndMintPayload};
+use tokio::time::Instant;
 use tracing::{debug, warn};
 
-use crate::config::{Config, RelayerNonce};
+use crate::config::Config;
+use crate::dry_run::DryRun;
 use crate::incoming::error::Error as IncomingError;
+use crate::metrics::{metrics, Chain};
+use crate::onemoney::api::OneMoneyApi;
+use crate::sidechain::tx_manager::TxManager;
+use crate::signer::error::Error as SignerError;
+use crate::signer::{RelayerSigner, Role};
+use crate::store::{StateStore, TransferKind, TransferState};
 
 pub struct Relayer1MoneyContext<'a> {
-    client: &'a Client,
-    relayer_address: Address,
-    private_key_hex: String,
+    onemoney: &'a dyn OneMoneyApi,
+    signer: &'a RelayerSigner,
+    dry_run: Option<&'a DryRun>,
     chain_id: u64,
 }
 
 impl<'a> Relayer1MoneyContext<'a> {
+    /// Creates the context submitting 1Money transactions, only logged if `dry_run` is set.
     pub async fn new(
-        client: &'a Client,
-        relayer_signer: &PrivateKeySigner,
+        onemoney: &'a dyn OneMoneyApi,
+        signer: &'a RelayerSigner,
+        dry_run: Option<&'a DryRun>,
     ) -> Result<Self, IncomingError> {
-        let relayer_address = relayer_signer.address();
-        let private_key_hex = relayer_signer.to_bytes().encode_hex_with_prefix();
-        let chain_id = client.fetch_chain_id_from_network().await?;
+        let chain_id = onemoney.chain_id().await?;
 
         Ok(Self {
-            client,
-            relayer_address,
-            private_key_hex,
+            onemoney,
+            signer,
+            dry_run,
             chain_id,
         })
     }
 
-    fn private_key(&self) -> &str {
-        &self.private_key_hex
+    fn relayer_address(&self) -> Address {
+        self.signer.address()
     }
 
     pub async fn should_process_nonce(&self, sidechain_nonce: u64) -> Result<bool, IncomingError> {
-        let om_nonce = self
-            .client
-            .get_account_nonce(self.relayer_address)
-            .await?
-            .nonce;
+        let om_nonce = self.onemoney.account_nonce(self.relayer_address()).await?;
+        metrics().set_relayer_nonce(Chain::Onemoney, Role::Interop, om_nonce);
+        metrics().set_event_nonce(sidechain_nonce);
 
         if om_nonce > sidechain_nonce {
             warn!(
@@ -57,6 +58,14 @@ impl<'a> Relayer1MoneyContext<'a> {
                 "Layer 1 probably processed this nonce already: skip"
             );
             Ok(false)
+        } else if om_nonce < sidechain_nonce && self.dry_run.is_some() {
+            // Simulated transactions never consume their nonce on 1Money
+            debug!(
+                %sidechain_nonce,
+                %om_nonce,
+                "Dry-run: not waiting for earlier nonces"
+            );
+            Ok(true)
         } else if om_nonce < sidechain_nonce {
             warn!(
                 %sidechain_nonce,
@@ -65,11 +74,7 @@ impl<'a> Relayer1MoneyContext<'a> {
             );
             // TODO: Temporary workaround
             loop {
-                let current_nonce = self
-                    .client
-                    .get_account_nonce(self.relayer_address)
-                    .await?
-                    .nonce;
+                let current_nonce = self.onemoney.account_nonce(self.relayer_address()).await?;
                 if current_nonce == sidechain_nonce {
                     debug!(
                         %sidechain_nonce,
@@ -94,7 +99,8 @@ impl<'a> Relayer1MoneyContext<'a> {
     pub async fn handle_om_interop_received(
         &self,
         config: &Config,
-        relayer_nonce: RelayerNonce,
+        tx_manager: &TxManager,
+        store: &StateStore,
         OMInteropReceived {
             nonce: sidechain_nonce,
             to,
@@ -103,113 +109,143 @@ impl<'a> Relayer1MoneyContext<'a> {
             srcChainId: src_chain_id,
         }: OMInteropReceived,
         source_tx_hash: B256,
-    ) -> Result<Option<TransactionResponse>, IncomingError> {
-        let provider = ProviderBuilder::new()
-            .wallet(config.relayer_private_key.clone())
-            .connect_http(config.side_chain_http_url.clone());
+    ) -> Result<Option<B256>, IncomingError> {
+        let started = Instant::now();
+        let provider = ProviderBuilder::new().connect_http(config.side_chain_http_url.clone());
         let mapping_contract = TxHashMapping::new(config.tx_mapping_contract_address, provider);
 
-        debug!(bridgeFromHash = %source_tx_hash, "Will register deposit transaction hash");
+        let (registered, relayed) = match store.transfer(TransferKind::Deposit, source_tx_hash) {
+            Some(TransferState::Relayed { destination_hash }) => (true, Some(destination_hash)),
+            Some(TransferState::Registered) => (true, None),
+            Some(TransferState::Observed) => (false, None),
+            None => {
+                store
+                    .set_transfer(
+                        TransferKind::Deposit,
+                        source_tx_hash,
+                        TransferState::Observed,
+                    )
+                    .await?;
+                (false, None)
+            }
+        };
 
-        match mapping_contract
-            .registerDeposit(source_tx_hash)
-            .nonce(relayer_nonce.fetch_add(1, Ordering::SeqCst))
-            .send()
-            .await
-            .map(Ok)
-            .or_else(|e| {
-                e.try_decode_into_interface_error::<TxHashMapping::TxHashMappingErrors>()
-                    .map(Err)
-            })?
-            .map_err(IncomingError::MappingContractReverted)
-        {
-            Ok(pending_tx) => {
-                if let Err(e) = pending_tx.get_receipt().await {
+        // Registering again would revert, e.g. when the event is relayed again after a failed
+        // `BridgeAndMint`
+        if !registered {
+            debug!(bridgeFromHash = %source_tx_hash, "Will register deposit transaction hash");
+
+            match tx_manager
+                .send(
+                    Role::TxMapping,
+                    mapping_contract.registerDeposit(source_tx_hash),
+                )
+                .await
+                .map_err(|e| {
+                    e.try_decode_revert::<TxHashMapping::TxHashMappingErrors>()
+                        .map_or_else(IncomingError::from, IncomingError::MappingContractReverted)
+                }) {
+                Ok(_) => {
+                    store
+                        .set_transfer(
+                            TransferKind::Deposit,
+                            source_tx_hash,
+                            TransferState::Registered,
+                        )
+                        .await?;
+                }
+                Err(e) => {
                     warn!(
                         bridge_from_hash=%source_tx_hash,
                         error = %e,
-                        "Failed to retrieve `registerDeposit` receipt"
+                        "Failed to register deposit transaction hash"
                     );
                 }
             }
-            Err(e) => {
-                // If send failed, decrement the nonce
-                relayer_nonce.fetch_sub(1, Ordering::SeqCst);
-                warn!(
-                    bridge_from_hash=%source_tx_hash,
-                    error = %e,
-                    "Failed to register deposit transaction hash"
-                );
-            }
         }
 
-        let payload = TokenBridgeAndMintPayload {
-            chain_id: self.chain_id,
-            nonce: sidechain_nonce,
-            recipient: to,
-            value: amount,
-            token: om_token,
-            source_chain_id: src_chain_id.into(),
-            source_tx_hash: source_tx_hash.encode_hex_with_prefix(),
-            bridge_metadata: None,
-        };
+        // The `BridgeAndMint` was submitted before a restart, only the link is missing
+        let destination_hash = match relayed {
+            Some(destination_hash) => destination_hash,
+            None => {
+                let payload = TokenBridgeAndMintPayload {
+                    chain_id: self.chain_id,
+                    nonce: sidechain_nonce,
+                    recipient: to,
+                    value: amount,
+                    token: om_token,
+                    source_chain_id: src_chain_id.into(),
+                    source_tx_hash: source_tx_hash.encode_hex_with_prefix(),
+                    bridge_metadata: None,
+                };
 
-        let tx_response = match self
-            .client
-            .bridge_and_mint(payload, self.private_key())
-            .await
-        {
-            Ok(response) => response,
-            Err(e) if is_error_transaction_already_exists(&e) => {
-                warn!(%e, "Mint and Bridge transaction already exists on 1Money, skipping...");
-                return Ok(None);
+                let destination_hash = match self.dry_run {
+                    Some(dry_run) => Ok(dry_run.bridge_and_mint(&payload, source_tx_hash)),
+                    None => self
+                        .onemoney
+                        .bridge_and_mint(self.signer, payload)
+                        .await
+                        .map(|response| response.hash),
+                };
+                let destination_hash = match destination_hash {
+                    Ok(hash) => hash,
+                    Err(SignerError::Onemoney(e)) if is_error_transaction_already_exists(&e) => {
+                        warn!(%e, "Mint and Bridge transaction already exists on 1Money, skipping...");
+                        return Ok(None);
+                    }
+                    Err(e) => {
+                        metrics().submission_failed("onemoney");
+                        return Err(IncomingError::from(e));
+                    }
+                };
+                metrics().observe_relay_latency(TransferKind::Deposit, started.elapsed());
+
+                store
+                    .set_transfer(
+                        TransferKind::Deposit,
+                        source_tx_hash,
+                        TransferState::Relayed { destination_hash },
+                    )
+                    .await?;
+                destination_hash
             }
-            Err(e) => return Err(IncomingError::Onemoney(e)),
         };
 
-        debug!(bridgeFromHash = %source_tx_hash, bridgeAndMintHash = %tx_response.hash, "Will link deposit transaction hashes");
+        debug!(bridgeFromHash = %source_tx_hash, bridgeAndMintHash = %destination_hash, "Will link deposit transaction hashes");
 
-        match mapping_contract
-            .linkDepositHashes(source_tx_hash, tx_response.hash)
-            .nonce(relayer_nonce.fetch_add(1, Ordering::SeqCst))
-            .send()
+        match tx_manager
+            .send(
+                Role::TxMapping,
+                mapping_contract.linkDepositHashes(source_tx_hash, destination_hash),
+            )
             .await
-            .map(Ok)
-            .or_else(|e| {
-                e.try_decode_into_interface_error::<TxHashMapping::TxHashMappingErrors>()
-                    .map(Err)
-            })?
-            .map_err(IncomingError::MappingContractReverted)
-        {
-            Ok(pending_tx) => {
-                if let Err(e) = pending_tx.get_receipt().await {
-                    warn!(
-                        bridge_from_hash=%source_tx_hash,
-                        bridge_and_mint_hash=%tx_response.hash,
-                        error = %e,
-                        "Failed to retrieve `linkDepositHashes` receipt"
-                    );
-                }
+            .map_err(|e| {
+                e.try_decode_revert::<TxHashMapping::TxHashMappingErrors>()
+                    .map_or_else(IncomingError::from, IncomingError::MappingContractReverted)
+            }) {
+            Ok(_) => {
+                store
+                    .complete_transfer(TransferKind::Deposit, source_tx_hash)
+                    .await?;
             }
             Err(e) => {
-                // If send failed, decrement the nonce
-                relayer_nonce.fetch_sub(1, Ordering::SeqCst);
                 warn!(
                     bridge_from_hash=%source_tx_hash,
-                    bridge_and_mint_hash=%tx_response.hash,
+                    bridge_and_mint_hash=%destination_hash,
                     error = %e,
                     "Failed to link deposit hashes"
                 );
             }
         }
 
-        Ok(Some(tx_response))
+        Ok(Some(destination_hash))
     }
 
     pub async fn handle_om_interop_sent(
         &self,
         config: &Config,
-        relayer_nonce: RelayerNonce,
+        tx_manager: &TxManager,
+        store: &StateStore,
         OMInteropSent {
             nonce: sidechain_nonce,
             from,
@@ -218,66 +254,94 @@ impl<'a> Relayer1MoneyContext<'a> {
             dstChainId: _dst_chain_id,
             sourceHash: source_hash,
         }: OMInteropSent,
-    ) -> Result<Option<TransactionResponse>, IncomingError> {
-        let provider = ProviderBuilder::new()
-            .wallet(config.relayer_private_key.clone())
-            .connect_http(config.side_chain_http_url.clone());
+    ) -> Result<Option<B256>, IncomingError> {
+        let started = Instant::now();
+        let provider = ProviderBuilder::new().connect_http(config.side_chain_http_url.clone());
         let mapping_contract = TxHashMapping::new(config.tx_mapping_contract_address, provider);
 
-        let payload = PaymentPayload {
-            chain_id: self.chain_id,
-            nonce: sidechain_nonce,
-            recipient: from,
-            value: refund_amount,
-            token: om_token,
+        let relayed = match store.transfer(TransferKind::Refund, source_hash) {
+            Some(TransferState::Relayed { destination_hash }) => Some(destination_hash),
+            Some(_) => None,
+            None => {
+                store
+                    .set_transfer(TransferKind::Refund, source_hash, TransferState::Observed)
+                    .await?;
+                None
+            }
         };
 
-        let tx_response = match self.client.send_payment(payload, self.private_key()).await {
-            Ok(response) => response,
-            Err(e) if is_error_transaction_already_exists(&e) => {
-                warn!(%e, "Payment transaction already exists on 1Money, skipping...");
-                return Ok(None);
+        // The payment was submitted before a restart, only the link is missing
+        let destination_hash = match relayed {
+            Some(destination_hash) => destination_hash,
+            None => {
+                let payload = PaymentPayload {
+                    chain_id: self.chain_id,
+                    nonce: sidechain_nonce,
+                    recipient: from,
+                    value: refund_amount,
+                    token: om_token,
+                };
+
+                let destination_hash = match self.dry_run {
+                    Some(dry_run) => Ok(dry_run.send_payment(&payload, source_hash)),
+                    None => self
+                        .onemoney
+                        .send_payment(self.signer, payload)
+                        .await
+                        .map(|response| response.hash),
+                };
+                let destination_hash = match destination_hash {
+                    Ok(hash) => hash,
+                    Err(SignerError::Onemoney(e)) if is_error_transaction_already_exists(&e) => {
+                        warn!(%e, "Payment transaction already exists on 1Money, skipping...");
+                        return Ok(None);
+                    }
+                    Err(e) => {
+                        metrics().submission_failed("onemoney");
+                        return Err(IncomingError::from(e));
+                    }
+                };
+                metrics().observe_relay_latency(TransferKind::Refund, started.elapsed());
+
+                store
+                    .set_transfer(
+                        TransferKind::Refund,
+                        source_hash,
+                        TransferState::Relayed { destination_hash },
+                    )
+                    .await?;
+                destination_hash
             }
-            Err(e) => return Err(IncomingError::Onemoney(e)),
         };
 
-        debug!(burnAndBridgeHas = %source_hash, refundHash = %tx_response.hash, "Will link refund transaction hash");
+        debug!(burnAndBridgeHas = %source_hash, refundHash = %destination_hash, "Will link refund transaction hash");
 
-        match mapping_contract
-            .linkRefundHashes(source_hash, tx_response.hash)
-            .nonce(relayer_nonce.fetch_add(1, Ordering::SeqCst))
-            .send()
+        match tx_manager
+            .send(
+                Role::TxMapping,
+                mapping_contract.linkRefundHashes(source_hash, destination_hash),
+            )
             .await
-            .map(Ok)
-            .or_else(|e| {
-                e.try_decode_into_interface_error::<TxHashMapping::TxHashMappingErrors>()
-                    .map(Err)
-            })?
-            .map_err(IncomingError::MappingContractReverted)
-        {
-            Ok(pending_tx) => {
-                if let Err(e) = pending_tx.get_receipt().await {
-                    warn!(
-                        burn_and_bridge_hash=%source_hash,
-                        refund_hash=%tx_response.hash,
-                        error = %e,
-                        "Failed to retrieve `linkRefundHashes` receipt"
-                    );
-                }
+            .map_err(|e| {
+                e.try_decode_revert::<TxHashMapping::TxHashMappingErrors>()
+                    .map_or_else(IncomingError::from, IncomingError::MappingContractReverted)
+            }) {
+            Ok(_) => {
+                store
+                    .complete_transfer(TransferKind::Refund, source_hash)
+                    .await?;
             }
             Err(e) => {
-                // If send failed, decrement the nonce
-                relayer_nonce.fetch_sub(1, Ordering::SeqCst);
                 warn!(
                     burn_and_bridge_hash=%source_hash,
-                    refund_hash=%tx_response.hash,
+                    refund_hash=%destination_hash,
                     error = %e,
                     "Failed to link refund hash"
                 );
             }
         }
 
-        Ok(Some(tx_response))
+        Ok(Some(destination_hash))
     }
 }
 
@@ -295,3 +359,240 @@ fn is_error_transaction_already_exists(err: &OnemoneyError) -> bool {
         _ => false,
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::collections::VecDeque;
+    use std::sync::Mutex;
+
+    use alloy_primitives::{b256, U256};
+    use alloy_signer_local::PrivateKeySigner;
+    use alloy_sol_types::SolCall;
+    use async_trait::async_trait;
+    use clap::Parser;
+    use httpmock::prelude::*;
+    use onemoney_protocol::responses::{
+        Checkpoint, EpochResponse, TransactionReceipt, TransactionResponse,
+    };
+    use onemoney_protocol::Transaction;
+    use serde_json::json;
+
+    use super::*;
+    use crate::cli::Cli;
+    use crate::config::SignerConfig;
+    use crate::signer::Signers;
+
+    const CHAIN_ID: u64 = 1_212_101;
+
+    /// 1Money node reporting the successive `nonces` of the relayer account.
+    struct FakeOneMoney {
+        relayer: Address,
+        nonces: Mutex<VecDeque<u64>>,
+    }
+
+    impl FakeOneMoney {
+        fn new(relayer: Address, nonces: impl IntoIterator<Item = u64>) -> Self {
+            Self {
+                relayer,
+                nonces: Mutex::new(nonces.into_iter().collect()),
+            }
+        }
+    }
+
+    /// Error returned for the requests the nonce checks never make.
+    fn unsupported(what: &str) -> OnemoneyError {
+        OnemoneyError::custom(format!("{what} are not served by the fake 1Money node"))
+    }
+
+    #[async_trait]
+    impl OneMoneyApi for FakeOneMoney {
+        async fn account_nonce(&self, address: Address) -> Result<u64, OnemoneyError> {
+            assert_eq!(address, self.relayer);
+            let mut nonces = self.nonces.lock().expect("nonces are not poisoned");
+            let nonce = if nonces.len() > 1 {
+                nonces.pop_front()
+            } else {
+                nonces.front().copied()
+            };
+            Ok(nonce.expect("a nonce is reported"))
+        }
+
+        async fn chain_id(&self) -> Result<u64, OnemoneyError> {
+            Ok(CHAIN_ID)
+        }
+
+        async fn current_epoch(&self) -> Result<EpochResponse, OnemoneyError> {
+            Err(unsupported("epochs"))
+        }
+
+        async fn latest_checkpoint_number(&self) -> Result<u64, OnemoneyError> {
+            Err(unsupported("checkpoints"))
+        }
+
+        async fn checkpoint_by_number(
+            &self,
+            _number: u64,
+            _full: bool,
+        ) -> Result<Checkpoint, OnemoneyError> {
+            Err(unsupported("checkpoints"))
+        }
+
+        async fn transaction_by_hash(&self, _hash: B256) -> Result<Transaction, OnemoneyError> {
+            Err(unsupported("transactions"))
+        }
+
+        async fn transaction_receipt(
+            &self,
+            _hash: B256,
+        ) -> Result<TransactionReceipt, OnemoneyError> {
+            Err(unsupported("receipts"))
+        }
+
+        async fn bridge_and_mint(
+            &self,
+            _signer: &RelayerSigner,
+            _payload: TokenBridgeAndMintPayload,
+        ) -> Result<TransactionResponse, SignerError> {
+            Err(unsupported("transactions").into())
+        }
+
+        async fn send_payment(
+            &self,
+            _signer: &RelayerSigner,
+            _payload: PaymentPayload,
+        ) -> Result<TransactionResponse, SignerError> {
+            Err(unsupported("transactions").into())
+        }
+    }
+
+    #[tokio::test]
+    async fn nonces_already_used_on_onemoney_are_skipped() {
+        let signer = RelayerSigner::Local(PrivateKeySigner::random());
+        let onemoney = FakeOneMoney::new(signer.address(), [5]);
+        let ctx = Relayer1MoneyContext::new(&onemoney, &signer, None)
+            .await
+            .expect("context is created");
+
+        assert_eq!(ctx.chain_id, CHAIN_ID);
+        assert!(!ctx.should_process_nonce(4).await.expect("nonce is checked"));
+        assert!(ctx.should_process_nonce(5).await.expect("nonce is checked"));
+    }
+
+    #[tokio::test]
+    async fn later_nonces_wait_for_the_onemoney_account() {
+        let signer = RelayerSigner::Local(PrivateKeySigner::random());
+        let onemoney = FakeOneMoney::new(signer.address(), [3, 3, 4, 5]);
+        let ctx = Relayer1MoneyContext::new(&onemoney, &signer, None)
+            .await
+            .expect("context is created");
+
+        assert!(ctx.should_process_nonce(5).await.expect("nonce is checked"));
+        assert_eq!(
+            onemoney
+                .nonces
+                .lock()
+                .expect("nonces are not poisoned")
+                .len(),
+            1
+        );
+    }
+
+    #[tokio::test]
+    async fn relayed_deposit_is_only_linked_after_a_restart() {
+        const SOURCE_HASH: B256 =
+            b256!("0x1111111111111111111111111111111111111111111111111111111111111111");
+        const DESTINATION_HASH: B256 =
+            b256!("0x2222222222222222222222222222222222222222222222222222222222222222");
+
+        // Sidechain node answering every simulated call of the dry-run mode
+        let server = MockServer::start_async().await;
+        let rpc_result = |result: &str| json!({ "jsonrpc": "2.0", "id": 0, "result": result });
+        server
+            .mock_async(|when, then| {
+                when.method(POST).body_includes("eth_getTransactionCount");
+                then.status(200).json_body(rpc_result("0x0"));
+            })
+            .await;
+        let register = server
+            .mock_async(|when, then| {
+                when.method(POST)
+                    .body_includes(hex::encode(TxHashMapping::registerDepositCall::SELECTOR));
+                then.status(200).json_body(rpc_result("0x"));
+            })
+            .await;
+        let link = server
+            .mock_async(|when, then| {
+                when.method(POST)
+                    .body_includes(hex::encode(TxHashMapping::linkDepositHashesCall::SELECTOR))
+                    .body_includes(hex::encode(DESTINATION_HASH));
+                then.status(200).json_body(rpc_result("0x"));
+            })
+            .await;
+
+        let config = Cli::try_parse_from([
+            "relayer",
+            "--interop-contract-address",
+            "0x0000000000000000000000000000000000000001",
+            "--tx-mapping-contract-address",
+            "0x0000000000000000000000000000000000000002",
+            "--side-chain-http-url",
+            &server.base_url(),
+            "--dry-run",
+            "proof-of-authority",
+        ])
+        .expect("valid arguments")
+        .config;
+        let signers = Signers::load(&SignerConfig {
+            relayer_private_key: Some(PrivateKeySigner::random()),
+            ..SignerConfig::default()
+        })
+        .expect("loaded signers");
+        let tx_manager = TxManager::spawn(
+            config.side_chain_http_url.clone(),
+            &signers,
+            config.sidechain_tx.clone(),
+            DryRun::new(&config, config.onemoney_api().expect("valid 1Money url")),
+        )
+        .await
+        .expect("spawned tx manager");
+
+        // The relayer stopped after the `BridgeAndMint`, before linking it
+        let store = StateStore::in_memory();
+        store
+            .set_transfer(
+                TransferKind::Deposit,
+                SOURCE_HASH,
+                TransferState::Relayed {
+                    destination_hash: DESTINATION_HASH,
+                },
+            )
+            .await
+            .expect("set transfer");
+
+        let onemoney = FakeOneMoney::new(signers.relayer.address(), [0]);
+        let ctx = Relayer1MoneyContext::new(&onemoney, &signers.relayer, tx_manager.dry_run())
+            .await
+            .expect("context is created");
+        let destination_hash = ctx
+            .handle_om_interop_received(
+                &config,
+                &tx_manager,
+                &store,
+                OMInteropReceived {
+                    nonce: 0,
+                    to: Address::repeat_byte(0x33),
+                    amount: U256::from(100),
+                    omToken: Address::repeat_byte(0x44),
+                    srcChainId: 1,
+                },
+                SOURCE_HASH,
+            )
+            .await
+            .expect("deposit handled");
+
+        assert_eq!(destination_hash, Some(DESTINATION_HASH));
+        assert_eq!(register.calls_async().await, 0);
+        assert_eq!(link.calls_async().await, 1);
+        assert_eq!(store.transfer(TransferKind::Deposit, SOURCE_HASH), None);
+    }
+}
@@ -1,35 +1,49 @@
-use alloy_primitives::BlockNumber;
+use alloy_primitives::{BlockNumber, B256};
 use alloy_rpc_types_eth::Log;
-use futures::TryStreamExt;
+use futures::{StreamExt, TryStreamExt};
 use onemoney_interop::contract::OMInterop::{self, OMInteropEvents};
-use onemoney_protocol::client::http::Client;
-use tracing::{debug, info, warn};
+use onemoney_interop::event::EventUpdate;
+use tracing::{debug, error, info, warn};
 
-use crate::config::{Config, RelayerNonce};
+use crate::context::RelayerContext;
+use crate::metrics::metrics;
+use crate::shutdown::Shutdown;
+use crate::store::{LogCursor, StateStore, TransferKind, TransferState};
 
 pub mod error;
-mod handlers;
+pub mod handlers;
 pub mod recovery;
 
 use error::Error as IncomingError;
 use handlers::Relayer1MoneyContext;
 
 pub async fn relay_incoming_events(
-    config: &Config,
-    relayer_nonce: RelayerNonce,
+    ctx: &RelayerContext,
     from_block: BlockNumber,
+    shutdown: &Shutdown,
 ) -> Result<(), IncomingError> {
     let sc_event_stream = onemoney_interop::event::event_stream(
-        config.side_chain_http_url.clone(),
-        config.side_chain_ws_url.clone(),
-        config.interop_contract_address,
+        ctx.config.side_chain_http_url.clone(),
+        ctx.config.side_chain_ws_url.clone(),
+        ctx.config.interop_contract_address,
         from_block,
+        ctx.config.side_chain_confirmations,
     )
     .await;
 
+    // Stop pulling events on shutdown, the event being processed is completed first
     sc_event_stream
+        .take_until(shutdown.requested())
         .map_err(IncomingError::from)
-        .try_for_each(|event| async { process_event(event, config, relayer_nonce.clone()).await })
+        .try_for_each(|update| async {
+            match update {
+                EventUpdate::Confirmed(event) => process_event(event, ctx).await,
+                EventUpdate::Retracted(event) => {
+                    report_retracted_event(&event, ctx);
+                    Ok(())
+                }
+            }
+        })
         .await?;
 
     Ok(())
@@ -37,12 +51,17 @@ pub async fn relay_incoming_events(
 
 pub async fn process_event(
     event: Log<OMInteropEvents>,
-    config: &Config,
-    relayer_nonce: RelayerNonce,
+    ctx: &RelayerContext,
 ) -> Result<(), IncomingError> {
-    let onemoney_client = Client::custom(config.one_money_node_url.to_string())?;
+    let RelayerContext {
+        config,
+        signer,
+        tx_manager,
+        store,
+        onemoney,
+    } = ctx;
     let relayer_ctx =
-        Relayer1MoneyContext::new(&onemoney_client, &config.relayer_private_key).await?;
+        Relayer1MoneyContext::new(onemoney.as_ref(), signer, tx_manager.dry_run()).await?;
 
     let block_number = event
         .block_number
@@ -62,6 +81,7 @@ pub async fn process_event(
     }
 
     let log = event.inner;
+    metrics().event_processed(event_name(&log.data));
 
     match log.data {
         OMInteropEvents::OMInteropReceived(inner) => {
@@ -78,9 +98,12 @@ pub async fn process_event(
                 "Handling OMInteropReceived event"
             );
 
-            if relayer_ctx.should_process_nonce(inner.nonce).await? {
+            // A deposit relayed before a restart only misses its link, its nonce is used
+            if is_relayed(store, TransferKind::Deposit, tx_hash)
+                || relayer_ctx.should_process_nonce(inner.nonce).await?
+            {
                 let response = relayer_ctx
-                    .handle_om_interop_received(config, relayer_nonce.clone(), inner, tx_hash)
+                    .handle_om_interop_received(config, tx_manager, store, inner, tx_hash)
                     .await?;
 
                 info!(
@@ -116,9 +139,11 @@ pub async fn process_event(
                     );
             }
 
-            if relayer_ctx.should_process_nonce(inner.nonce).await? {
+            if is_relayed(store, TransferKind::Refund, inner.sourceHash)
+                || relayer_ctx.should_process_nonce(inner.nonce).await?
+            {
                 let response = relayer_ctx
-                    .handle_om_interop_sent(config, relayer_nonce.clone(), inner)
+                    .handle_om_interop_sent(config, tx_manager, store, inner)
                     .await?;
 
                 info!(
@@ -201,5 +226,57 @@ pub async fn process_event(
             );
         }
     }
+
+    store.advance_sidechain_cursor(LogCursor {
+        block_number,
+        log_index,
+    });
+    metrics().set_sidechain_block_cursor(block_number);
+
     Ok(())
 }
+
+/// Whether the 1Money transaction of the transfer `source_hash` was submitted, but not linked.
+fn is_relayed(store: &StateStore, kind: TransferKind, source_hash: B256) -> bool {
+    matches!(
+        store.transfer(kind, source_hash),
+        Some(TransferState::Relayed { .. })
+    )
+}
+
+/// Reports an event removed by a sidechain reorg after it was processed and rolls the store
+/// cursors back to its position.
+///
+/// The transaction submitted for it cannot be reverted, so the event is only reported for an
+/// operator to reconcile. Raise `--side-chain-confirmations` if this happens.
+fn report_retracted_event(event: &Log<OMInteropEvents>, ctx: &RelayerContext) {
+    let event_name = event_name(&event.inner.data);
+    metrics().event_retracted(event_name);
+    if let Some((block_number, log_index)) = event.block_number.zip(event.log_index) {
+        ctx.store.rewind_sidechain_cursors(LogCursor {
+            block_number,
+            log_index,
+        });
+    }
+    error!(
+        block_number = ?event.block_number,
+        log_index = ?event.log_index,
+        tx_hash = ?event.transaction_hash,
+        event = event_name,
+        "Processed OMInterop event was removed by a sidechain reorg"
+    );
+}
+
+fn event_name(event: &OMInteropEvents) -> &'static str {
+    match event {
+        OMInteropEvents::OMInteropReceived(_) => "OMInteropReceived",
+        OMInteropEvents::OMInteropSent(_) => "OMInteropSent",
+        OMInteropEvents::OperatorUpdated(_) => "OperatorUpdated",
+        OMInteropEvents::RelayerUpdated(_) => "RelayerUpdated",
+        OMInteropEvents::OwnershipTransferred(_) => "OwnershipTransferred",
+        OMInteropEvents::RateLimitsChanged(_) => "RateLimitsChanged",
+        OMInteropEvents::Initialized(_) => "Initialized",
+        OMInteropEvents::Upgraded(_) => "Upgraded",
+        OMInteropEvents::PriceOracleUpdated(_) => "PriceOracleUpdated",
+    }
+}
@@ -1,35 +1,33 @@
-use core::sync::atomic::Ordering;
 use core::time::Duration;
 
-use alloy_primitives::TxHash;
+use alloy_primitives::{FixedBytes, TxHash};
 use alloy_provider::{Provider, ProviderBuilder};
 use alloy_rpc_types_eth::Filter;
 use alloy_sol_types::SolEvent;
 use onemoney_interop::contract::OMInterop::{self, OMInteropErrors, OMInteropReceived};
 use onemoney_interop::contract::TxHashMapping;
 use onemoney_interop::event::decode_event;
-use onemoney_protocol::{CheckpointTransactions, Client, TxPayload};
-use tracing::warn;
+use tracing::{info, warn};
 
-use crate::config::{Config, RelayerNonce};
+use crate::context::RelayerContext;
 use crate::incoming::error::Error;
 use crate::incoming::process_event;
+use crate::metrics::metrics;
+use crate::onemoney::index::{RelayerTransactions, TransferKey, CHECKPOINT_FETCH_CONCURRENCY};
+use crate::shutdown::Shutdown;
+use crate::sidechain::tx_manager::TxManager;
+use crate::signer::Role;
+use crate::store::{StateStore, TransferKind, TransferState};
 
 const MAX_BLOCK_RANGE: u64 = 100_000;
 
-pub async fn get_latest_incomplete_block_number(config: &Config) -> Result<u64, Error> {
-    let provider = ProviderBuilder::new()
-        .wallet(config.relayer_private_key.clone())
-        .connect_http(config.side_chain_http_url.clone());
+pub async fn get_latest_incomplete_block_number(ctx: &RelayerContext) -> Result<u64, Error> {
+    let provider = ProviderBuilder::new().connect_http(ctx.config.side_chain_http_url.clone());
     let latest_block_number = provider.get_block_number().await?;
 
-    let contract = OMInterop::new(config.interop_contract_address, provider);
-    let client = Client::custom(config.one_money_node_url.to_string())?;
+    let contract = OMInterop::new(ctx.config.interop_contract_address, provider);
 
-    let om_relayer_nonce = client
-        .get_account_nonce(config.relayer_private_key.address())
-        .await?
-        .nonce;
+    let om_relayer_nonce = ctx.onemoney.account_nonce(ctx.signer.address()).await?;
     let sc_relayer_nonce = sc_inbound_nonce_at(&contract, latest_block_number).await?;
 
     if om_relayer_nonce > sc_relayer_nonce {
@@ -62,6 +60,33 @@ pub async fn get_latest_incomplete_block_number(config: &Config) -> Result<u64,
     Ok(low)
 }
 
+/// Returns the block from which the incoming flows should resume.
+///
+/// The earliest block of the stream and clearing cursors recorded in the state store is used
+/// when available. Falls back to [`get_latest_incomplete_block_number`] when the store is empty or its cursor
+/// is ahead of the sidechain head.
+pub async fn resume_block_number(ctx: &RelayerContext) -> Result<u64, Error> {
+    if let Some(block_number) = ctx.store.snapshot().sidechain_resume_block() {
+        let latest_block_number = ProviderBuilder::new()
+            .connect_http(ctx.config.side_chain_http_url.clone())
+            .get_block_number()
+            .await?;
+
+        if block_number <= latest_block_number {
+            info!(block_number, "Resuming sidechain events from state store");
+            return Ok(block_number);
+        }
+
+        warn!(
+            block_number,
+            latest_block_number,
+            "State store cursor is ahead of the sidechain, deriving start block from chain state"
+        );
+    }
+
+    get_latest_incomplete_block_number(ctx).await
+}
+
 async fn sc_inbound_nonce_at<P: Provider>(
     contract: &onemoney_interop::contract::OMInterop::OMInteropInstance<P>,
     block: u64,
@@ -83,28 +108,40 @@ async fn sc_inbound_nonce_at<P: Provider>(
 
 pub async fn relay_incoming_events_from_blocks(
     from_block: u64,
-    config: &Config,
-    relayer_nonce: RelayerNonce,
+    ctx: &RelayerContext,
     interval: Duration,
+    shutdown: &Shutdown,
 ) -> Result<(), Error> {
-    let provider = ProviderBuilder::new()
-        .wallet(config.relayer_private_key.clone())
-        .connect_http(config.side_chain_http_url.clone());
+    let RelayerContext { config, store, .. } = ctx;
+    let provider = ProviderBuilder::new().connect_http(config.side_chain_http_url.clone());
 
     let mut from_block = from_block;
-    let mut to_block = provider.get_block_number().await?;
+    let mut to_block = provider
+        .get_block_number()
+        .await?
+        .saturating_sub(config.side_chain_confirmations);
 
     loop {
         // Trigger transaction clearing
-        interval_clearing(from_block, to_block, config, relayer_nonce.clone()).await?;
+        interval_clearing(from_block, to_block, ctx).await?;
+
+        // Every log up to `to_block` has been processed
+        store.advance_sidechain_clearing_cursor(to_block);
+        metrics().set_sidechain_block_cursor(to_block);
 
         // Set next clearing start to current end
         from_block = to_block;
 
-        tokio::time::sleep(interval).await;
+        tokio::select! {
+            () = shutdown.requested() => return Ok(()),
+            () = tokio::time::sleep(interval) => {}
+        }
 
-        // Update to_block to the latest block number
-        to_block = provider.get_block_number().await?;
+        // Update to_block to the latest block with enough confirmations
+        to_block = provider
+            .get_block_number()
+            .await?
+            .saturating_sub(config.side_chain_confirmations);
     }
 }
 
@@ -112,10 +149,9 @@ pub async fn relay_incoming_events_from_blocks(
 pub async fn interval_clearing(
     from_block: u64,
     to_block: u64,
-    config: &Config,
-    relayer_nonce: RelayerNonce,
+    ctx: &RelayerContext,
 ) -> Result<(), Error> {
-    let http_provider = ProviderBuilder::new().connect_http(config.side_chain_http_url.clone());
+    let http_provider = ProviderBuilder::new().connect_http(ctx.config.side_chain_http_url.clone());
 
     let mut start = from_block;
 
@@ -125,7 +161,7 @@ pub async fn interval_clearing(
         let end = core::cmp::min(start + MAX_BLOCK_RANGE - 1, to_block);
 
         let history_filter = Filter::new()
-            .address(config.interop_contract_address)
+            .address(ctx.config.interop_contract_address)
             .select(start..=end);
 
         let historical = http_provider.get_logs(&history_filter).await?;
@@ -143,7 +179,7 @@ pub async fn interval_clearing(
         });
 
         for log in decoded {
-            process_event(log, config, relayer_nonce.clone()).await?;
+            process_event(log, ctx).await?;
         }
 
         start = end + 1;
@@ -153,35 +189,54 @@ pub async fn interval_clearing(
 }
 
 pub async fn recover_incomplete_deposit_hash_mapping(
-    config: &Config,
-    relayer_nonce: RelayerNonce,
+    ctx: &RelayerContext,
     start_checkpoint: Option<u64>,
 ) -> Result<(), Error> {
-    let provider = ProviderBuilder::new()
-        .wallet(config.relayer_private_key.clone())
-        .connect_http(config.side_chain_http_url.clone());
+    let RelayerContext {
+        config,
+        signer,
+        tx_manager,
+        store,
+        onemoney,
+        ..
+    } = ctx;
+    let provider = ProviderBuilder::new().connect_http(config.side_chain_http_url.clone());
     let mapping_contract = TxHashMapping::new(config.tx_mapping_contract_address, provider.clone());
 
     let incomplete_hashes = mapping_contract.incompleteDeposits().call().await?;
 
-    let client = Client::custom(config.one_money_node_url.to_string())?;
-
-    // If a start checkpoint has been given use it, else start from 0
-    let start = start_checkpoint.unwrap_or_default();
-
-    let last_checkpoint = client.get_checkpoint_number().await?.number;
-
-    if start > last_checkpoint {
-        warn!(
-            start,
-            last_checkpoint, "Start checkpoint is greater than last checkpoint; nothing to recover"
-        );
-        return Ok(());
+    // Forget stored deposits which have been linked since they were recorded
+    for hash in store.snapshot().deposits.into_keys() {
+        if incomplete_hashes.contains(&hash) {
+            continue;
+        }
+        let deposit = mapping_contract.getDepositByBridgeFrom(hash).call().await?;
+        if deposit.linked != FixedBytes::ZERO {
+            store.complete_transfer(TransferKind::Deposit, hash).await?;
+        }
     }
 
-    'hash_loop: for hash in incomplete_hashes.iter() {
+    // Deposits whose BridgeAndMint transaction has to be found on 1Money
+    let mut unresolved = Vec::new();
+
+    for hash in incomplete_hashes.iter() {
         let tx_hash: TxHash = *hash;
 
+        // The BridgeAndMint hash is already known if the relayer stopped before linking it
+        if let Some(TransferState::Relayed { destination_hash }) =
+            store.transfer(TransferKind::Deposit, tx_hash)
+        {
+            link_deposit_hashes(
+                &mapping_contract,
+                tx_manager,
+                store,
+                tx_hash,
+                destination_hash,
+            )
+            .await?;
+            continue;
+        }
+
         // Get the transaction receipt from the transaction hash
         let receipt = match provider.get_transaction_receipt(tx_hash).await {
             Ok(tx_receipt) => tx_receipt.ok_or_else(|| {
@@ -189,90 +244,114 @@ pub async fn recover_incomplete_deposit_hash_mapping(
             })?,
             Err(e) => {
                 warn!("Failed to query `{tx_hash}` receipt, most likely due to the receipt not existing. The mapping will be done when recovering transactions. Cause {e}");
-                continue 'hash_loop;
+                continue;
             }
         };
 
-        // Decode OMInteropReceived to retrieve the nonce and account address
-        let mut sidechain_nonce = None;
-        let mut to = None;
+        // Decode OMInteropReceived to retrieve the fields of the BridgeAndMint transaction
+        let received = receipt
+            .logs()
+            .iter()
+            .find_map(|log| OMInteropReceived::decode_raw_log(log.topics(), &log.data().data).ok())
+            .ok_or_else(|| {
+                Error::Generic(format!(
+                    "Failed to retrieve `OMInteropReceived` for transaction `{tx_hash}`"
+                ))
+            })?;
+
+        unresolved.push((
+            tx_hash,
+            TransferKey {
+                nonce: received.nonce,
+                recipient: received.to,
+                token: received.omToken,
+                value: received.amount,
+            },
+        ));
+    }
+
+    if unresolved.is_empty() {
+        return Ok(());
+    }
+
+    // If a start checkpoint has been given use it, else start from 0
+    let start = start_checkpoint.unwrap_or_default();
+
+    let last_checkpoint = onemoney.latest_checkpoint_number().await?;
+
+    if start > last_checkpoint {
+        warn!(
+            start,
+            last_checkpoint, "Start checkpoint is greater than last checkpoint; nothing to recover"
+        );
+        return Ok(());
+    }
 
-        for log in receipt.logs() {
-            if let Ok(ev) = OMInteropReceived::decode_raw_log(log.topics(), &log.data().data) {
-                sidechain_nonce = Some(ev.nonce);
-                to = Some(ev.to);
-                break;
+    info!(
+        deposits = unresolved.len(),
+        start, last_checkpoint, "Searching 1Money checkpoints for unlinked deposits"
+    );
+    let index = RelayerTransactions::index(
+        onemoney.as_ref(),
+        signer.address(),
+        start..=last_checkpoint,
+        CHECKPOINT_FETCH_CONCURRENCY,
+    )
+    .await;
+
+    for (tx_hash, key) in unresolved {
+        match index.bridge_and_mint(&key) {
+            Some(bridge_and_mint_hash) => {
+                link_deposit_hashes(
+                    &mapping_contract,
+                    tx_manager,
+                    store,
+                    tx_hash,
+                    bridge_and_mint_hash,
+                )
+                .await?;
             }
+            None => warn!(
+                bridge_from_hash = %tx_hash,
+                nonce = key.nonce,
+                "BridgeAndMint transaction not found in 1Money checkpoints"
+            ),
         }
+    }
 
-        let sidechain_nonce = sidechain_nonce.ok_or_else(|| {
-            Error::Generic(format!(
-                "Failed to retrieve `nonce` for transaction `{tx_hash}`"
-            ))
-        })?;
-        let to = to.ok_or_else(|| {
-            Error::Generic(format!(
-                "Failed to retrieve `to` for transaction `{tx_hash}`"
-            ))
-        })?;
-
-        for i in start..=last_checkpoint {
-            let checkpoint = match client.get_checkpoint_by_number(i, true).await {
-                Ok(ch) => ch,
-                Err(e) => {
-                    warn!("Failed to query checkpoint `{i}`. Cause: {e}");
-                    continue;
-                }
-            };
-
-            let maybe_bridge_and_mint_transaction = match &checkpoint.transactions {
-                CheckpointTransactions::Full(txs) => txs.iter().find(|tx| {
-                    if let TxPayload::TokenBridgeAndMint { recipient, .. } = &tx.data {
-                        tx.nonce == sidechain_nonce && *recipient == to
-                    } else {
-                        false
-                    }
-                }),
-                _ => None,
-            };
-
-            if let Some(bridge_and_mint_transaction) = maybe_bridge_and_mint_transaction {
-                match mapping_contract
-                    .linkDepositHashes(*hash, bridge_and_mint_transaction.hash)
-                    .nonce(relayer_nonce.fetch_add(1, Ordering::SeqCst))
-                    .send()
-                    .await
-                    .map(Ok)
-                    .or_else(|e| {
-                        e.try_decode_into_interface_error::<TxHashMapping::TxHashMappingErrors>()
-                            .map(Err)
-                    })?
-                    .map_err(Error::MappingContractReverted)
-                {
-                    Ok(pending_tx) => {
-                        if let Err(e) = pending_tx.get_receipt().await {
-                            warn!(
-                                bridge_from_hash=%hash,
-                                bridge_and_mint_hash=%bridge_and_mint_transaction.hash,
-                                error = %e,
-                                "Failed to retrieve `linkDepositHashes` receipt"
-                            );
-                        }
-                    }
-                    Err(e) => {
-                        // If send failed, decrement the nonce
-                        relayer_nonce.fetch_sub(1, Ordering::SeqCst);
-                        warn!(
-                                bridge_from_hash=%hash,
-                                bridge_and_mint_hash=%bridge_and_mint_transaction.hash,
-                            error = %e,
-                            "Failed to link deposit hashes"
-                        );
-                    }
-                }
-                // Process next incomplete deposit hash
-                continue 'hash_loop;
-            }
+    Ok(())
+}
+
+async fn link_deposit_hashes<P: Provider>(
+    mapping_contract: &TxHashMapping::TxHashMappingInstance<P>,
+    tx_manager: &TxManager,
+    store: &StateStore,
+    bridge_from_hash: TxHash,
+    bridge_and_mint_hash: TxHash,
+) -> Result<(), Error> {
+    match tx_manager
+        .send(
+            Role::TxMapping,
+            mapping_contract.linkDepositHashes(bridge_from_hash, bridge_and_mint_hash),
+        )
+        .await
+        .map_err(|e| {
+            e.try_decode_revert::<TxHashMapping::TxHashMappingErrors>()
+                .map_or_else(Error::from, Error::MappingContractReverted)
+        }) {
+        Ok(_) => {
+            store
+                .complete_transfer(TransferKind::Deposit, bridge_from_hash)
+                .await?;
+            metrics().hash_mapping_recovered(TransferKind::Deposit);
+        }
+        Err(e) => {
+            warn!(
+                %bridge_from_hash,
+                %bridge_and_mint_hash,
+                error = %e,
+                "Failed to link deposit hashes"
+            );
         }
     }
 
@@ -1,8 +1,20 @@
+pub mod admin;
 pub mod cli;
 pub mod config;
+pub mod context;
+pub mod deploy;
+pub mod dry_run;
 pub mod error;
+pub mod health;
 pub mod incoming;
+pub mod metrics;
 pub mod onemoney;
 pub mod outgoing;
 pub mod poa;
+pub mod server;
+pub mod shutdown;
 pub mod sidechain;
+pub mod signer;
+pub mod store;
+pub mod supervisor;
+pub mod trace;
@@ -1,10 +1,8 @@
-use clap::Parser;
-
 #[tokio::main]
 async fn main() -> color_eyre::Result<()> {
     tracing_subscriber::fmt::init();
     color_eyre::install()?;
-    let cli = relayer::cli::Cli::parse();
+    let cli = relayer::cli::Cli::parse_layered()?;
     cli.run().await?;
     Ok(())
 }
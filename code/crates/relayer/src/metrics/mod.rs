@@ -0,0 +1,312 @@
+//! Prometheus metrics exposed by the relayer.
+//!
+//! Metrics are registered once in a process-wide registry and served in the Prometheus text
+//! format on `/metrics` when the relayer is started with `--metrics-addr`, see [`crate::server`].
+
+use core::time::Duration;
+use std::sync::LazyLock;
+
+use axum::http::header::CONTENT_TYPE;
+use axum::response::IntoResponse;
+use axum::routing::get;
+use axum::Router;
+use prometheus::{
+    Encoder, HistogramOpts, HistogramVec, IntCounter, IntCounterVec, IntGauge, IntGaugeVec, Opts,
+    Registry, TextEncoder, TEXT_FORMAT,
+};
+use tracing::error;
+
+use crate::signer::Role;
+use crate::store::TransferKind;
+
+static METRICS: LazyLock<Metrics> = LazyLock::new(Metrics::new);
+
+/// Returns the process-wide relayer metrics.
+pub fn metrics() -> &'static Metrics {
+    &METRICS
+}
+
+/// Chain on which a relayer nonce is tracked.
+#[derive(Debug, Clone, Copy)]
+pub enum Chain {
+    Onemoney,
+    Sidechain,
+}
+
+pub struct Metrics {
+    registry: Registry,
+    /// Sidechain events processed, by `OMInteropEvents` variant.
+    events_processed: IntCounterVec,
+    /// Processed sidechain events later removed by a reorg, by `OMInteropEvents` variant.
+    events_retracted: IntCounterVec,
+    /// `BurnAndBridge` transactions relayed as a sidechain `bridgeTo`.
+    burn_and_bridge_relayed: IntCounter,
+    /// 1Money checkpoints cleared by the outgoing clearing flow.
+    checkpoints_cleared: IntCounter,
+    /// Last sidechain block processed by the incoming flows.
+    sidechain_block_cursor: IntGauge,
+    /// Last 1Money checkpoint cleared by the outgoing clearing flow.
+    checkpoint_cursor: IntGauge,
+    /// Relayer account nonces on 1Money and on the sidechain, by role.
+    relayer_nonce: IntGaugeVec,
+    /// Nonce of the last `OMInteropReceived` event checked against the 1Money nonce.
+    event_nonce: IntGauge,
+    /// Failed submissions, by error variant.
+    submission_failures: IntCounterVec,
+    /// Validator set updates applied on the sidechain.
+    validator_set_updates: IntCounter,
+    /// Hash mappings linked by the recovery flows, by transfer kind.
+    hash_mappings_recovered: IntCounterVec,
+    /// Time from picking up a source transaction to the acceptance of its destination
+    /// transaction, by transfer kind.
+    relay_latency: HistogramVec,
+    /// Simulated writes not landed as intended by the production relayer, by write kind.
+    shadow_divergences: IntCounterVec,
+}
+
+impl Metrics {
+    fn new() -> Self {
+        let registry = Registry::new_custom(Some("relayer".to_owned()), None)
+            .expect("metrics prefix is valid");
+
+        let events_processed = IntCounterVec::new(
+            Opts::new(
+                "events_processed_total",
+                "Sidechain events processed by OMInteropEvents variant",
+            ),
+            &["event"],
+        )
+        .expect("metric is valid");
+        let events_retracted = IntCounterVec::new(
+            Opts::new(
+                "events_retracted_total",
+                "Processed sidechain events removed by a reorg by OMInteropEvents variant",
+            ),
+            &["event"],
+        )
+        .expect("metric is valid");
+        let burn_and_bridge_relayed = IntCounter::new(
+            "burn_and_bridge_relayed_total",
+            "BurnAndBridge transactions relayed to the sidechain",
+        )
+        .expect("metric is valid");
+        let checkpoints_cleared = IntCounter::new(
+            "checkpoints_cleared_total",
+            "1Money checkpoints cleared by the outgoing clearing flow",
+        )
+        .expect("metric is valid");
+        let sidechain_block_cursor = IntGauge::new(
+            "sidechain_block_cursor",
+            "Last sidechain block processed by the incoming flows",
+        )
+        .expect("metric is valid");
+        let checkpoint_cursor = IntGauge::new(
+            "checkpoint_cursor",
+            "Last 1Money checkpoint cleared by the outgoing clearing flow",
+        )
+        .expect("metric is valid");
+        let relayer_nonce = IntGaugeVec::new(
+            Opts::new("nonce", "Relayer account nonce by chain and role"),
+            &["chain", "role"],
+        )
+        .expect("metric is valid");
+        let event_nonce = IntGauge::new(
+            "event_nonce",
+            "Nonce of the last OMInteropReceived event checked against the 1Money nonce",
+        )
+        .expect("metric is valid");
+        let submission_failures = IntCounterVec::new(
+            Opts::new(
+                "submission_failures_total",
+                "Failed transaction submissions by error variant",
+            ),
+            &["error"],
+        )
+        .expect("metric is valid");
+        let validator_set_updates = IntCounter::new(
+            "validator_set_updates_total",
+            "Validator set updates applied on the sidechain",
+        )
+        .expect("metric is valid");
+        let hash_mappings_recovered = IntCounterVec::new(
+            Opts::new(
+                "hash_mappings_recovered_total",
+                "Hash mappings linked by the recovery flows by transfer kind",
+            ),
+            &["kind"],
+        )
+        .expect("metric is valid");
+        let relay_latency = HistogramVec::new(
+            HistogramOpts::new(
+                "relay_latency_seconds",
+                "Time from picking up a source transaction to the acceptance of its destination transaction",
+            )
+            .buckets(vec![0.5, 1.0, 2.5, 5.0, 10.0, 30.0, 60.0, 120.0, 300.0, 600.0]),
+            &["kind"],
+        )
+        .expect("metric is valid");
+        let shadow_divergences = IntCounterVec::new(
+            Opts::new(
+                "shadow_divergences_total",
+                "Simulated writes not landed as intended by the production relayer by write kind",
+            ),
+            &["kind"],
+        )
+        .expect("metric is valid");
+
+        let collectors: [Box<dyn prometheus::core::Collector>; 13] = [
+            Box::new(events_processed.clone()),
+            Box::new(events_retracted.clone()),
+            Box::new(burn_and_bridge_relayed.clone()),
+            Box::new(checkpoints_cleared.clone()),
+            Box::new(sidechain_block_cursor.clone()),
+            Box::new(checkpoint_cursor.clone()),
+            Box::new(relayer_nonce.clone()),
+            Box::new(event_nonce.clone()),
+            Box::new(submission_failures.clone()),
+            Box::new(validator_set_updates.clone()),
+            Box::new(hash_mappings_recovered.clone()),
+            Box::new(relay_latency.clone()),
+            Box::new(shadow_divergences.clone()),
+        ];
+        for collector in collectors {
+            registry
+                .register(collector)
+                .expect("metric names are unique");
+        }
+
+        Self {
+            registry,
+            events_processed,
+            events_retracted,
+            burn_and_bridge_relayed,
+            checkpoints_cleared,
+            sidechain_block_cursor,
+            checkpoint_cursor,
+            relayer_nonce,
+            event_nonce,
+            submission_failures,
+            validator_set_updates,
+            hash_mappings_recovered,
+            relay_latency,
+            shadow_divergences,
+        }
+    }
+
+    pub fn event_processed(&self, event: &str) {
+        self.events_processed.with_label_values(&[event]).inc();
+    }
+
+    pub fn event_retracted(&self, event: &str) {
+        self.events_retracted.with_label_values(&[event]).inc();
+    }
+
+    pub fn burn_and_bridge_relayed(&self) {
+        self.burn_and_bridge_relayed.inc();
+    }
+
+    pub fn checkpoint_cleared(&self, checkpoint: u64) {
+        self.checkpoints_cleared.inc();
+        self.checkpoint_cursor.set(gauge_value(checkpoint));
+    }
+
+    pub fn set_sidechain_block_cursor(&self, block_number: u64) {
+        self.sidechain_block_cursor.set(gauge_value(block_number));
+    }
+
+    /// Sets the next nonce of the account of `role` on `chain`.
+    pub fn set_relayer_nonce(&self, chain: Chain, role: Role, nonce: u64) {
+        let chain = match chain {
+            Chain::Onemoney => "onemoney",
+            Chain::Sidechain => "sidechain",
+        };
+        self.relayer_nonce
+            .with_label_values(&[chain, role.as_str()])
+            .set(gauge_value(nonce));
+    }
+
+    pub fn set_event_nonce(&self, nonce: u64) {
+        self.event_nonce.set(gauge_value(nonce));
+    }
+
+    pub fn submission_failed(&self, error: &str) {
+        self.submission_failures.with_label_values(&[error]).inc();
+    }
+
+    pub fn validator_set_updated(&self) {
+        self.validator_set_updates.inc();
+    }
+
+    pub fn hash_mapping_recovered(&self, kind: TransferKind) {
+        self.hash_mappings_recovered
+            .with_label_values(&[kind_label(kind)])
+            .inc();
+    }
+
+    pub fn observe_relay_latency(&self, kind: TransferKind, latency: Duration) {
+        self.relay_latency
+            .with_label_values(&[kind_label(kind)])
+            .observe(latency.as_secs_f64());
+    }
+
+    pub fn shadow_divergence(&self, kind: &str) {
+        self.shadow_divergences.with_label_values(&[kind]).inc();
+    }
+
+    /// Encodes all metrics in the Prometheus text format.
+    pub fn encode(&self) -> String {
+        let mut buffer = Vec::new();
+        if let Err(err) = TextEncoder::new().encode(&self.registry.gather(), &mut buffer) {
+            error!(%err, "Failed to encode metrics");
+        }
+        String::from_utf8_lossy(&buffer).into_owned()
+    }
+}
+
+fn kind_label(kind: TransferKind) -> &'static str {
+    match kind {
+        TransferKind::Deposit => "deposit",
+        TransferKind::Withdrawal => "withdrawal",
+        TransferKind::Refund => "refund",
+    }
+}
+
+fn gauge_value(value: u64) -> i64 {
+    i64::try_from(value).unwrap_or(i64::MAX)
+}
+
+async fn serve_metrics() -> impl IntoResponse {
+    ([(CONTENT_TYPE, TEXT_FORMAT)], metrics().encode())
+}
+
+/// Routes serving the metrics on `/metrics`.
+pub fn router() -> Router {
+    Router::new().route("/metrics", get(serve_metrics))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn metrics_are_encoded() {
+        let metrics = Metrics::new();
+        metrics.event_processed("OMInteropReceived");
+        metrics.event_retracted("OMInteropSent");
+        metrics.checkpoint_cleared(42);
+        metrics.set_relayer_nonce(Chain::Sidechain, Role::TxMapping, 7);
+        metrics.set_event_nonce(3);
+        metrics.observe_relay_latency(TransferKind::Withdrawal, Duration::from_secs(3));
+        metrics.shadow_divergence("deposit");
+
+        let encoded = metrics.encode();
+        assert!(encoded.contains(r#"relayer_events_processed_total{event="OMInteropReceived"} 1"#));
+        assert!(encoded.contains(r#"relayer_events_retracted_total{event="OMInteropSent"} 1"#));
+        assert!(encoded.contains("relayer_checkpoints_cleared_total 1"));
+        assert!(encoded.contains("relayer_checkpoint_cursor 42"));
+        assert!(encoded.contains(r#"relayer_nonce{chain="sidechain",role="tx_mapping"} 7"#));
+        assert!(encoded.contains("relayer_event_nonce 3"));
+        assert!(encoded.contains(r#"relayer_relay_latency_seconds_count{kind="withdrawal"} 1"#));
+        assert!(encoded.contains(r#"relayer_shadow_divergences_total{kind="deposit"} 1"#));
+    }
+}
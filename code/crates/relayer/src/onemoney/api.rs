@@ -0,0 +1,112 @@
+//! Calls of the relayer to the 1Money REST API.
+//!
+//! The flows go through [`OneMoneyApi`] rather than a concrete client, so that they run
+//! against the HTTP [`Client`] of `onemoney_protocol` in production and against a test double
+//! in unit tests.
+
+use alloy_primitives::{Address, B256};
+use async_trait::async_trait;
+use onemoney_protocol::client::http::Client;
+use onemoney_protocol::error::Error as OnemoneyError;
+use onemoney_protocol::responses::{
+    Checkpoint, EpochResponse, TransactionReceipt, TransactionResponse,
+};
+use onemoney_protocol::{PaymentPayload, TokenBridgeAndMintPayload, Transaction};
+
+use crate::signer::error::Error as SignerError;
+use crate::signer::RelayerSigner;
+
+/// Subset of the 1Money REST API used by the relayer.
+#[async_trait]
+pub trait OneMoneyApi: Send + Sync {
+    /// Returns the next nonce of `address`.
+    async fn account_nonce(&self, address: Address) -> Result<u64, OnemoneyError>;
+
+    /// Returns the chain id of the 1Money network.
+    async fn chain_id(&self) -> Result<u64, OnemoneyError>;
+
+    /// Returns the current epoch with its governance certificate.
+    async fn current_epoch(&self) -> Result<EpochResponse, OnemoneyError>;
+
+    /// Returns the number of the latest checkpoint.
+    async fn latest_checkpoint_number(&self) -> Result<u64, OnemoneyError>;
+
+    /// Returns the checkpoint `number`, with its full transactions if `full` is set.
+    async fn checkpoint_by_number(
+        &self,
+        number: u64,
+        full: bool,
+    ) -> Result<Checkpoint, OnemoneyError>;
+
+    /// Returns the transaction `hash`.
+    async fn transaction_by_hash(&self, hash: B256) -> Result<Transaction, OnemoneyError>;
+
+    /// Returns the receipt of the transaction `hash`.
+    async fn transaction_receipt(&self, hash: B256) -> Result<TransactionReceipt, OnemoneyError>;
+
+    /// Submits a `BridgeAndMint` transaction signed by `signer`.
+    async fn bridge_and_mint(
+        &self,
+        signer: &RelayerSigner,
+        payload: TokenBridgeAndMintPayload,
+    ) -> Result<TransactionResponse, SignerError>;
+
+    /// Submits a payment signed by `signer`.
+    async fn send_payment(
+        &self,
+        signer: &RelayerSigner,
+        payload: PaymentPayload,
+    ) -> Result<TransactionResponse, SignerError>;
+}
+
+#[async_trait]
+impl OneMoneyApi for Client {
+    async fn account_nonce(&self, address: Address) -> Result<u64, OnemoneyError> {
+        Ok(self.get_account_nonce(address).await?.nonce)
+    }
+
+    async fn chain_id(&self) -> Result<u64, OnemoneyError> {
+        self.fetch_chain_id_from_network().await
+    }
+
+    async fn current_epoch(&self) -> Result<EpochResponse, OnemoneyError> {
+        self.get_current_epoch().await
+    }
+
+    async fn latest_checkpoint_number(&self) -> Result<u64, OnemoneyError> {
+        Ok(self.get_checkpoint_number().await?.number)
+    }
+
+    async fn checkpoint_by_number(
+        &self,
+        number: u64,
+        full: bool,
+    ) -> Result<Checkpoint, OnemoneyError> {
+        self.get_checkpoint_by_number(number, full).await
+    }
+
+    async fn transaction_by_hash(&self, hash: B256) -> Result<Transaction, OnemoneyError> {
+        self.get_transaction_by_hash(&hash.to_string()).await
+    }
+
+    async fn transaction_receipt(&self, hash: B256) -> Result<TransactionReceipt, OnemoneyError> {
+        self.get_transaction_receipt_by_hash(&hash.to_string())
+            .await
+    }
+
+    async fn bridge_and_mint(
+        &self,
+        signer: &RelayerSigner,
+        payload: TokenBridgeAndMintPayload,
+    ) -> Result<TransactionResponse, SignerError> {
+        signer.bridge_and_mint(self, payload).await
+    }
+
+    async fn send_payment(
+        &self,
+        signer: &RelayerSigner,
+        payload: PaymentPayload,
+    ) -> Result<TransactionResponse, SignerError> {
+        signer.send_payment(self, payload).await
+    }
+}
@@ -9,6 +9,8 @@ pub enum Error {
     Http(#[from] reqwest::Error),
     #[error("Validator {address:?} has an invalid consensus public key")]
     InvalidValidatorKey { address: Address },
+    #[error("Failed to decode epoch: {0}")]
+    InvalidEpoch(#[from] serde_json::Error),
     #[error("Failed to query 1Money: {0}")]
     FailedQuery(#[from] onemoney_protocol::Error),
     #[error("Pending transaction failed: {0}")]
@@ -18,3 +20,18 @@ pub enum Error {
     #[error("Failed: {0}")]
     Generic(String),
 }
+
+/// Reason the subscription to the 1Money certified transactions was interrupted.
+#[derive(Debug, Error)]
+pub enum SubscriptionError {
+    #[error("Failed to connect to the websocket: {0}")]
+    Connect(tokio_tungstenite::tungstenite::Error),
+    #[error("Failed to send to the websocket: {0}")]
+    Send(tokio_tungstenite::tungstenite::Error),
+    #[error("Failed to receive from the websocket: {0}")]
+    Receive(tokio_tungstenite::tungstenite::Error),
+    #[error("Websocket closed by the server: {reason}")]
+    Closed { reason: String },
+    #[error("No message received from the websocket for {}", humantime::format_duration(*.0))]
+    HeartbeatTimeout(core::time::Duration),
+}
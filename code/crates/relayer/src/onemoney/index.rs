@@ -0,0 +1,151 @@
+use core::ops::RangeInclusive;
+use std::collections::HashMap;
+
+use alloy_primitives::{Address, B256, U256};
+use futures::{stream, StreamExt};
+use onemoney_protocol::{CheckpointTransactions, Transaction, TxPayload};
+use tracing::{info, warn};
+
+use crate::onemoney::api::OneMoneyApi;
+
+/// Number of checkpoints fetched concurrently while building a [`RelayerTransactions`] index.
+pub const CHECKPOINT_FETCH_CONCURRENCY: usize = 16;
+
+/// Fields identifying a transfer of the relayer on 1Money.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct TransferKey {
+    /// Nonce of the relayer account, shared with the interop contract.
+    pub nonce: u64,
+    pub recipient: Address,
+    pub token: Address,
+    pub value: U256,
+}
+
+/// Hashes of the `TokenBridgeAndMint` and `TokenTransfer` transactions sent by the relayer,
+/// indexed by [`TransferKey`].
+///
+/// Built with a single scan of a checkpoint range, so that the hash mapping recoveries resolve
+/// every incomplete hash without fetching the checkpoints again.
+#[derive(Debug, Default)]
+pub struct RelayerTransactions {
+    relayer: Address,
+    bridge_and_mints: HashMap<TransferKey, B256>,
+    transfers: HashMap<TransferKey, B256>,
+}
+
+impl RelayerTransactions {
+    pub fn new(relayer: Address) -> Self {
+        Self {
+            relayer,
+            ..Default::default()
+        }
+    }
+
+    /// Indexes the transactions sent by `relayer` in `checkpoints`, fetching up to
+    /// `concurrency` checkpoints at once.
+    ///
+    /// Checkpoints which fail to be fetched are logged and skipped, the transfers they contain
+    /// are then missing from the index.
+    pub async fn index(
+        onemoney: &dyn OneMoneyApi,
+        relayer: Address,
+        checkpoints: RangeInclusive<u64>,
+        concurrency: usize,
+    ) -> Self {
+        let total = checkpoints
+            .end()
+            .saturating_sub(*checkpoints.start())
+            .saturating_add(1);
+        let mut index = Self::new(relayer);
+        let mut fetches = stream::iter(checkpoints)
+            .map(
+                |number| async move { (number, onemoney.checkpoint_by_number(number, true).await) },
+            )
+            .buffer_unordered(concurrency.max(1));
+
+        let mut scanned = 0u64;
+        let mut failed = 0u64;
+        let mut reported_decile = 0u64;
+        while let Some((number, checkpoint)) = fetches.next().await {
+            scanned += 1;
+            match checkpoint.map(|checkpoint| checkpoint.transactions) {
+                Ok(CheckpointTransactions::Full(transactions)) => {
+                    for tx in &transactions {
+                        index.insert(tx);
+                    }
+                }
+                Ok(CheckpointTransactions::Hashes(_)) => {
+                    warn!(
+                        checkpoint = number,
+                        "Checkpoint contains hashed transactions instead of full transactions"
+                    );
+                    failed += 1;
+                }
+                Err(e) => {
+                    warn!("Failed to query checkpoint `{number}`. Cause: {e}");
+                    failed += 1;
+                }
+            }
+
+            let decile = scanned * 10 / total;
+            if decile > reported_decile {
+                reported_decile = decile;
+                info!(
+                    scanned,
+                    total,
+                    failed,
+                    bridge_and_mints = index.bridge_and_mints.len(),
+                    transfers = index.transfers.len(),
+                    "Indexing 1Money checkpoints"
+                );
+            }
+        }
+
+        index
+    }
+
+    /// Adds `tx` to the index if it is a `TokenBridgeAndMint` or `TokenTransfer` of the relayer.
+    pub fn insert(&mut self, tx: &Transaction) {
+        if tx.from != self.relayer {
+            return;
+        }
+        let (transactions, value, recipient, token) = match &tx.data {
+            TxPayload::TokenBridgeAndMint {
+                value,
+                recipient,
+                token,
+                ..
+            } => (&mut self.bridge_and_mints, value, recipient, *token),
+            // Refunds always name their token, transfers without one are not the relayer's.
+            TxPayload::TokenTransfer {
+                value,
+                recipient,
+                token: Some(token),
+            } => (&mut self.transfers, value, recipient, *token),
+            _ => return,
+        };
+        let Ok(value) = value.parse() else {
+            warn!(hash = %tx.hash, %value, "Transaction with an invalid value");
+            return;
+        };
+        transactions.insert(
+            TransferKey {
+                nonce: tx.nonce,
+                recipient: *recipient,
+                token,
+                value,
+            },
+            tx.hash,
+        );
+    }
+
+    /// Returns the hash of the `TokenBridgeAndMint` transaction identified by `key`.
+    pub fn bridge_and_mint(&self, key: &TransferKey) -> Option<B256> {
+        self.bridge_and_mints.get(key).copied()
+    }
+
+    /// Returns the hash of the `TokenTransfer` transaction identified by `key`.
+    pub fn transfer(&self, key: &TransferKey) -> Option<B256> {
+        self.transfers.get(key).copied()
+    }
+}
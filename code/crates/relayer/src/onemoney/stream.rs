@@ -1,27 +1,33 @@
 use core::time::Duration;
+use std::sync::Arc;
 
 use alloy_primitives::B256;
-use async_stream::try_stream;
+use async_stream::{stream, try_stream};
 use futures::stream::BoxStream;
 use futures::{SinkExt, StreamExt};
+use humantime::format_duration;
 use onemoney_protocol::{Transaction, TxPayload};
 use serde_json::json;
-use tokio::time::interval;
-use tokio_tungstenite::tungstenite::Message;
+use tokio::net::TcpStream;
+use tokio::time::{interval, timeout};
+use tokio_tungstenite::tungstenite::{Bytes, Message};
+use tokio_tungstenite::{MaybeTlsStream, WebSocketStream};
 use tracing::{debug, info, warn};
+use url::Url;
 
-use crate::config::Config;
-use crate::onemoney::error::Error;
+use crate::config::OnemoneyWsConfig;
+use crate::onemoney::api::OneMoneyApi;
+use crate::onemoney::error::{Error, SubscriptionError};
 use crate::onemoney::transaction::get_transactions_from_checkpoint;
 use crate::onemoney::types::transaction::CertifiedTransaction;
 
+type WebSocket = WebSocketStream<MaybeTlsStream<TcpStream>>;
+
 pub fn transaction_stream_from_checkpoint(
-    config: &Config,
+    onemoney: Arc<dyn OneMoneyApi>,
     start_checkpoint: u64,
     poll_interval: Duration,
 ) -> BoxStream<'static, Result<(u64, Vec<Transaction>), Error>> {
-    let config = config.clone();
-
     try_stream! {
         let mut interval = interval(poll_interval);
         let mut current_checkpoint_id = start_checkpoint;
@@ -30,7 +36,7 @@ pub fn transaction_stream_from_checkpoint(
             interval.tick().await;
 
             // TODO: This will be replaced by certified transactions
-            match get_transactions_from_checkpoint(config.one_money_node_url.to_string(), current_checkpoint_id, |tx| {
+            match get_transactions_from_checkpoint(onemoney.as_ref(), current_checkpoint_id, |tx| {
                 matches!(tx.data, TxPayload::TokenBurnAndBridge { .. })
             }).await {
                 Ok(transactions) => {
@@ -62,52 +68,141 @@ pub fn transaction_stream_from_checkpoint(
     .boxed()
 }
 
+/// Event of the subscription to the 1Money certified transactions.
+#[derive(Debug)]
+pub enum SubscriptionEvent {
+    /// The subscription was established, after startup or a reconnection.
+    Connected,
+    /// The connection was lost, a new one is attempted after `retry_in`.
+    ///
+    /// Transactions certified while disconnected are not replayed by the subscription.
+    Disconnected {
+        error: SubscriptionError,
+        retry_in: Duration,
+    },
+    /// A `BurnAndBridge` transaction was certified.
+    BurnAndBridge { payload: TxPayload, tx_hash: B256 },
+}
+
+/// Subscribes to the certified transactions of the 1Money websocket at `url`.
+///
+/// The subscription never ends: the websocket is reconnected with an exponential backoff
+/// whenever it fails, is closed by the server or stays silent for longer than the heartbeat
+/// timeout, and every connection change is reported as a [`SubscriptionEvent`].
 pub fn certified_transaction_stream(
-    config: &Config,
-) -> BoxStream<'static, Result<(TxPayload, B256), Error>> {
-    let config = config.clone();
+    url: Url,
+    settings: OnemoneyWsConfig,
+) -> BoxStream<'static, SubscriptionEvent> {
+    stream! {
+        let mut backoff = settings.one_money_ws_reconnect_backoff;
 
-    try_stream! {
-        let raw_ws = tokio_tungstenite::connect_async(&config.one_money_ws_url.to_string()).await;
-
-        let (mut ws, _resp) = raw_ws.unwrap();
-
-        let subscribe = json!({
-            "id": 1,
-            "method": "SUBSCRIBE",
-            "stream": { "name": "CERTIFIED_TRANSACTIONS", "full": true }
-        });
-
-        ws.send(Message::Text(subscribe.to_string().into()))
-            .await
-            .unwrap();
-
-        info!("send subscription: {}", config.one_money_ws_url);
-
-        // Read messages forever
-        while let Some(msg) = ws.next().await {
-            let msg = msg.unwrap();
-            match msg {
-                Message::Text(raw_tx) => {
-                    match serde_json::from_str::<CertifiedTransaction>(&raw_tx) {
-                        Ok(certified_transaction) => {
-                        let tx = certified_transaction.get_transaction_envelope().to_tx_payload();
-                        if matches!(tx, TxPayload::TokenBurnAndBridge { .. }) {
-                            yield (tx.clone(), certified_transaction.result.tx_hash);
-                        }
-                    }
-                        Err(err) => {
-                            warn!("failed to deserialize certified transaction: {err:?}");
+        loop {
+            let error = match subscribe(&url).await {
+                Ok(mut ws) => {
+                    info!(%url, "Subscribed to certified transactions");
+                    backoff = settings.one_money_ws_reconnect_backoff;
+                    yield SubscriptionEvent::Connected;
+
+                    loop {
+                        match next_message(&mut ws, settings.one_money_ws_heartbeat_timeout).await {
+                            Ok(Message::Text(raw_tx)) => {
+                                match serde_json::from_str::<CertifiedTransaction>(&raw_tx) {
+                                    Ok(certified_transaction) => {
+                                        let payload = certified_transaction.get_transaction_envelope().to_tx_payload();
+                                        if matches!(payload, TxPayload::TokenBurnAndBridge { .. }) {
+                                            yield SubscriptionEvent::BurnAndBridge {
+                                                payload,
+                                                tx_hash: certified_transaction.result.tx_hash,
+                                            };
+                                        }
+                                    }
+                                    Err(err) => {
+                                        warn!("failed to deserialize certified transaction: {err:?}");
+                                    }
+                                }
+                            }
+                            Ok(Message::Ping(payload)) => {
+                                if let Err(err) = ws.send(Message::Pong(payload)).await {
+                                    break SubscriptionError::Send(err);
+                                }
+                            }
+                            Ok(Message::Close(frame)) => {
+                                break SubscriptionError::Closed {
+                                    reason: frame.map_or_else(
+                                        || "no close frame".to_owned(),
+                                        |frame| format!("{} {}", frame.code, frame.reason),
+                                    ),
+                                };
+                            }
+                            Ok(_) => {}
+                            Err(error) => break error,
                         }
                     }
                 }
-                Message::Close(frame) => {
-                    warn!("Certified transactions websocket stream closed: {frame:?}");
-                    break;
-                }
-                _ => {}
-            }
+                Err(error) => error,
+            };
+
+            warn!(
+                %url,
+                %error,
+                retry_in = %format_duration(backoff),
+                "Certified transactions subscription interrupted, reconnecting",
+            );
+            yield SubscriptionEvent::Disconnected {
+                error,
+                retry_in: backoff,
+            };
+            tokio::time::sleep(backoff).await;
+            backoff = backoff
+                .saturating_mul(2)
+                .min(settings.one_money_ws_max_reconnect_backoff);
         }
     }
     .boxed()
 }
+
+/// Connects to the websocket at `url` and subscribes to the certified transactions.
+async fn subscribe(url: &Url) -> Result<WebSocket, SubscriptionError> {
+    let (mut ws, _resp) = tokio_tungstenite::connect_async(url.as_str())
+        .await
+        .map_err(SubscriptionError::Connect)?;
+
+    let subscribe = json!({
+        "id": 1,
+        "method": "SUBSCRIBE",
+        "stream": { "name": "CERTIFIED_TRANSACTIONS", "full": true }
+    });
+    ws.send(Message::Text(subscribe.to_string().into()))
+        .await
+        .map_err(SubscriptionError::Send)?;
+
+    Ok(ws)
+}
+
+/// Waits for the next message, pinging the server once the connection has been silent for
+/// half of `heartbeat_timeout` and failing once it has been silent for `heartbeat_timeout`.
+async fn next_message(
+    ws: &mut WebSocket,
+    heartbeat_timeout: Duration,
+) -> Result<Message, SubscriptionError> {
+    let mut pinged = false;
+    loop {
+        match timeout(heartbeat_timeout / 2, ws.next()).await {
+            Ok(Some(Ok(message))) => return Ok(message),
+            Ok(Some(Err(err))) => return Err(SubscriptionError::Receive(err)),
+            Ok(None) => {
+                return Err(SubscriptionError::Closed {
+                    reason: "connection closed".to_owned(),
+                })
+            }
+            Err(_) if pinged => return Err(SubscriptionError::HeartbeatTimeout(heartbeat_timeout)),
+            Err(_) => {
+                debug!("Certified transactions websocket silent, sending ping");
+                ws.send(Message::Ping(Bytes::new()))
+                    .await
+                    .map_err(SubscriptionError::Send)?;
+                pinged = true;
+            }
+        }
+    }
+}
@@ -0,0 +1,106 @@
+use core::time::Duration;
+
+use futures::{SinkExt, StreamExt};
+use tokio::net::TcpListener;
+use tokio_tungstenite::tungstenite::{Bytes, Message};
+use url::Url;
+
+use crate::config::OnemoneyWsConfig;
+use crate::onemoney::error::SubscriptionError;
+use crate::onemoney::stream::{certified_transaction_stream, SubscriptionEvent};
+
+fn settings() -> OnemoneyWsConfig {
+    OnemoneyWsConfig {
+        one_money_ws_heartbeat_timeout: Duration::from_millis(200),
+        one_money_ws_reconnect_backoff: Duration::from_millis(10),
+        one_money_ws_max_reconnect_backoff: Duration::from_millis(50),
+    }
+}
+
+#[tokio::test]
+async fn test_subscription_reconnects_after_close_and_silence() {
+    let listener = TcpListener::bind("127.0.0.1:0").await.expect("bound");
+    let url: Url = format!("ws://{}", listener.local_addr().expect("local address"))
+        .parse()
+        .expect("valid url");
+
+    let server = tokio::spawn(async move {
+        // First connection answers a ping and is then closed by the server
+        let (socket, _) = listener.accept().await.expect("first connection");
+        let mut ws = tokio_tungstenite::accept_async(socket)
+            .await
+            .expect("handshake");
+        let subscribe = ws.next().await.expect("message").expect("subscription");
+        assert!(subscribe
+            .to_text()
+            .expect("text subscription")
+            .contains("CERTIFIED_TRANSACTIONS"));
+        ws.send(Message::Ping(Bytes::from_static(b"heartbeat")))
+            .await
+            .expect("ping sent");
+        assert_eq!(
+            ws.next().await.expect("message").expect("pong"),
+            Message::Pong(Bytes::from_static(b"heartbeat"))
+        );
+        ws.close(None).await.expect("closed");
+
+        // Second connection re-subscribes and then stays silent
+        let (socket, _) = listener.accept().await.expect("second connection");
+        let mut ws = tokio_tungstenite::accept_async(socket)
+            .await
+            .expect("handshake");
+        let subscribe = ws.next().await.expect("message").expect("subscription");
+        assert!(subscribe.is_text());
+        tokio::time::sleep(Duration::from_secs(1)).await;
+    });
+
+    let mut subscription = certified_transaction_stream(url, settings());
+
+    assert!(matches!(
+        subscription.next().await,
+        Some(SubscriptionEvent::Connected)
+    ));
+    assert!(matches!(
+        subscription.next().await,
+        Some(SubscriptionEvent::Disconnected {
+            error: SubscriptionError::Closed { .. },
+            ..
+        })
+    ));
+    assert!(matches!(
+        subscription.next().await,
+        Some(SubscriptionEvent::Connected)
+    ));
+    assert!(matches!(
+        subscription.next().await,
+        Some(SubscriptionEvent::Disconnected {
+            error: SubscriptionError::HeartbeatTimeout(_),
+            ..
+        })
+    ));
+
+    server.await.expect("server assertions");
+}
+
+#[tokio::test]
+async fn test_subscription_backs_off_while_unreachable() {
+    let listener = TcpListener::bind("127.0.0.1:0").await.expect("bound");
+    let url: Url = format!("ws://{}", listener.local_addr().expect("local address"))
+        .parse()
+        .expect("valid url");
+    drop(listener);
+
+    let mut subscription = certified_transaction_stream(url, settings());
+
+    let mut delays = Vec::new();
+    for _ in 0..4 {
+        match subscription.next().await {
+            Some(SubscriptionEvent::Disconnected {
+                error: SubscriptionError::Connect(_),
+                retry_in,
+            }) => delays.push(retry_in.as_millis()),
+            other => panic!("unexpected event: {other:?}"),
+        }
+    }
+    assert_eq!(delays, [10, 20, 40, 50]);
+}
@@ -1,12 +1,13 @@
 use core::time::Duration;
+use std::sync::Arc;
 
 use alloy_primitives::Address;
 use alloy_signer::k256::ecdsa::VerifyingKey;
 use alloy_signer::utils::public_key_to_address;
 use futures::TryStreamExt;
 use httpmock::prelude::*;
+use onemoney_protocol::client::http::Client;
 use serde_json::json;
-use url::Url;
 
 use crate::onemoney::epoch_stream;
 use crate::onemoney::tests::utils::consensus_key;
@@ -68,8 +69,8 @@ async fn test_epoch_stream_emits_epoch_from_mock() {
         })
         .await;
 
-    let url = Url::parse(&server.base_url()).expect("valid base url");
-    let mut stream = epoch_stream(url, Duration::from_millis(200));
+    let client = Client::custom(server.base_url()).expect("client is created");
+    let mut stream = epoch_stream(Arc::new(client), Duration::from_millis(200));
 
     let result = tokio::time::timeout(Duration::from_secs(5), stream.try_next())
         .await
@@ -78,6 +79,7 @@ async fn test_epoch_stream_emits_epoch_from_mock() {
     let epoch = result.expect("stream error").expect("no epoch emitted");
     assert_eq!(epoch.epoch_id, 1);
     assert_eq!(epoch.validator_set.members.len(), 1);
+    assert_eq!(epoch.validator_set.members[0].voting_power, None);
 
     mock.assert_async().await;
 }
@@ -98,8 +100,8 @@ async fn test_epoch_stream_stops_after_bad_payload() {
         })
         .await;
 
-    let url = Url::parse(&server.base_url()).expect("valid base url");
-    let mut stream = epoch_stream(url.clone(), Duration::from_millis(200));
+    let client = Client::custom(server.base_url()).expect("client is created");
+    let mut stream = epoch_stream(Arc::new(client), Duration::from_millis(200));
 
     // First poll should surface the JSON decode error.
     let err = tokio::time::timeout(Duration::from_secs(5), stream.try_next())
@@ -107,7 +109,7 @@ async fn test_epoch_stream_stops_after_bad_payload() {
         .expect("timed out waiting for error")
         .expect_err("expected stream error");
     assert!(
-        matches!(err, crate::onemoney::error::Error::Http(_)),
+        matches!(err, crate::onemoney::error::Error::FailedQuery(_)),
         "unexpected error variant: {err:?}"
     );
     error_mock.assert_async().await;
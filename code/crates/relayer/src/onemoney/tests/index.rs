@@ -0,0 +1,76 @@
+use alloy_primitives::{address, b256, U256};
+use httpmock::prelude::*;
+use onemoney_protocol::client::http::Client;
+
+use crate::onemoney::index::{RelayerTransactions, TransferKey};
+
+const CHECKPOINT_JSON: &str = include_str!("data/checkpoint.json");
+
+/// Key of the `TokenTransfer` transaction of `data/checkpoint.json`.
+const TRANSFER: TransferKey = TransferKey {
+    nonce: 0,
+    recipient: address!("0xc7a8e117cb43d7935da4c30b9f9d0cdb5a372808"),
+    token: address!("0xf864012249f6843fbdc1eb0d55aff9252c09cef8"),
+    value: U256::from_limbs([200_000_000, 0, 0, 0]),
+};
+
+async fn serve_checkpoints(server: &MockServer) {
+    let response_body: serde_json::Value =
+        serde_json::from_str(CHECKPOINT_JSON).expect("failed to read data/checkpoint.json");
+    server
+        .mock_async(|when, then| {
+            when.method(GET)
+                .path("/v1/checkpoints/by_number")
+                .query_param("number", "1")
+                .query_param("full", "true");
+            then.status(200)
+                .header("content-type", "application/json")
+                .json_body(response_body);
+        })
+        .await;
+    server
+        .mock_async(|when, then| {
+            when.method(GET)
+                .path("/v1/checkpoints/by_number")
+                .query_param("number", "2");
+            then.status(500);
+        })
+        .await;
+}
+
+#[tokio::test]
+async fn test_index_relayer_transfers() {
+    let server = MockServer::start_async().await;
+    serve_checkpoints(&server).await;
+
+    let client = Client::custom(server.base_url()).expect("client is created");
+    let relayer = address!("0x2d4c699936bcccbcde1ea12392b080181f17c2c2");
+    let index = RelayerTransactions::index(&client, relayer, 1..=2, 4).await;
+
+    assert_eq!(
+        index.transfer(&TRANSFER),
+        Some(b256!(
+            "0x05e0238f5f130a41d1102d9e532e338eac86f6d1bf427e39fb9e427598c64034"
+        ))
+    );
+    assert_eq!(index.bridge_and_mint(&TRANSFER), None);
+    assert_eq!(
+        index.transfer(&TransferKey {
+            value: U256::from(1u64),
+            ..TRANSFER
+        }),
+        None
+    );
+}
+
+#[tokio::test]
+async fn test_index_ignores_other_senders() {
+    let server = MockServer::start_async().await;
+    serve_checkpoints(&server).await;
+
+    let client = Client::custom(server.base_url()).expect("client is created");
+    let relayer = address!("0xa634dfba8c7550550817898bc4820cd10888aac5");
+    let index = RelayerTransactions::index(&client, relayer, 1..=2, 4).await;
+
+    assert_eq!(index.transfer(&TRANSFER), None);
+}
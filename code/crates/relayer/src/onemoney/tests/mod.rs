@@ -1,4 +1,6 @@
+pub mod certified_transactions;
 pub mod epochs;
+pub mod index;
 pub mod query_transactions;
 
 pub mod utils;
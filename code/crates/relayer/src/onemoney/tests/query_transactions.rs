@@ -1,4 +1,5 @@
 use httpmock::prelude::*;
+use onemoney_protocol::client::http::Client;
 use onemoney_protocol::TxPayload;
 
 use crate::onemoney::transaction::get_transactions_from_checkpoint;
@@ -25,7 +26,8 @@ async fn test_get_token_create_tx() {
         })
         .await;
 
-    let raw_transactions = get_transactions_from_checkpoint(server.base_url(), 1, |tx| {
+    let client = Client::custom(server.base_url()).expect("client is created");
+    let raw_transactions = get_transactions_from_checkpoint(&client, 1, |tx| {
         matches!(tx.data, TxPayload::TokenCreate { .. })
     })
     .await;
@@ -54,7 +56,8 @@ async fn test_get_token_grant_authority_tx() {
         })
         .await;
 
-    let raw_transactions = get_transactions_from_checkpoint(server.base_url(), 1, |tx| {
+    let client = Client::custom(server.base_url()).expect("client is created");
+    let raw_transactions = get_transactions_from_checkpoint(&client, 1, |tx| {
         matches!(tx.data, TxPayload::TokenGrantAuthority { .. })
     })
     .await;
@@ -83,7 +86,8 @@ async fn test_get_token_mint_tx() {
         })
         .await;
 
-    let raw_transactions = get_transactions_from_checkpoint(server.base_url(), 1, |tx| {
+    let client = Client::custom(server.base_url()).expect("client is created");
+    let raw_transactions = get_transactions_from_checkpoint(&client, 1, |tx| {
         matches!(tx.data, TxPayload::TokenMint { .. })
     })
     .await;
@@ -112,7 +116,8 @@ async fn test_get_token_transfer_tx() {
         })
         .await;
 
-    let raw_transactions = get_transactions_from_checkpoint(server.base_url(), 1, |tx| {
+    let client = Client::custom(server.base_url()).expect("client is created");
+    let raw_transactions = get_transactions_from_checkpoint(&client, 1, |tx| {
         matches!(tx.data, TxPayload::TokenTransfer { .. })
     })
     .await;
@@ -1,20 +1,19 @@
-use onemoney_protocol::client::http::Client;
 use onemoney_protocol::responses::CheckpointTransactions;
 use onemoney_protocol::Transaction;
 
+use crate::onemoney::api::OneMoneyApi;
 use crate::onemoney::error::Error;
 
 pub async fn get_transactions_from_checkpoint<FilterFn>(
-    url: String,
+    onemoney: &dyn OneMoneyApi,
     checkpoint_number: u64,
     filter: FilterFn,
 ) -> Result<Vec<Transaction>, Error>
 where
     FilterFn: Fn(&Transaction) -> bool,
 {
-    let client = Client::custom(url)?;
-    let checkpoint = client
-        .get_checkpoint_by_number(checkpoint_number, true)
+    let checkpoint = onemoney
+        .checkpoint_by_number(checkpoint_number, true)
         .await?;
 
     match checkpoint
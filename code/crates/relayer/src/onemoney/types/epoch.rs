@@ -1,5 +1,6 @@
 use alloy_primitives::{Address, B256};
 use alloy_signer::k256::ecdsa::VerifyingKey;
+use onemoney_protocol::responses::EpochResponse;
 use serde::Deserialize;
 
 use crate::onemoney::types::utils::deserialize_verifying_key;
@@ -19,6 +20,15 @@ pub struct RawEpoch {
     pub certificate: Certificate,
 }
 
+impl TryFrom<EpochResponse> for RawEpoch {
+    type Error = serde_json::Error;
+
+    /// Decodes the certificate of an epoch served as JSON, a BCS encoded certificate is rejected.
+    fn try_from(response: EpochResponse) -> Result<Self, Self::Error> {
+        serde_json::from_value(serde_json::to_value(response)?)
+    }
+}
+
 impl From<RawEpoch> for Epoch {
     fn from(raw: RawEpoch) -> Self {
         let message = match raw.certificate {
@@ -13,6 +13,9 @@ pub struct Validator {
     pub address: Address,
     pub peer_id: String,
     pub archive: bool,
+    /// Voting power of the validator in the epoch, when exposed by 1Money
+    #[serde(default, alias = "power")]
+    pub voting_power: Option<u64>,
 }
 
 impl core::hash::Hash for Validator {
@@ -21,19 +24,15 @@ impl core::hash::Hash for Validator {
         self.address.hash(state);
         self.peer_id.hash(state);
         self.archive.hash(state);
+        self.voting_power.hash(state);
     }
 }
 
-impl TryFrom<Validator> for ValidatorInfo {
-    type Error = OnemoneyError;
-
-    fn try_from(v: Validator) -> Result<Self, Self::Error> {
-        let Validator {
-            consensus_public_key,
-            address,
-            ..
-        } = v;
-        let pubkey = consensus_public_key.to_encoded_point(false);
+impl Validator {
+    /// Returns the sidechain registration of the validator with the voting power `power`.
+    pub fn validator_info(&self, power: u64) -> Result<ValidatorInfo, OnemoneyError> {
+        let address = self.address;
+        let pubkey = self.consensus_public_key.to_encoded_point(false);
         let x = U256::from_be_slice(
             pubkey
                 .x()
@@ -44,9 +43,9 @@ impl TryFrom<Validator> for ValidatorInfo {
                 .y()
                 .ok_or(OnemoneyError::InvalidValidatorKey { address })?,
         );
-        Ok(Self {
+        Ok(ValidatorInfo {
             validatorKey: Secp256k1Key { x, y },
-            power: 100, // Default power, update as needed
+            power,
         })
     }
 }
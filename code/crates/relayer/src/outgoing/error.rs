@@ -14,6 +14,8 @@ pub enum Error {
     ContractCall(#[from] alloy_contract::Error),
     #[error("Pending transaction failed: {0}")]
     PendingTransaction(#[from] alloy_provider::PendingTransactionError),
+    #[error("Sidechain transaction failed: {0}")]
+    Transaction(#[from] crate::sidechain::error::TransactionError),
     #[error("Alloy RPC Transport error: {0}")]
     ContractRpcTransport(#[from] alloy_transport::RpcError<alloy_transport::TransportErrorKind>),
     #[error(transparent)]
@@ -22,6 +24,10 @@ pub enum Error {
     ContractReverted(onemoney_interop::contract::OMInterop::OMInteropErrors),
     #[error("Contract reverted: {0:?}")]
     MappingContractReverted(onemoney_interop::contract::TxHashMapping::TxHashMappingErrors),
+    #[error("Failed to issue refund: {0}")]
+    Refund(#[from] crate::incoming::error::Error),
+    #[error("State store error: {0}")]
+    Store(#[from] crate::store::error::Error),
     #[error("Missing checkpoint number in transaction")]
     MissingCheckpointNumber,
     #[error("Generic error: {0}")]
@@ -1,238 +1,386 @@
-use core::sync::atomic::Ordering;
+use core::ops::RangeInclusive;
 
-use alloy_primitives::FixedBytes;
+use alloy_primitives::{Address, FixedBytes, TxHash, B256};
 use alloy_provider::{Provider, ProviderBuilder};
 use alloy_rpc_types_eth::{BlockNumberOrTag, Filter};
 use alloy_sol_types::SolEvent;
 use onemoney_interop::contract::OMInterop::{self, OMInteropSent};
 use onemoney_interop::contract::TxHashMapping;
-use onemoney_protocol::{CheckpointTransactions, Client, TxPayload};
-use tracing::warn;
+use tracing::{debug, info, warn};
 
-use crate::config::{Config, RelayerNonce};
+use crate::config::Config;
+use crate::context::RelayerContext;
+use crate::incoming::handlers::Relayer1MoneyContext;
+use crate::metrics::metrics;
+use crate::onemoney::index::{RelayerTransactions, TransferKey, CHECKPOINT_FETCH_CONCURRENCY};
 use crate::outgoing::error::Error;
+use crate::sidechain::tx_manager::TxManager;
+use crate::signer::Role;
+use crate::store::{StateStore, TransferKind, TransferState};
 
 const MAX_BLOCK_RANGE: u64 = 100_000;
 
 pub async fn get_earliest_incomplete_checkpoint_number(config: &Config) -> Result<u64, Error> {
-    let provider = ProviderBuilder::new()
-        .wallet(config.relayer_private_key.clone())
-        .connect_http(config.side_chain_http_url.clone());
+    let provider = ProviderBuilder::new().connect_http(config.side_chain_http_url.clone());
     let contract = OMInterop::new(config.interop_contract_address, provider);
 
     let res = contract.getLatestCompletedCheckpoint().call().await?;
     Ok(res)
 }
 
+/// Returns the checkpoint from which the outgoing clearing flow should resume.
+///
+/// The checkpoint following the last one recorded in the state store is used when available.
+/// Falls back to [`get_earliest_incomplete_checkpoint_number`] when the store is empty or its
+/// cursor is ahead of the latest 1Money checkpoint.
+pub async fn resume_checkpoint_number(ctx: &RelayerContext) -> Result<u64, Error> {
+    if let Some(checkpoint) = ctx.store.snapshot().checkpoint_cursor {
+        let last_checkpoint = ctx.onemoney.latest_checkpoint_number().await?;
+
+        if checkpoint <= last_checkpoint {
+            info!(checkpoint, "Resuming 1Money checkpoints from state store");
+            return Ok(checkpoint + 1);
+        }
+
+        warn!(
+            checkpoint,
+            last_checkpoint,
+            "State store cursor is ahead of 1Money, deriving start checkpoint from chain state"
+        );
+    }
+
+    get_earliest_incomplete_checkpoint_number(&ctx.config).await
+}
+
 pub async fn recover_incomplete_withdrawals_hash_mapping(
-    config: &Config,
-    relayer_nonce: RelayerNonce,
-    start_checkpoint: Option<u64>,
+    ctx: &RelayerContext,
     start_block: Option<u64>,
 ) -> Result<(), Error> {
-    let provider = ProviderBuilder::new()
-        .wallet(config.relayer_private_key.clone())
-        .connect_http(config.side_chain_http_url.clone());
+    let RelayerContext {
+        config,
+        tx_manager,
+        store,
+        ..
+    } = ctx;
+    let provider = ProviderBuilder::new().connect_http(config.side_chain_http_url.clone());
     let mapping_contract = TxHashMapping::new(config.tx_mapping_contract_address, provider.clone());
 
     let incomplete_hashes = mapping_contract.incompleteWithdrawals().call().await?;
 
-    let client = Client::custom(config.one_money_node_url.to_string())?;
+    // Forget stored withdrawals which have been linked since they were recorded
+    for hash in store.snapshot().withdrawals.into_keys() {
+        if incomplete_hashes.contains(&hash) {
+            continue;
+        }
+        let withdrawal_hashes = mapping_contract.getWithdrawal(hash).call().await?;
+        if withdrawal_hashes.bridgeTo != FixedBytes::ZERO {
+            store
+                .complete_transfer(TransferKind::Withdrawal, hash)
+                .await?;
+        }
+    }
 
-    // If a start checkpoint has been given use it, else start from 0
-    let start = start_checkpoint.unwrap_or_default();
     // If a start block has been given use it, else start from 0
     let start_block = start_block.unwrap_or_default();
+    let latest_block = provider.get_block_number().await?;
 
-    let last_checkpoint = client.get_checkpoint_number().await?.number;
-
-    'hash_loop: for hash in incomplete_hashes.iter() {
-        let tx_hash = *hash;
-        let withdrawal_hashes = mapping_contract.getWithdrawal(tx_hash).call().await?;
+    for hash in incomplete_hashes {
+        // The bridgeTo hash is already known if the relayer stopped before linking it
+        let bridge_to_tx_hash = match store.transfer(TransferKind::Withdrawal, hash) {
+            Some(TransferState::Relayed { destination_hash }) => destination_hash,
+            _ => {
+                let Some((_, bridge_to_tx_hash)) = find_om_interop_sent(
+                    &provider,
+                    config.interop_contract_address,
+                    hash,
+                    start_block..=latest_block,
+                )
+                .await?
+                else {
+                    warn!(burn_and_bridge_hash = %hash, "No bridgeTo transaction found for the withdrawal. The mapping will be done when recovering transactions");
+                    continue;
+                };
+                bridge_to_tx_hash
+            }
+        };
 
-        let tx_receipt = match client
-            .get_transaction_receipt_by_hash(&hash.to_string())
+        match tx_manager
+            .send(
+                Role::TxMapping,
+                mapping_contract.linkWithdrawalHashes(hash, bridge_to_tx_hash),
+            )
             .await
-        {
-            Ok(tx_receipt) => tx_receipt,
+            .map_err(|e| {
+                e.try_decode_revert::<TxHashMapping::TxHashMappingErrors>()
+                    .map_or_else(Error::from, Error::MappingContractReverted)
+            }) {
+            Ok(_) => {
+                store
+                    .complete_transfer(TransferKind::Withdrawal, hash)
+                    .await?;
+                metrics().hash_mapping_recovered(TransferKind::Withdrawal);
+            }
             Err(e) => {
-                warn!("Failed to query `{tx_hash}` receipt, most likely due to the receipt not existing. The mapping will be done when recovering transactions. Cause {e}");
-                continue 'hash_loop;
+                warn!(
+                    burn_and_bridge_hash = %hash,
+                    bridge_to_hash = %bridge_to_tx_hash,
+                    error = %e,
+                    "Failed to link withdrawal hashes"
+                );
             }
-        };
+        }
+    }
 
-        let latest: u64 = provider.get_block_number().await?;
-        let deployment_block: u64 = start_block;
-        let mut from_block = deployment_block;
-
-        let mut bridge_to_tx_hash = None;
-        let mut refund_amount = None;
-        let mut om_token = None;
-        let mut sidechain_nonce = None;
-        let mut from = None;
-
-        while from_block <= latest {
-            let to = (from_block + MAX_BLOCK_RANGE - 1).min(latest);
-
-            // The filter is safe because `from` and `sourceHash` are indexed in `OMInteropSent`
-            let filter = Filter::new()
-                .address(config.interop_contract_address)
-                .topic1(tx_receipt.from)
-                .topic3(tx_hash)
-                .from_block(BlockNumberOrTag::Number(from_block))
-                .to_block(BlockNumberOrTag::Number(to));
-
-            let logs = provider.get_logs(&filter).await?;
-
-            for log in logs {
-                if let Ok(parsed) = OMInteropSent::decode_raw_log(log.topics(), &log.data().data) {
-                    if parsed.sourceHash != tx_hash || parsed.from != tx_receipt.from {
-                        continue;
-                    }
-
-                    if let Some(transaction_hash) = log.transaction_hash {
-                        bridge_to_tx_hash = Some(transaction_hash);
-                        refund_amount = Some(parsed.refundAmount);
-                        om_token = Some(parsed.omToken);
-                        sidechain_nonce = Some(parsed.nonce);
-                        from = Some(parsed.from);
-                        break;
-                    }
-                }
-            }
+    Ok(())
+}
 
-            if bridge_to_tx_hash.is_some() {
-                break;
-            }
+/// Links the refunds of the withdrawals listed by `incompleteRefunds` in the Tx Hash Mapping
+/// contract.
+///
+/// The refund of a withdrawal is described by its `OMInteropSent` event, searched from
+/// `start_block`, and looked up in the 1Money checkpoints from `start_checkpoint`. Refunds
+/// which were never sent are issued, in the order of their nonces, as long as their nonce is
+/// the next one of the relayer account on 1Money.
+pub async fn recover_incomplete_refunds_hash_mapping(
+    ctx: &RelayerContext,
+    start_checkpoint: Option<u64>,
+    start_block: Option<u64>,
+) -> Result<(), Error> {
+    let RelayerContext {
+        config,
+        signer,
+        tx_manager,
+        store,
+        onemoney,
+        ..
+    } = ctx;
+    let provider = ProviderBuilder::new().connect_http(config.side_chain_http_url.clone());
+    let mapping_contract = TxHashMapping::new(config.tx_mapping_contract_address, provider.clone());
+
+    let incomplete_hashes = mapping_contract.incompleteRefunds().call().await?;
+
+    // Forget stored refunds which have been linked since they were recorded
+    for hash in store.snapshot().refunds.into_keys() {
+        if incomplete_hashes.contains(&hash) {
+            continue;
+        }
+        let withdrawal_hashes = mapping_contract.getWithdrawal(hash).call().await?;
+        if withdrawal_hashes.refund != FixedBytes::ZERO {
+            store.complete_transfer(TransferKind::Refund, hash).await?;
+        }
+    }
+
+    // If a start block has been given use it, else start from 0
+    let start_block = start_block.unwrap_or_default();
+    let latest_block = provider.get_block_number().await?;
+
+    // Refunds whose TokenTransfer transaction has to be found on 1Money
+    let mut unresolved = Vec::new();
+
+    for hash in incomplete_hashes {
+        // The refund hash is already known if the relayer stopped before linking it
+        if let Some(TransferState::Relayed { destination_hash }) =
+            store.transfer(TransferKind::Refund, hash)
+        {
+            link_refund_hashes(&mapping_contract, tx_manager, store, hash, destination_hash)
+                .await?;
+            continue;
+        }
 
-            from_block = to + 1;
+        match find_om_interop_sent(
+            &provider,
+            config.interop_contract_address,
+            hash,
+            start_block..=latest_block,
+        )
+        .await?
+        {
+            Some((sent, _)) => unresolved.push(sent),
+            None => debug!(
+                burn_and_bridge_hash = %hash,
+                "Withdrawal not bridged yet, its refund is sent once bridged"
+            ),
         }
-        let bridge_to_tx_hash = bridge_to_tx_hash.ok_or_else(|| {
-            Error::Generic(format!(
-                "Failed to retrieve `tx_hash` for transaction `{tx_hash}`"
-            ))
-        })?;
-        let refund_amount = refund_amount.ok_or_else(|| {
-            Error::Generic(format!(
-                "Failed to retrieve `refundAmount` for transaction `{tx_hash}`"
-            ))
-        })?;
-        let sidechain_nonce = sidechain_nonce.ok_or_else(|| {
-            Error::Generic(format!(
-                "Failed to retrieve `nonce` for transaction `{tx_hash}`"
-            ))
-        })?;
-        let from = from.ok_or_else(|| {
-            Error::Generic(format!(
-                "Failed to retrieve `from` for transaction `{tx_hash}`"
-            ))
-        })?;
-
-        if withdrawal_hashes.bridgeTo == FixedBytes::ZERO {
-            match mapping_contract
-                .linkWithdrawalHashes(tx_hash, bridge_to_tx_hash)
-                .nonce(relayer_nonce.fetch_add(1, Ordering::SeqCst))
-                .send()
-                .await
-                .map(Ok)
-                .or_else(|e| {
-                    e.try_decode_into_interface_error::<TxHashMapping::TxHashMappingErrors>()
-                        .map(Err)
-                })?
-                .map_err(Error::MappingContractReverted)
-            {
-                Ok(pending_tx) => {
-                    if let Err(e) = pending_tx.get_receipt().await {
-                        warn!(
-                            burn_and_bridge_hash=%hash,
-                            bridge_to_hash=%bridge_to_tx_hash,
-                            error = %e,
-                            "Failed to retrieve `linkWithdrawalHashes` receipt"
-                        );
-                    }
-                }
-                Err(e) => {
-                    // If send failed, decrement the nonce
-                    relayer_nonce.fetch_sub(1, Ordering::SeqCst);
-                    warn!(
-                            burn_and_bridge_hash=%hash,
-                            bridge_to_hash=%bridge_to_tx_hash,
-                        error = %e,
-                        "Failed to link withdrawal hashes"
-                    );
-                }
+    }
+
+    if unresolved.is_empty() {
+        return Ok(());
+    }
+
+    // If a start checkpoint has been given use it, else start from 0
+    let start = start_checkpoint.unwrap_or_default();
+    let last_checkpoint = onemoney.latest_checkpoint_number().await?;
+
+    let index = if start <= last_checkpoint {
+        info!(
+            refunds = unresolved.len(),
+            start, last_checkpoint, "Searching 1Money checkpoints for unlinked refunds"
+        );
+        RelayerTransactions::index(
+            onemoney.as_ref(),
+            signer.address(),
+            start..=last_checkpoint,
+            CHECKPOINT_FETCH_CONCURRENCY,
+        )
+        .await
+    } else {
+        RelayerTransactions::new(signer.address())
+    };
+
+    // Refunds which are not on 1Money
+    let mut unsent = Vec::new();
+
+    for sent in unresolved {
+        let key = TransferKey {
+            nonce: sent.nonce,
+            recipient: sent.from,
+            token: sent.omToken,
+            value: sent.refundAmount,
+        };
+        match index.transfer(&key) {
+            Some(refund_hash) => {
+                link_refund_hashes(
+                    &mapping_contract,
+                    tx_manager,
+                    store,
+                    sent.sourceHash,
+                    refund_hash,
+                )
+                .await?;
             }
+            None => unsent.push(sent),
         }
+    }
 
-        if withdrawal_hashes.refund == FixedBytes::ZERO {
-            for i in start..=last_checkpoint {
-                let checkpoint = match client.get_checkpoint_by_number(i, true).await {
-                    Ok(ch) => ch,
-                    Err(e) => {
-                        warn!("Failed to query checkpoint `{i}`. Cause: {e}");
-                        continue;
-                    }
-                };
+    issue_refunds(ctx, unsent).await
+}
 
-                let maybe_token_transfer_transaction = match &checkpoint.transactions {
-                    CheckpointTransactions::Full(txs) => txs.iter().find(|tx| {
-                        if let TxPayload::TokenTransfer {
-                            value,
-                            recipient,
-                            token,
-                        } = &tx.data
-                        {
-                            tx.nonce == sidechain_nonce
-                                && *value == refund_amount.to_string()
-                                && *token == om_token
-                                && *recipient == from
-                        } else {
-                            false
-                        }
-                    }),
-                    _ => None,
-                };
+/// Sends the refunds of `unsent` to 1Money, starting from the next nonce of the relayer account.
+///
+/// Refunds whose nonce is already used are not found in the scanned checkpoints, they are only
+/// reported. Refunds after a nonce gap are left to the transaction clearing, which relays the
+/// events of the missing nonces first.
+async fn issue_refunds(ctx: &RelayerContext, mut unsent: Vec<OMInteropSent>) -> Result<(), Error> {
+    if unsent.is_empty() {
+        return Ok(());
+    }
+    unsent.sort_by_key(|sent| sent.nonce);
+
+    let RelayerContext {
+        config,
+        signer,
+        tx_manager,
+        store,
+        onemoney,
+        ..
+    } = ctx;
+    let relayer_ctx =
+        Relayer1MoneyContext::new(onemoney.as_ref(), signer, tx_manager.dry_run()).await?;
+    let mut next_nonce = onemoney.account_nonce(signer.address()).await?;
+
+    for sent in unsent {
+        let burn_and_bridge_hash = sent.sourceHash;
+        let nonce = sent.nonce;
 
-                if let Some(token_transfer_transaction) = maybe_token_transfer_transaction {
-                    match mapping_contract
-                        .linkRefundHashes(tx_hash, token_transfer_transaction.hash)
-                        .nonce(relayer_nonce.fetch_add(1, Ordering::SeqCst))
-                        .send()
-                        .await
-                        .map(Ok)
-                        .or_else(|e| {
-                            e.try_decode_into_interface_error::<TxHashMapping::TxHashMappingErrors>(
-                            )
-                            .map(Err)
-                        })?
-                        .map_err(Error::MappingContractReverted)
-                    {
-                        Ok(pending_tx) => {
-                            if let Err(e) = pending_tx.get_receipt().await {
-                                warn!(
-                                    bridge_from_hash=%hash,
-                                    bridge_and_mint_hash=%token_transfer_transaction.hash,
-                                    error = %e,
-                                    "Failed to retrieve `linkRefundHashes` receipt"
-                                );
-                            }
-                        }
-                        Err(e) => {
-                            // If send failed, decrement the nonce
-                            relayer_nonce.fetch_sub(1, Ordering::SeqCst);
-                            warn!(
-                                    bridge_from_hash=%hash,
-                                    bridge_and_mint_hash=%token_transfer_transaction.hash,
-                                error = %e,
-                                "Failed to link refund hash"
-                            );
-                        }
-                    }
-                    // Process next incomplete deposit hash
-                    continue 'hash_loop;
-                }
+        if nonce < next_nonce {
+            warn!(
+                %burn_and_bridge_hash,
+                nonce,
+                next_nonce,
+                "Refund nonce already used on 1Money but no refund found in the scanned checkpoints"
+            );
+            continue;
+        }
+        if nonce > next_nonce {
+            info!(
+                %burn_and_bridge_hash,
+                nonce,
+                next_nonce,
+                "Refund waits for earlier nonces, leaving it to the transaction clearing"
+            );
+            break;
+        }
+
+        let refund_hash = relayer_ctx
+            .handle_om_interop_sent(config, tx_manager, store, sent)
+            .await?;
+        info!(%burn_and_bridge_hash, ?refund_hash, nonce, "Issued missing refund");
+        next_nonce += 1;
+    }
+
+    Ok(())
+}
+
+/// Returns the `OMInteropSent` event emitted by bridging the withdrawal `source_hash` within
+/// `blocks`, with the hash of its `bridgeTo` transaction.
+async fn find_om_interop_sent<P: Provider>(
+    provider: &P,
+    interop_contract_address: Address,
+    source_hash: B256,
+    blocks: RangeInclusive<u64>,
+) -> Result<Option<(OMInteropSent, TxHash)>, Error> {
+    let mut from_block = *blocks.start();
+
+    while from_block <= *blocks.end() {
+        let to_block = (from_block + MAX_BLOCK_RANGE - 1).min(*blocks.end());
+
+        // The filter is safe because `sourceHash` is indexed in `OMInteropSent`
+        let filter = Filter::new()
+            .address(interop_contract_address)
+            .event_signature(OMInteropSent::SIGNATURE_HASH)
+            .topic3(source_hash)
+            .from_block(BlockNumberOrTag::Number(from_block))
+            .to_block(BlockNumberOrTag::Number(to_block));
+
+        for log in provider.get_logs(&filter).await? {
+            let Ok(sent) = OMInteropSent::decode_raw_log(log.topics(), &log.data().data) else {
+                continue;
+            };
+            if sent.sourceHash != source_hash {
+                continue;
+            }
+            if let Some(tx_hash) = log.transaction_hash {
+                return Ok(Some((sent, tx_hash)));
             }
         }
+
+        from_block = to_block + 1;
+    }
+
+    Ok(None)
+}
+
+async fn link_refund_hashes<P: Provider>(
+    mapping_contract: &TxHashMapping::TxHashMappingInstance<P>,
+    tx_manager: &TxManager,
+    store: &StateStore,
+    burn_and_bridge_hash: B256,
+    refund_hash: B256,
+) -> Result<(), Error> {
+    match tx_manager
+        .send(
+            Role::TxMapping,
+            mapping_contract.linkRefundHashes(burn_and_bridge_hash, refund_hash),
+        )
+        .await
+        .map_err(|e| {
+            e.try_decode_revert::<TxHashMapping::TxHashMappingErrors>()
+                .map_or_else(Error::from, Error::MappingContractReverted)
+        }) {
+        Ok(_) => {
+            store
+                .complete_transfer(TransferKind::Refund, burn_and_bridge_hash)
+                .await?;
+            metrics().hash_mapping_recovered(TransferKind::Refund);
+        }
+        Err(e) => {
+            warn!(
+                %burn_and_bridge_hash,
+                %refund_hash,
+                error = %e,
+                "Failed to link refund hash"
+            );
+        }
     }
 
     Ok(())
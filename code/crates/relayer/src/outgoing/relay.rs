@@ -1,39 +1,38 @@
-use core::sync::atomic::Ordering;
-
 use alloy_primitives::{Bytes, FixedBytes, B256};
 use alloy_provider::ProviderBuilder;
 use onemoney_interop::contract::{OMInterop, TxHashMapping};
-use onemoney_protocol::{Client, TxPayload};
+use onemoney_protocol::TxPayload;
+use tokio::time::Instant;
 use tracing::{debug, warn};
 
-use crate::config::{Config, RelayerNonce};
+use crate::config::Config;
+use crate::context::RelayerContext;
+use crate::metrics::metrics;
 use crate::outgoing::error::Error;
+use crate::sidechain::tx_manager::TxManager;
+use crate::signer::Role;
+use crate::store::{TransferKind, TransferState};
 
 pub async fn process_checkpoint_info(
     config: &Config,
-    relayer_nonce: RelayerNonce,
+    tx_manager: &TxManager,
     current_checkpoint_id: u64,
     transaction_hashes: Vec<FixedBytes<32>>,
 ) -> Result<(), Error> {
-    let provider = ProviderBuilder::new()
-        .wallet(config.relayer_private_key.clone())
-        .connect_http(config.side_chain_http_url.clone());
+    let provider = ProviderBuilder::new().connect_http(config.side_chain_http_url.clone());
 
     let contract = OMInterop::new(config.interop_contract_address, provider);
 
-    let tx_receipt = contract
-        .updateCheckpointInfo(current_checkpoint_id, transaction_hashes)
-        .nonce(relayer_nonce.fetch_add(1, Ordering::SeqCst))
-        .send()
+    let tx_receipt = tx_manager
+        .send(
+            Role::Interop,
+            contract.updateCheckpointInfo(current_checkpoint_id, transaction_hashes),
+        )
         .await
-        .map(Ok)
-        .or_else(|e| {
-            e.try_decode_into_interface_error::<OMInterop::OMInteropErrors>()
-                .map(Err)
-        })?
-        .map_err(Error::ContractReverted)?
-        .get_receipt()
-        .await?;
+        .map_err(|e| {
+            e.try_decode_revert::<OMInterop::OMInteropErrors>()
+                .map_or_else(Error::from, Error::ContractReverted)
+        })?;
 
     debug!(
         ?tx_receipt,
@@ -46,22 +45,25 @@ pub async fn process_checkpoint_info(
 /// Process burn and bridge transactions by invoking the bridgeTo method on the OMInterop contract.
 /// This function expects a TokenBurnAndBridge transaction and extracts necessary details to call the contract method.
 pub async fn process_burn_and_bridge_transactions(
-    config: &Config,
-    relayer_nonce: RelayerNonce,
+    ctx: &RelayerContext,
     tx_data: TxPayload,
     tx_hash: B256,
     checkpoint_number: u64,
 ) -> Result<(), Error> {
-    let provider = ProviderBuilder::new()
-        .wallet(config.relayer_private_key.clone())
-        .connect_http(config.side_chain_http_url.clone());
+    let RelayerContext {
+        config,
+        tx_manager,
+        store,
+        onemoney,
+        ..
+    } = ctx;
+    let started = Instant::now();
+    let provider = ProviderBuilder::new().connect_http(config.side_chain_http_url.clone());
 
     let contract = OMInterop::new(config.interop_contract_address, provider.clone());
 
     let mapping_contract = TxHashMapping::new(config.tx_mapping_contract_address, provider);
 
-    let client = Client::custom(config.one_money_node_url.to_string())?;
-
     let TxPayload::TokenBurnAndBridge {
         value,
         sender,
@@ -77,130 +79,143 @@ pub async fn process_burn_and_bridge_transactions(
         ));
     };
 
-    debug!(burnAndBridgeHas = %tx_hash, "Will register withdrawal transaction hash");
+    let (registered, relayed) = match store.transfer(TransferKind::Withdrawal, tx_hash) {
+        Some(TransferState::Relayed { destination_hash }) => (true, Some(destination_hash)),
+        Some(TransferState::Registered) => (true, None),
+        Some(TransferState::Observed) => (false, None),
+        None => {
+            store
+                .set_transfer(TransferKind::Withdrawal, tx_hash, TransferState::Observed)
+                .await?;
+            (false, None)
+        }
+    };
 
-    match mapping_contract
-        .registerWithdrawal(tx_hash)
-        .nonce(relayer_nonce.fetch_add(1, Ordering::SeqCst))
-        .send()
-        .await
-        .map(Ok)
-        .or_else(|e| {
-            e.try_decode_into_interface_error::<TxHashMapping::TxHashMappingErrors>()
-                .map(Err)
-        })?
-        .map_err(Error::MappingContractReverted)
-    {
-        Ok(pending_tx) => {
-            if let Err(e) = pending_tx.get_receipt().await {
+    // Registering again would revert, e.g. when the checkpoint is cleared again after a failed
+    // `bridgeTo`
+    if !registered {
+        debug!(burnAndBridgeHas = %tx_hash, "Will register withdrawal transaction hash");
+
+        match tx_manager
+            .send(
+                Role::TxMapping,
+                mapping_contract.registerWithdrawal(tx_hash),
+            )
+            .await
+            .map_err(|e| {
+                e.try_decode_revert::<TxHashMapping::TxHashMappingErrors>()
+                    .map_or_else(Error::from, Error::MappingContractReverted)
+            }) {
+            Ok(_) => {
+                store
+                    .set_transfer(TransferKind::Withdrawal, tx_hash, TransferState::Registered)
+                    .await?;
+            }
+            Err(e) => {
                 warn!(
                     %tx_hash,
                     error = %e,
-                    "Failed to retrieve withdrawal transaction hash link receipt"
+                    "Failed to register withdrawal transaction hash"
                 );
             }
         }
-        Err(e) => {
-            // If send failed, decrement the nonce
-            relayer_nonce.fetch_sub(1, Ordering::SeqCst);
-            warn!(
-                %tx_hash,
-                error = %e,
-                "Failed to register withdrawal transaction hash"
-            );
-        }
     }
 
-    let burn_and_bridge_receipt = client
-        .get_transaction_receipt_by_hash(&tx_hash.to_string())
-        .await?;
-
-    // The bbnonce in the BurnAndBridge receipt is the account's next nonce,
-    // so we subtract 1 to get the current nonce.
-    let bbnonce = burn_and_bridge_receipt
-        .success_info
-        .ok_or_else(|| {
-            Error::Generic(format!(
-                "missing `success_info` in BurnAndBridge receipt for transaction `{tx_hash}`"
-            ))
-        })?
-        .bridge_info
-        .ok_or_else(|| {
-            Error::Generic(format!(
-                "missing `bridge_info` in BurnAndBridge receipt for transaction `{tx_hash}`"
-            ))
-        })?
-        .bbnonce
-        - 1;
-
-    // TODO: Handle bridgeData when it is added to the TokenBurnAndBridge.
-    // For now, we pass an empty bytes array.
-    let bridge_data = Bytes::new();
-
-    let latest_bb = contract.getLatestProcessedNonce(sender).call().await?;
-
-    if latest_bb > bbnonce {
-        warn!(burn_and_bridge_hash=%tx_hash, "Skipping BurnAndBridge as it was already processed");
-        return Ok(());
-    }
+    // The `bridgeTo` was mined before a restart, only the link is missing
+    let bridge_to_hash = match relayed {
+        Some(bridge_to_hash) => bridge_to_hash,
+        None => {
+            let burn_and_bridge_receipt = onemoney.transaction_receipt(tx_hash).await?;
+
+            // The bbnonce in the BurnAndBridge receipt is the account's next nonce,
+            // so we subtract 1 to get the current nonce.
+            let bbnonce = burn_and_bridge_receipt
+                .success_info
+                .ok_or_else(|| {
+                    Error::Generic(format!(
+                        "missing `success_info` in BurnAndBridge receipt for transaction `{tx_hash}`"
+                    ))
+                })?
+                .bridge_info
+                .ok_or_else(|| {
+                    Error::Generic(format!(
+                        "missing `bridge_info` in BurnAndBridge receipt for transaction `{tx_hash}`"
+                    ))
+                })?
+                .bbnonce
+                - 1;
+
+            // TODO: Handle bridgeData when it is added to the TokenBurnAndBridge.
+            // For now, we pass an empty bytes array.
+            let bridge_data = Bytes::new();
+
+            let latest_bb = contract.getLatestProcessedNonce(sender).call().await?;
+
+            if latest_bb > bbnonce {
+                warn!(burn_and_bridge_hash=%tx_hash, "Skipping BurnAndBridge as it was already processed");
+                return Ok(());
+            }
+
+            let tx_receipt = tx_manager
+                .send(
+                    Role::Interop,
+                    contract.bridgeTo(
+                        sender,
+                        bbnonce,
+                        destination_address.parse()?,
+                        value.parse()?,
+                        destination_chain_id.try_into()?,
+                        escrow_fee.parse()?,
+                        token,
+                        checkpoint_number,
+                        bridge_data,
+                        tx_hash,
+                    ),
+                )
+                .await
+                .map_err(|e| {
+                    e.try_decode_revert::<OMInterop::OMInteropErrors>()
+                        .map_or_else(Error::from, Error::ContractReverted)
+                })?;
+
+            debug!(?tx_receipt, "Tx receipt for bridge to");
+            metrics().burn_and_bridge_relayed();
+            metrics().observe_relay_latency(TransferKind::Withdrawal, started.elapsed());
+
+            store
+                .set_transfer(
+                    TransferKind::Withdrawal,
+                    tx_hash,
+                    TransferState::Relayed {
+                        destination_hash: tx_receipt.transaction_hash,
+                    },
+                )
+                .await?;
+            tx_receipt.transaction_hash
+        }
+    };
+
+    debug!(burnAndBridgeHas = %tx_hash, bridgeToHash = %bridge_to_hash, "Will link withdrawal transaction hash");
 
-    let tx_receipt = contract
-        .bridgeTo(
-            sender,
-            bbnonce,
-            destination_address.parse()?,
-            value.parse()?,
-            destination_chain_id.try_into()?,
-            escrow_fee.parse()?,
-            token,
-            checkpoint_number,
-            bridge_data,
-            tx_hash,
+    match tx_manager
+        .send(
+            Role::TxMapping,
+            mapping_contract.linkWithdrawalHashes(tx_hash, bridge_to_hash),
         )
-        .nonce(relayer_nonce.fetch_add(1, Ordering::SeqCst))
-        .send()
         .await
-        .map(Ok)
-        .or_else(|e| {
-            e.try_decode_into_interface_error::<OMInterop::OMInteropErrors>()
-                .map(Err)
-        })?
-        .map_err(Error::ContractReverted)?
-        .get_receipt()
-        .await?;
-
-    debug!(?tx_receipt, "Tx receipt for bridge to");
-
-    debug!(burnAndBridgeHas = %tx_hash, bridgeToHash = %tx_receipt.transaction_hash, "Will link withdrawal transaction hash");
-
-    match mapping_contract
-        .linkWithdrawalHashes(tx_hash, tx_receipt.transaction_hash)
-        .nonce(relayer_nonce.fetch_add(1, Ordering::SeqCst))
-        .send()
-        .await
-        .map(Ok)
-        .or_else(|e| {
-            e.try_decode_into_interface_error::<TxHashMapping::TxHashMappingErrors>()
-                .map(Err)
-        })?
-        .map_err(Error::MappingContractReverted)
-    {
-        Ok(pending_tx) => {
-            if let Err(e) = pending_tx.get_receipt().await {
-                warn!(
-                    burn_and_bridge_hash=%tx_hash,
-                    bridge_to_hash=%tx_receipt.transaction_hash,
-                    error = %e,
-                    "Failed to retrieve `linkWithdrawalHashes` receipt"
-                );
-            }
+        .map_err(|e| {
+            e.try_decode_revert::<TxHashMapping::TxHashMappingErrors>()
+                .map_or_else(Error::from, Error::MappingContractReverted)
+        }) {
+        Ok(_) => {
+            store
+                .complete_transfer(TransferKind::Withdrawal, tx_hash)
+                .await?;
         }
         Err(e) => {
-            // If send failed, decrement the nonce
-            relayer_nonce.fetch_sub(1, Ordering::SeqCst);
             warn!(
                     burn_and_bridge_hash=%tx_hash,
-                    bridge_to_hash=%tx_receipt.transaction_hash,
+                    %bridge_to_hash,
                 error = %e,
                 "Failed to link withdrawal hashes"
             );
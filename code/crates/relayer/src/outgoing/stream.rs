@@ -1,18 +1,24 @@
+use core::pin::pin;
 use core::time::Duration;
 
-use futures::TryStreamExt;
+use futures::{StreamExt, TryStreamExt};
 use humantime::format_duration;
 use tracing::{debug, error, info};
 
-use crate::config::{Config, RelayerNonce};
-use crate::onemoney::stream::{certified_transaction_stream, transaction_stream_from_checkpoint};
+use crate::context::RelayerContext;
+use crate::metrics::metrics;
+use crate::onemoney::stream::{
+    certified_transaction_stream, transaction_stream_from_checkpoint, SubscriptionEvent,
+};
 use crate::outgoing::error::Error;
 use crate::outgoing::relay::{process_burn_and_bridge_transactions, process_checkpoint_info};
+use crate::shutdown::Shutdown;
+use crate::signer::Role;
 
-pub async fn relay_outgoing_events(
-    config: &Config,
-    relayer_nonce: RelayerNonce,
-) -> Result<(), Error> {
+pub async fn relay_outgoing_events(ctx: &RelayerContext, shutdown: &Shutdown) -> Result<(), Error> {
+    let RelayerContext {
+        config, tx_manager, ..
+    } = ctx;
     info!(
         url = %config.one_money_node_url,
         "Connecting to onemoney",
@@ -22,41 +28,50 @@ pub async fn relay_outgoing_events(
         "Connecting to sidechain",
     );
     info!(
-        relayer_address = %config.relayer_private_key.address(),
+        relayer_address = %tx_manager.address(Role::Interop),
     );
 
-    let mut transaction_payload_stream = certified_transaction_stream(config);
+    // Stop pulling transactions on shutdown, the transaction being processed is completed first
+    let mut subscription = pin!(certified_transaction_stream(
+        config.one_money_ws_url.clone(),
+        config.one_money_ws.clone(),
+    )
+    .take_until(shutdown.requested()));
 
-    while let Some((transaction_payload, checkpoint_number)) =
-        transaction_payload_stream.try_next().await?
-    {
+    while let Some(event) = subscription.next().await {
+        let (transaction_payload, tx_hash) = match event {
+            SubscriptionEvent::BurnAndBridge { payload, tx_hash } => (payload, tx_hash),
+            // Transactions certified while disconnected are relayed by the clearing flow
+            SubscriptionEvent::Connected | SubscriptionEvent::Disconnected { .. } => continue,
+        };
         debug!(
             ?transaction_payload,
             "Processing BurnAndBridge transaction payload from stream"
         );
 
-        process_burn_and_bridge_transactions(
-            config,
-            relayer_nonce.clone(),
-            transaction_payload,
-            checkpoint_number,
-            0,
-        )
-        .await
-        .inspect_err(|err| {
-            error!(?err, "Failed processing burn and bridge transaction stream");
-        })?;
+        process_burn_and_bridge_transactions(ctx, transaction_payload, tx_hash, 0)
+            .await
+            .inspect_err(|err| {
+                error!(?err, "Failed processing burn and bridge transaction stream");
+            })?;
     }
 
     Ok(())
 }
 
 pub async fn relay_outgoing_events_from_checkpoints(
-    config: &Config,
-    relayer_nonce: RelayerNonce,
+    ctx: &RelayerContext,
     start_checkpoint: u64,
     poll_interval: Duration,
+    shutdown: &Shutdown,
 ) -> Result<(), Error> {
+    let RelayerContext {
+        config,
+        tx_manager,
+        store,
+        onemoney,
+        ..
+    } = ctx;
     info!(
         url = %config.one_money_node_url,
         "Connecting to onemoney",
@@ -66,7 +81,7 @@ pub async fn relay_outgoing_events_from_checkpoints(
         "Connecting to sidechain",
     );
     info!(
-        relayer_address = %config.relayer_private_key.address(),
+        relayer_address = %tx_manager.address(Role::Interop),
     );
 
     // TODO: Checkpoints will be replaced by certified transactions
@@ -76,7 +91,10 @@ pub async fn relay_outgoing_events_from_checkpoints(
     );
 
     let mut transaction_stream =
-        transaction_stream_from_checkpoint(config, start_checkpoint, poll_interval);
+        pin!(
+            transaction_stream_from_checkpoint(onemoney.clone(), start_checkpoint, poll_interval)
+                .take_until(shutdown.requested())
+        );
 
     while let Some((current_checkpoint_id, transactions)) = transaction_stream.try_next().await? {
         debug!(
@@ -89,7 +107,7 @@ pub async fn relay_outgoing_events_from_checkpoints(
 
         process_checkpoint_info(
             config,
-            relayer_nonce.clone(),
+            tx_manager,
             current_checkpoint_id,
             transaction_hashes,
         )
@@ -97,18 +115,15 @@ pub async fn relay_outgoing_events_from_checkpoints(
 
         for tx in transactions {
             let checkpoint_number = tx.checkpoint_number.ok_or(Error::MissingCheckpointNumber)?;
-            process_burn_and_bridge_transactions(
-                config,
-                relayer_nonce.clone(),
-                tx.data,
-                tx.hash,
-                checkpoint_number,
-            )
-            .await
-            .inspect_err(|err| {
-                error!(?err, "Failed processing burn and bridge transaction stream");
-            })?;
+            process_burn_and_bridge_transactions(ctx, tx.data, tx.hash, checkpoint_number)
+                .await
+                .inspect_err(|err| {
+                    error!(?err, "Failed processing burn and bridge transaction stream");
+                })?;
         }
+
+        store.advance_checkpoint_cursor(current_checkpoint_id);
+        metrics().checkpoint_cleared(current_checkpoint_id);
     }
 
     Ok(())
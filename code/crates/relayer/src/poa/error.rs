@@ -1,3 +1,5 @@
+use std::path::PathBuf;
+
 use alloy_primitives::Address;
 
 #[derive(Debug, thiserror::Error)]
@@ -6,6 +8,20 @@ pub enum Error {
     Onemoney(#[from] crate::onemoney::error::Error),
     #[error(transparent)]
     Sidechain(#[from] crate::sidechain::error::Error),
+    #[error(transparent)]
+    Store(#[from] crate::store::error::Error),
     #[error("Validator {address:?} has an invalid consensus public key")]
     InvalidValidatorKey { address: Address },
+    #[error("Failed to read validator power file `{path}`: {source}")]
+    ReadPowerFile {
+        path: PathBuf,
+        source: std::io::Error,
+    },
+    #[error("Failed to parse validator power file `{path}`: {source}")]
+    ParsePowerFile {
+        path: PathBuf,
+        source: serde_json::Error,
+    },
+    #[error("Validator {address:?} is given a zero voting power")]
+    ZeroPower { address: Address },
 }
@@ -1,20 +1,30 @@
+use core::pin::pin;
 use core::time::Duration;
 
 use futures::StreamExt;
 use humantime::format_duration;
 use tracing::{debug, error, info};
-use validator_manager::ValidatorManager::ValidatorInfo;
 
-use crate::config::{Config, RelayerNonce};
+use crate::context::RelayerContext;
+use crate::metrics::metrics;
 use crate::poa::error::Error as PoaError;
+use crate::poa::power::PowerPolicy;
+use crate::shutdown::Shutdown;
+use crate::signer::Role;
 
 pub mod error;
+pub mod power;
 
 pub async fn relay_poa_events(
-    config: &Config,
-    relayer_nonce: RelayerNonce,
+    ctx: &RelayerContext,
     poll_interval: Duration,
+    power_policy: &PowerPolicy,
+    shutdown: &Shutdown,
 ) -> Result<(), PoaError> {
+    let config = &ctx.config;
+    let tx_manager = &ctx.tx_manager;
+    let store = &ctx.store;
+
     info!(
         "Connecting to onemoney endpoint: {}",
         config.one_money_node_url
@@ -25,32 +35,46 @@ pub async fn relay_poa_events(
     );
     info!(
         "Using relayer address: {}",
-        config.relayer_private_key.address()
+        tx_manager.address(Role::ValidatorManager)
     );
     info!("Fetching epochs every {}", format_duration(poll_interval));
 
-    let mut epoch_stream =
-        crate::onemoney::epoch_stream(config.one_money_node_url.clone(), poll_interval);
+    let mut epoch_stream = pin!(
+        crate::onemoney::epoch_stream(ctx.onemoney.clone(), poll_interval)
+            .take_until(shutdown.requested())
+    );
     while let Some(epoch_result) = epoch_stream.next().await {
         match epoch_result {
             Ok(epoch) => {
+                if store
+                    .snapshot()
+                    .epoch_id
+                    .is_some_and(|applied| applied >= epoch.epoch_id)
+                {
+                    info!(
+                        epoch = epoch.epoch_id,
+                        "Validator set of epoch already applied; skipping update"
+                    );
+                    continue;
+                }
+
                 info!(epoch = epoch.epoch_id, "Updating validator set");
                 debug!(?epoch, "Epoch details");
-                let sidechain_validator_info = epoch
-                    .validator_set
-                    .members
-                    .into_iter()
-                    .map(ValidatorInfo::try_from)
-                    .collect::<Result<Vec<_>, _>>()?;
-
-                if let Err(err) = crate::sidechain::process_new_validator_set(
+                let sidechain_validator_info =
+                    power_policy.validator_set(&epoch.validator_set.members)?;
+
+                match crate::sidechain::process_new_validator_set(
                     config,
-                    relayer_nonce.clone(),
+                    tx_manager,
                     sidechain_validator_info,
                 )
                 .await
                 {
-                    error!("Failed updating validator set: {:?}", err);
+                    Ok(()) => {
+                        store.set_epoch(epoch.epoch_id).await?;
+                        metrics().validator_set_updated();
+                    }
+                    Err(err) => error!("Failed updating validator set: {:?}", err),
                 }
             }
             Err(e) => {
@@ -0,0 +1,149 @@
+//! Voting power given to the 1Money validators on the sidechain.
+
+use std::collections::HashMap;
+use std::fs;
+
+use alloy_primitives::Address;
+use validator_manager::ValidatorManager::ValidatorInfo;
+
+use crate::config::ValidatorPowerConfig;
+use crate::onemoney::error::Error as OnemoneyError;
+use crate::onemoney::types::validator::Validator;
+use crate::poa::error::Error as PoaError;
+
+/// Voting power of the validators, taken in order of precedence from the power file, from the
+/// epoch and from the default power.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct PowerPolicy {
+    default_power: u64,
+    overrides: HashMap<Address, u64>,
+}
+
+impl PowerPolicy {
+    pub const fn new(default_power: u64, overrides: HashMap<Address, u64>) -> Self {
+        Self {
+            default_power,
+            overrides,
+        }
+    }
+
+    /// Creates the policy of `config`, reading its power file if one is set.
+    pub fn load(config: &ValidatorPowerConfig) -> Result<Self, PoaError> {
+        let Some(path) = &config.validator_power_file else {
+            return Ok(Self::new(config.default_validator_power, HashMap::new()));
+        };
+
+        let contents = fs::read_to_string(path).map_err(|source| PoaError::ReadPowerFile {
+            path: path.clone(),
+            source,
+        })?;
+        let overrides: HashMap<Address, u64> =
+            serde_json::from_str(&contents).map_err(|source| PoaError::ParsePowerFile {
+                path: path.clone(),
+                source,
+            })?;
+        if let Some(address) = overrides
+            .iter()
+            .find_map(|(address, power)| (*power == 0).then_some(*address))
+        {
+            return Err(PoaError::ZeroPower { address });
+        }
+
+        Ok(Self::new(config.default_validator_power, overrides))
+    }
+
+    /// Returns the voting power of `validator`.
+    pub fn power(&self, validator: &Validator) -> u64 {
+        self.overrides
+            .get(&validator.address)
+            .copied()
+            .or(validator.voting_power)
+            .unwrap_or(self.default_power)
+    }
+
+    /// Returns the sidechain validator set registering `validators` with their voting power.
+    pub fn validator_set(
+        &self,
+        validators: &[Validator],
+    ) -> Result<Vec<ValidatorInfo>, OnemoneyError> {
+        validators
+            .iter()
+            .map(|validator| validator.validator_info(self.power(validator)))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Write;
+
+    use alloy_signer::k256::ecdsa::SigningKey;
+    use alloy_signer::k256::Scalar;
+    use alloy_signer::utils::public_key_to_address;
+
+    use super::*;
+
+    fn validator(index: u64, voting_power: Option<u64>) -> Validator {
+        let signing_key =
+            SigningKey::from_bytes(&Scalar::from(index + 1).to_bytes()).expect("valid key");
+        let consensus_public_key = *signing_key.verifying_key();
+        Validator {
+            consensus_public_key,
+            address: public_key_to_address(&consensus_public_key),
+            peer_id: format!("peer-{index}"),
+            archive: false,
+            voting_power,
+        }
+    }
+
+    fn config(validator_power_file: Option<std::path::PathBuf>) -> ValidatorPowerConfig {
+        ValidatorPowerConfig {
+            default_validator_power: 100,
+            validator_power_file,
+        }
+    }
+
+    #[test]
+    fn power_file_takes_precedence_over_the_epoch() {
+        let weighted = validator(0, Some(30));
+        let overridden = validator(1, Some(30));
+        let unweighted = validator(2, None);
+        let policy = PowerPolicy::new(100, HashMap::from([(overridden.address, 7)]));
+
+        assert_eq!(policy.power(&weighted), 30);
+        assert_eq!(policy.power(&overridden), 7);
+        assert_eq!(policy.power(&unweighted), 100);
+
+        let powers = policy
+            .validator_set(&[weighted, overridden, unweighted])
+            .expect("valid validators")
+            .into_iter()
+            .map(|info| info.power)
+            .collect::<Vec<_>>();
+        assert_eq!(powers, vec![30, 7, 100]);
+    }
+
+    #[test]
+    fn load_reads_the_power_file() {
+        let overridden = validator(0, None);
+        let mut file = tempfile::NamedTempFile::new().expect("temporary file");
+        write!(file, r#"{{ "{}": 250 }}"#, overridden.address).expect("written power file");
+
+        let policy =
+            PowerPolicy::load(&config(Some(file.path().to_owned()))).expect("valid power file");
+
+        assert_eq!(policy.power(&overridden), 250);
+        assert_eq!(policy.power(&validator(1, None)), 100);
+    }
+
+    #[test]
+    fn load_rejects_a_zero_power() {
+        let mut file = tempfile::NamedTempFile::new().expect("temporary file");
+        write!(file, r#"{{ "{}": 0 }}"#, Address::repeat_byte(1)).expect("written power file");
+
+        assert!(matches!(
+            PowerPolicy::load(&config(Some(file.path().to_owned()))),
+            Err(PoaError::ZeroPower { address }) if address == Address::repeat_byte(1)
+        ));
+    }
+}
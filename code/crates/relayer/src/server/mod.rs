@@ -0,0 +1,32 @@
+//! HTTP server exposing the metrics and health endpoints of the relayer.
+
+use core::net::SocketAddr;
+
+use axum::Router;
+use tokio::net::TcpListener;
+use tracing::{error, info};
+
+pub mod error;
+
+use error::Error as ServerError;
+
+use crate::health::Health;
+
+/// Binds `addr` and serves `/metrics`, `/healthz` and `/readyz` in the background.
+pub async fn spawn_server(addr: SocketAddr, health: Health) -> Result<(), ServerError> {
+    let listener = TcpListener::bind(addr)
+        .await
+        .map_err(|source| ServerError::Bind { addr, source })?;
+    let router = Router::new()
+        .merge(crate::metrics::router())
+        .merge(crate::health::router(health));
+
+    info!(%addr, "Serving metrics and health endpoints");
+    tokio::spawn(async move {
+        if let Err(err) = axum::serve(listener, router).await {
+            error!(%err, "HTTP server stopped");
+        }
+    });
+
+    Ok(())
+}
@@ -0,0 +1,109 @@
+//! Graceful shutdown of the relayer flows.
+//!
+//! Once a shutdown is requested the flows stop pulling new events and return after the
+//! transfer they are processing, if any, has finished its current step.
+
+use std::sync::Arc;
+
+use tokio::sync::watch;
+use tracing::info;
+
+/// Shared handle signalling the flows that the relayer is shutting down.
+#[derive(Clone)]
+pub struct Shutdown {
+    sender: Arc<watch::Sender<bool>>,
+}
+
+impl Default for Shutdown {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Shutdown {
+    pub fn new() -> Self {
+        let (sender, _) = watch::channel(false);
+        Self {
+            sender: Arc::new(sender),
+        }
+    }
+
+    /// Requests the flows to stop.
+    pub fn trigger(&self) {
+        self.sender.send_replace(true);
+    }
+
+    pub fn is_requested(&self) -> bool {
+        *self.sender.borrow()
+    }
+
+    /// Resolves once a shutdown is requested.
+    pub async fn requested(&self) {
+        let mut receiver = self.sender.subscribe();
+        // The sender lives as long as `self`, so the channel cannot be closed while waiting
+        let _ = receiver.wait_for(|requested| *requested).await;
+    }
+
+    /// Triggers the shutdown on the first SIGINT or SIGTERM received by the process.
+    pub fn listen_for_signals(&self) {
+        let shutdown = self.clone();
+        tokio::spawn(async move {
+            let signal = wait_for_signal().await;
+            info!(signal, "Received shutdown signal");
+            shutdown.trigger();
+        });
+    }
+}
+
+#[cfg(unix)]
+async fn wait_for_signal() -> &'static str {
+    use tokio::signal::unix::{signal, SignalKind};
+
+    let Ok(mut terminate) = signal(SignalKind::terminate()) else {
+        return wait_for_ctrl_c().await;
+    };
+    tokio::select! {
+        name = wait_for_ctrl_c() => name,
+        _ = terminate.recv() => "SIGTERM",
+    }
+}
+
+#[cfg(not(unix))]
+async fn wait_for_signal() -> &'static str {
+    wait_for_ctrl_c().await
+}
+
+async fn wait_for_ctrl_c() -> &'static str {
+    if tokio::signal::ctrl_c().await.is_err() {
+        // Without a signal handler the relayer can only be stopped by killing it
+        core::future::pending::<()>().await;
+    }
+    "SIGINT"
+}
+
+#[cfg(test)]
+mod tests {
+    use core::time::Duration;
+
+    use super::*;
+
+    #[tokio::test]
+    async fn waiters_are_released_on_trigger() {
+        let shutdown = Shutdown::new();
+        assert!(!shutdown.is_requested());
+
+        let waiter = tokio::spawn({
+            let shutdown = shutdown.clone();
+            async move { shutdown.requested().await }
+        });
+        tokio::time::sleep(Duration::from_millis(10)).await;
+        assert!(!waiter.is_finished());
+
+        shutdown.trigger();
+        waiter.await.expect("waiter released");
+        assert!(shutdown.is_requested());
+
+        // Waiting after the trigger resolves immediately
+        shutdown.requested().await;
+    }
+}
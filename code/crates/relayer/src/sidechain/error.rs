@@ -1,9 +1,53 @@
+use core::time::Duration;
+
+use alloy_primitives::B256;
+use alloy_sol_types::SolInterface;
+
 #[derive(Debug, thiserror::Error)]
 pub enum Error {
     #[error("Failed to process new validator set: {0}")]
     ProcessValidatorSet(#[from] alloy_contract::Error),
-    #[error("Failed to get transaction receipt: {0}")]
-    PendingTransactionReceipt(#[from] alloy_provider::PendingTransactionError),
+    #[error("Failed to submit transaction: {0}")]
+    Transaction(#[from] TransactionError),
     #[error("Contract reverted: {0:?}")]
     ValidatorManagerContractReverted(validator_manager::ValidatorManager::ValidatorManagerErrors),
 }
+
+#[derive(Debug, thiserror::Error)]
+pub enum TransactionError {
+    #[error("Failed to send transaction: {0}")]
+    Send(#[from] alloy_contract::Error),
+    #[error("Failed to query sidechain: {0}")]
+    Rpc(#[from] alloy_transport::RpcError<alloy_transport::TransportErrorKind>),
+    #[error("Transaction `{0}` reverted")]
+    Reverted(B256),
+    #[error("Nonce {nonce} was used by another transaction than {tx_hashes:?}")]
+    NonceUsed { nonce: u64, tx_hashes: Vec<B256> },
+    #[error("Transaction `{tx_hash}` still pending at the fee ceiling after {timeout:?}")]
+    Stuck { tx_hash: B256, timeout: Duration },
+    #[error("Transaction manager stopped")]
+    Stopped,
+}
+
+impl TransactionError {
+    /// Name of the error variant, used to label submission failures.
+    pub fn variant_name(&self) -> &'static str {
+        match self {
+            Self::Send(e) if e.as_revert_data().is_some() => "reverted",
+            Self::Send(_) => "send",
+            Self::Rpc(_) => "rpc",
+            Self::Reverted(_) => "reverted",
+            Self::NonceUsed { .. } => "nonce_used",
+            Self::Stuck { .. } => "stuck",
+            Self::Stopped => "stopped",
+        }
+    }
+
+    /// Decodes the revert reason of a rejected transaction into the contract errors `I`.
+    pub fn try_decode_revert<I: SolInterface>(self) -> Result<I, Self> {
+        match self {
+            Self::Send(e) => e.try_decode_into_interface_error::<I>().map_err(Self::Send),
+            other => Err(other),
+        }
+    }
+}
@@ -1,80 +1,103 @@
 pub mod error;
-use core::sync::atomic::Ordering;
-use std::collections::HashSet;
+pub mod tx_manager;
+
+use std::collections::{HashMap, HashSet};
 
 use alloy_provider::ProviderBuilder;
 use tracing::{debug, info};
 use validator_manager::ValidatorManager::{self, Secp256k1Key, ValidatorInfo};
 use validator_manager::CONTRACT_ADDRESS;
 
-use crate::config::{Config, RelayerNonce};
-use crate::sidechain::error::Error as SideChainError;
+use crate::config::Config;
+use crate::sidechain::error::{Error as SideChainError, TransactionError};
+use crate::sidechain::tx_manager::TxManager;
+use crate::signer::Role;
 
 pub async fn process_new_validator_set(
     config: &Config,
-    relayer_nonce: RelayerNonce,
+    tx_manager: &TxManager,
     new_validators: Vec<ValidatorInfo>,
 ) -> Result<(), SideChainError> {
-    let provider = ProviderBuilder::new()
-        .wallet(config.relayer_private_key.clone())
-        .connect_http(config.side_chain_http_url.clone());
-    let contract = ValidatorManager::new(CONTRACT_ADDRESS, provider.clone());
+    let provider = ProviderBuilder::new().connect_http(config.side_chain_http_url.clone());
+    let contract = ValidatorManager::new(CONTRACT_ADDRESS, provider);
 
     // Fetch current validator set from contract
     let old_validators = contract.getValidators().call().await?;
     debug!(?old_validators, "Old validator set");
 
-    let (add_validators, remove_validator_keys) =
-        compute_validator_diffs(&old_validators, &new_validators);
+    let ValidatorSetDiff {
+        add_validators,
+        remove_validator_keys,
+        update_powers,
+    } = compute_validator_diffs(&old_validators, &new_validators);
 
     debug!(
         ?add_validators,
         ?remove_validator_keys,
+        ?update_powers,
         "Validator set diff"
     );
 
-    if add_validators.is_empty() && remove_validator_keys.is_empty() {
+    if add_validators.is_empty() && remove_validator_keys.is_empty() && update_powers.is_empty() {
         info!("Validator set already up to date; skipping update");
         return Ok(());
     }
 
-    let add_validators_public_keys: Vec<_> = add_validators
-        .iter()
-        .map(|v| ValidatorManager::ValidatorRegistration {
-            publicKey: v.validatorKey.public_key_bytes().into(),
-            power: v.power,
-        })
-        .collect();
+    if !add_validators.is_empty() || !remove_validator_keys.is_empty() {
+        let add_validators_public_keys: Vec<_> = add_validators
+            .iter()
+            .map(|v| ValidatorManager::ValidatorRegistration {
+                publicKey: v.validatorKey.public_key_bytes().into(),
+                power: v.power,
+            })
+            .collect();
+
+        let remove_validator_addresses: Vec<_> = remove_validator_keys
+            .iter()
+            .map(|key| key.address())
+            .collect();
+
+        info!(
+            add_count = add_validators_public_keys.len(),
+            remove_count = remove_validator_addresses.len(),
+            "Updating validator set",
+        );
 
-    let remove_validator_addresses: Vec<_> = remove_validator_keys
-        .iter()
-        .map(|key| key.address())
-        .collect();
+        // Send transaction to update validator set
+        let tx_receipt = tx_manager
+            .send(
+                Role::ValidatorManager,
+                contract.updateValidatorSet(add_validators_public_keys, remove_validator_addresses),
+            )
+            .await
+            .map_err(decode_validator_manager_error)?;
 
-    info!(
-        add_count = add_validators_public_keys.len(),
-        remove_count = remove_validator_addresses.len(),
-        "Updating validator set",
-    );
+        info!(tx_hash=%tx_receipt.transaction_hash, "Validator set updated successfully");
 
-    // Send transaction to update validator set
-    let tx_receipt = contract
-        .updateValidatorSet(add_validators_public_keys, remove_validator_addresses)
-        .nonce(relayer_nonce.fetch_add(1, Ordering::SeqCst))
-        .send()
-        .await
-        .map(Ok)
-        .or_else(|e| {
-            e.try_decode_into_interface_error::<ValidatorManager::ValidatorManagerErrors>()
-                .map(Err)
-        })?
-        .map_err(SideChainError::ValidatorManagerContractReverted)?
-        .get_receipt()
-        .await?;
-
-    info!(tx_hash=%tx_receipt.transaction_hash, "Validator set updated successfully");
-
-    debug!(?tx_receipt, "Tx receipt for validator set update");
+        debug!(?tx_receipt, "Tx receipt for validator set update");
+    }
+
+    // Validators whose key is kept only have their power updated, one `updatePower` transaction
+    // each since `updateValidatorSet` cannot change powers. A failure part way leaves the set
+    // partially applied on purpose: the epoch is not stored as applied, and the next attempt
+    // diffs against the contract again, so it only sends the updates still missing
+    for validator in update_powers {
+        let validator_address = validator.validatorKey.address();
+        let tx_receipt = tx_manager
+            .send(
+                Role::ValidatorManager,
+                contract.updatePower(validator_address, validator.power),
+            )
+            .await
+            .map_err(decode_validator_manager_error)?;
+
+        info!(
+            tx_hash=%tx_receipt.transaction_hash,
+            %validator_address,
+            power = validator.power,
+            "Validator power updated successfully"
+        );
+    }
 
     // Query new validator set
     let new_validators = contract.getValidators().call().await?;
@@ -83,32 +106,62 @@ pub async fn process_new_validator_set(
     Ok(())
 }
 
+fn decode_validator_manager_error(e: TransactionError) -> SideChainError {
+    e.try_decode_revert::<ValidatorManager::ValidatorManagerErrors>()
+        .map_or_else(
+            SideChainError::from,
+            SideChainError::ValidatorManagerContractReverted,
+        )
+}
+
+/// Changes turning a validator set into another one.
+#[derive(Debug, Default, PartialEq, Eq)]
+struct ValidatorSetDiff {
+    add_validators: Vec<ValidatorInfo>,
+    remove_validator_keys: Vec<Secp256k1Key>,
+    /// Validators kept in the set with a different power.
+    update_powers: Vec<ValidatorInfo>,
+}
+
 fn compute_validator_diffs(
     old_validators: &[ValidatorInfo],
     new_validators: &[ValidatorInfo],
-) -> (Vec<ValidatorInfo>, Vec<Secp256k1Key>) {
-    let old_validators = old_validators
+) -> ValidatorSetDiff {
+    let old_powers = old_validators
         .iter()
-        .cloned()
-        .collect::<HashSet<ValidatorInfo>>();
-    let new_validators = new_validators
+        .map(|v| (&v.validatorKey, v.power))
+        .collect::<HashMap<_, _>>();
+    let new_keys = new_validators
         .iter()
-        .cloned()
-        .collect::<HashSet<ValidatorInfo>>();
-
-    let mut add_validators: Vec<_> = new_validators
-        .difference(&old_validators)
-        .cloned()
-        .collect();
+        .map(|v| &v.validatorKey)
+        .collect::<HashSet<_>>();
+
+    let mut add_validators = Vec::new();
+    let mut update_powers = Vec::new();
+    for validator in new_validators {
+        match old_powers.get(&validator.validatorKey) {
+            None => add_validators.push(validator.clone()),
+            Some(&power) if power != validator.power => update_powers.push(validator.clone()),
+            Some(_) => {}
+        }
+    }
     add_validators.sort();
+    add_validators.dedup();
+    update_powers.sort();
+    update_powers.dedup();
 
-    let mut remove_validator_keys: Vec<_> = old_validators
-        .difference(&new_validators)
-        .map(|v| v.validatorKey.clone())
+    let mut remove_validator_keys: Vec<_> = old_powers
+        .into_keys()
+        .filter(|key| !new_keys.contains(key))
+        .cloned()
         .collect();
     remove_validator_keys.sort();
 
-    (add_validators, remove_validator_keys)
+    ValidatorSetDiff {
+        add_validators,
+        remove_validator_keys,
+        update_powers,
+    }
 }
 
 #[cfg(test)]
@@ -136,12 +189,11 @@ mod tests {
     #[test]
     fn compute_validator_diffs_no_changes() {
         let validator = make_validator_info(0);
-        let (add, remove) = compute_validator_diffs(
+        let diff = compute_validator_diffs(
             core::slice::from_ref(&validator),
             core::slice::from_ref(&validator),
         );
-        assert!(add.is_empty());
-        assert!(remove.is_empty());
+        assert_eq!(diff, ValidatorSetDiff::default());
     }
 
     #[test]
@@ -152,9 +204,28 @@ mod tests {
 
         let old = [v1.clone(), v2.clone()];
         let new = [v2, v3.clone()];
-        let (add, remove) = compute_validator_diffs(&old, &new);
+        let diff = compute_validator_diffs(&old, &new);
+
+        assert_eq!(diff.add_validators, vec![v3]);
+        assert_eq!(diff.remove_validator_keys, vec![v1.validatorKey]);
+        assert!(diff.update_powers.is_empty());
+    }
 
-        assert_eq!(add, vec![v3]);
-        assert_eq!(remove, vec![v1.validatorKey]);
+    #[test]
+    fn compute_validator_diffs_power_change() {
+        let v1 = make_validator_info(1);
+        let v2 = make_validator_info(2);
+        let reweighted = ValidatorInfo {
+            power: 42,
+            ..v2.clone()
+        };
+
+        let old = [v1.clone(), v2];
+        let new = [v1, reweighted.clone()];
+        let diff = compute_validator_diffs(&old, &new);
+
+        assert!(diff.add_validators.is_empty());
+        assert!(diff.remove_validator_keys.is_empty());
+        assert_eq!(diff.update_powers, vec![reweighted]);
     }
 }
@@ -0,0 +1,526 @@
+//! Transaction manager owning the relayer account on the sidechain.
+//!
+//! All flows submit their contract calls through a [`TxManager`] handle, for the [`Role`]
+//! the call belongs to. A single task per account assigns nonces in submission order, so a
+//! failed submission in one flow can no longer leave a gap behind nonces already handed out
+//! to another flow. Roles sharing a key share the task of their account.
+//!
+//! Every submitted transaction is watched until one of its versions is mined. A transaction
+//! still pending after the configured timeout is re-broadcast with the same nonce and bumped
+//! EIP-1559 fees, up to the configured fee ceiling. A transaction which reverted, whose nonce
+//! was used by another transaction, or which stays pending at the fee ceiling for too long
+//! fails its submission.
+//!
+//! In dry-run mode the transactions are simulated by the [`DryRun`] handle instead.
+
+use core::time::Duration;
+
+use alloy_contract::{CallBuilder, CallDecoder};
+use alloy_primitives::{Address, B256};
+use alloy_provider::{Provider, ProviderBuilder};
+use alloy_rpc_types_eth::{TransactionReceipt, TransactionRequest};
+use alloy_transport::{RpcError, TransportErrorKind};
+use tokio::sync::{mpsc, oneshot};
+use tokio::time::Instant;
+use tracing::{debug, info, warn};
+use url::Url;
+
+use crate::config::SidechainTxConfig;
+use crate::dry_run::DryRun;
+use crate::metrics::{metrics, Chain};
+use crate::sidechain::error::TransactionError;
+use crate::signer::{RelayerSigner, Role, Signers};
+
+const RECEIPT_POLL_INTERVAL: Duration = Duration::from_secs(1);
+
+struct Submission {
+    request: TransactionRequest,
+    reply: oneshot::Sender<Result<TransactionReceipt, TransactionError>>,
+}
+
+/// EIP-1559 fees of a transaction.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Fees {
+    max_fee_per_gas: u128,
+    max_priority_fee_per_gas: u128,
+}
+
+impl Fees {
+    /// Caps the fees to `max_fee_per_gas`.
+    fn capped(self, max_fee_per_gas: u128) -> Self {
+        let max_fee_per_gas = self.max_fee_per_gas.min(max_fee_per_gas);
+        Self {
+            max_fee_per_gas,
+            max_priority_fee_per_gas: self.max_priority_fee_per_gas.min(max_fee_per_gas),
+        }
+    }
+
+    /// Fees of a replacement for a transaction paying `self`.
+    ///
+    /// Both fees are increased by `settings.tx_fee_bump_percent` and never go below the
+    /// `current` network estimate. Returns `None` once the fee ceiling is reached, i.e. once
+    /// the capped fees no longer pay the full bump, which the node would reject as an
+    /// underpriced replacement.
+    fn bumped(self, current: Self, settings: &SidechainTxConfig) -> Option<Self> {
+        let bump = |fee: u128| {
+            fee.saturating_mul(100 + u128::from(settings.tx_fee_bump_percent))
+                .div_ceil(100)
+                // A zero fee cannot be bumped by a percentage
+                .max(fee + 1)
+        };
+        let min_max_fee_per_gas = bump(self.max_fee_per_gas);
+        let min_max_priority_fee_per_gas = bump(self.max_priority_fee_per_gas);
+        let bumped = Self {
+            max_fee_per_gas: min_max_fee_per_gas.max(current.max_fee_per_gas),
+            max_priority_fee_per_gas: min_max_priority_fee_per_gas
+                .max(current.max_priority_fee_per_gas),
+        }
+        .capped(settings.tx_max_fee_per_gas);
+
+        (bumped.max_fee_per_gas >= min_max_fee_per_gas
+            && bumped.max_priority_fee_per_gas >= min_max_priority_fee_per_gas)
+            .then_some(bumped)
+    }
+}
+
+/// Handle used to submit transactions signed by the relayer accounts.
+#[derive(Clone)]
+pub struct TxManager {
+    interop: Account,
+    validator_manager: Account,
+    tx_mapping: Account,
+    dry_run: Option<DryRun>,
+}
+
+/// Handle to the task owning a sidechain account.
+#[derive(Clone)]
+struct Account {
+    address: Address,
+    submissions: mpsc::UnboundedSender<Submission>,
+}
+
+impl Account {
+    /// Spawns the task owning the account of `signer`, used by `roles`, on the sidechain at
+    /// `url`.
+    async fn spawn(
+        url: Url,
+        signer: &RelayerSigner,
+        roles: Vec<Role>,
+        settings: SidechainTxConfig,
+    ) -> Result<Self, RpcError<TransportErrorKind>> {
+        let address = signer.address();
+        let provider = ProviderBuilder::new()
+            .wallet(signer.wallet())
+            .connect_http(url);
+        let nonce = provider.get_transaction_count(address).pending().await?;
+
+        let (submissions, receiver) = mpsc::unbounded_channel();
+        tokio::spawn(run(provider, address, roles, nonce, settings, receiver));
+
+        Ok(Self {
+            address,
+            submissions,
+        })
+    }
+
+    /// Returns the account of `role` from the `spawned` accounts, spawning it if needed.
+    async fn get_or_spawn(
+        spawned: &mut Vec<Self>,
+        url: &Url,
+        signers: &Signers,
+        role: Role,
+        settings: &SidechainTxConfig,
+    ) -> Result<Self, RpcError<TransportErrorKind>> {
+        let signer = signers.get(role);
+        if let Some(account) = spawned
+            .iter()
+            .find(|account| account.address == signer.address())
+        {
+            return Ok(account.clone());
+        }
+
+        let roles = [Role::Interop, Role::ValidatorManager, Role::TxMapping]
+            .into_iter()
+            .filter(|role| signers.get(*role).address() == signer.address())
+            .collect();
+        let account = Self::spawn(url.clone(), signer, roles, settings.clone()).await?;
+        info!(%role, address = %account.address, "Spawned sidechain account");
+        spawned.push(account.clone());
+        Ok(account)
+    }
+}
+
+impl TxManager {
+    /// Spawns one task per distinct account of `signers` on the sidechain at `url`.
+    ///
+    /// Transactions are simulated instead of submitted if `dry_run` is set.
+    pub async fn spawn(
+        url: Url,
+        signers: &Signers,
+        settings: SidechainTxConfig,
+        dry_run: Option<DryRun>,
+    ) -> Result<Self, RpcError<TransportErrorKind>> {
+        let mut accounts = Vec::new();
+        let interop =
+            Account::get_or_spawn(&mut accounts, &url, signers, Role::Interop, &settings).await?;
+        let validator_manager = Account::get_or_spawn(
+            &mut accounts,
+            &url,
+            signers,
+            Role::ValidatorManager,
+            &settings,
+        )
+        .await?;
+        let tx_mapping =
+            Account::get_or_spawn(&mut accounts, &url, signers, Role::TxMapping, &settings).await?;
+
+        Ok(Self {
+            interop,
+            validator_manager,
+            tx_mapping,
+            dry_run,
+        })
+    }
+
+    const fn account(&self, role: Role) -> &Account {
+        match role {
+            Role::Interop => &self.interop,
+            Role::ValidatorManager => &self.validator_manager,
+            Role::TxMapping => &self.tx_mapping,
+        }
+    }
+
+    /// Address of the account of `role`.
+    pub fn address(&self, role: Role) -> Address {
+        self.account(role).address
+    }
+
+    /// Dry-run handle simulating the relayer writes, if enabled.
+    pub const fn dry_run(&self) -> Option<&DryRun> {
+        self.dry_run.as_ref()
+    }
+
+    /// Submits a contract call from the account of `role` and waits for its receipt.
+    pub async fn send<P, D>(
+        &self,
+        role: Role,
+        call: CallBuilder<P, D>,
+    ) -> Result<TransactionReceipt, TransactionError>
+    where
+        P: Provider,
+        D: CallDecoder,
+    {
+        self.send_transaction(role, call.into_transaction_request())
+            .await
+    }
+
+    /// Submits a transaction from the account of `role` and waits for its receipt.
+    ///
+    /// The `from`, `nonce` and fee fields of `request` are overwritten by the manager.
+    pub async fn send_transaction(
+        &self,
+        role: Role,
+        request: TransactionRequest,
+    ) -> Result<TransactionReceipt, TransactionError> {
+        if let Some(dry_run) = &self.dry_run {
+            return dry_run
+                .simulate_transaction(role, self.address(role), request)
+                .await
+                .inspect_err(|e| metrics().submission_failed(e.variant_name()));
+        }
+
+        let (reply, receipt) = oneshot::channel();
+        self.account(role)
+            .submissions
+            .send(Submission { request, reply })
+            .map_err(|_| TransactionError::Stopped)?;
+        receipt
+            .await
+            .map_err(|_| TransactionError::Stopped)
+            .and_then(|receipt| receipt)
+            .and_then(|receipt| {
+                if receipt.status() {
+                    Ok(receipt)
+                } else {
+                    Err(TransactionError::Reverted(receipt.transaction_hash))
+                }
+            })
+            .inspect_err(|e| metrics().submission_failed(e.variant_name()))
+    }
+}
+
+async fn run<P: Provider + Clone + 'static>(
+    provider: P,
+    address: Address,
+    roles: Vec<Role>,
+    mut nonce: u64,
+    settings: SidechainTxConfig,
+    mut submissions: mpsc::UnboundedReceiver<Submission>,
+) {
+    let set_nonce_metric = |nonce| {
+        for role in &roles {
+            metrics().set_relayer_nonce(Chain::Sidechain, *role, nonce);
+        }
+    };
+    set_nonce_metric(nonce);
+
+    while let Some(Submission { request, reply }) = submissions.recv().await {
+        // Transactions sent from the account by someone else move the pending nonce past the
+        // local one. A pending nonce behind the local one is not trusted, the node may not see
+        // the transactions submitted last yet, and a dropped transaction is re-broadcast by
+        // its watcher.
+        match provider.get_transaction_count(address).pending().await {
+            Ok(pending_nonce) if pending_nonce > nonce => {
+                warn!(
+                    local_nonce = nonce,
+                    pending_nonce, "Relayer nonce behind the sidechain, resyncing"
+                );
+                nonce = pending_nonce;
+                set_nonce_metric(nonce);
+            }
+            Ok(_) => {}
+            Err(e) => {
+                warn!(error = %e, "Failed to query pending relayer nonce, keeping local nonce");
+            }
+        }
+
+        let fees = match estimate_fees(&provider).await {
+            Ok(fees) => fees.capped(settings.tx_max_fee_per_gas),
+            Err(e) => {
+                let _ = reply.send(Err(e.into()));
+                continue;
+            }
+        };
+        let mut request = with_fees(request.from(address).nonce(nonce), fees);
+
+        let mut sent = provider.send_transaction(request.clone()).await;
+        // The node rejected the nonce itself, resync with it in either direction and retry once
+        if let Err(e) = &sent {
+            if is_nonce_error(e) {
+                match provider.get_transaction_count(address).pending().await {
+                    Ok(pending_nonce) => {
+                        warn!(
+                            local_nonce = nonce,
+                            pending_nonce,
+                            error = %e,
+                            "Sidechain rejected the relayer nonce, resyncing"
+                        );
+                        nonce = pending_nonce;
+                        set_nonce_metric(nonce);
+                        request = request.nonce(nonce);
+                        sent = provider.send_transaction(request.clone()).await;
+                    }
+                    Err(e) => {
+                        warn!(error = %e, "Failed to query pending relayer nonce, keeping local nonce");
+                    }
+                }
+            }
+        }
+
+        match sent {
+            Ok(pending_tx) => {
+                let tx_hash = *pending_tx.tx_hash();
+                debug!(%tx_hash, nonce, "Submitted sidechain transaction");
+                nonce += 1;
+                set_nonce_metric(nonce);
+                tokio::spawn({
+                    let provider = provider.clone();
+                    let settings = settings.clone();
+                    async move {
+                        let receipt =
+                            watch(provider, address, request, tx_hash, fees, settings).await;
+                        let _ = reply.send(receipt);
+                    }
+                });
+            }
+            Err(e) => {
+                // The nonce was not consumed and is reused by the next submission
+                let _ = reply.send(Err(alloy_contract::Error::from(e).into()));
+            }
+        }
+    }
+
+    debug!("Transaction manager stopped");
+}
+
+/// Waits until one of the versions of the transaction `request` sent from `address` is mined.
+///
+/// Each time the transaction stays pending for `tx_stuck_timeout` it is replaced by a copy
+/// paying bumped fees. Earlier versions stay watched since any of them may still be mined.
+/// The wait fails once the nonce of the transaction is used by a transaction which is not
+/// one of its versions, or once it stayed pending for `tx_fee_ceiling_timeout` at the fee
+/// ceiling or after a failed replacement.
+async fn watch<P: Provider>(
+    provider: P,
+    address: Address,
+    request: TransactionRequest,
+    tx_hash: B256,
+    mut fees: Fees,
+    settings: SidechainTxConfig,
+) -> Result<TransactionReceipt, TransactionError> {
+    let nonce = request.nonce.unwrap_or_default();
+    let mut tx_hashes = vec![tx_hash];
+    let mut deadline = Instant::now() + settings.tx_stuck_timeout;
+    let mut ceiling_deadline = None;
+
+    loop {
+        // Queried before the receipts, so that a version mined meanwhile has its receipt
+        let nonce_used = match provider.get_transaction_count(address).latest().await {
+            Ok(latest_nonce) => latest_nonce > nonce,
+            Err(e) => {
+                warn!(%address, error = %e, "Failed to query latest relayer nonce");
+                false
+            }
+        };
+        for tx_hash in &tx_hashes {
+            match provider.get_transaction_receipt(*tx_hash).await {
+                Ok(Some(receipt)) => return Ok(receipt),
+                Ok(None) => {}
+                Err(e) => warn!(%tx_hash, error = %e, "Failed to query transaction receipt"),
+            }
+        }
+        if nonce_used {
+            return Err(TransactionError::NonceUsed { nonce, tx_hashes });
+        }
+
+        if Instant::now() < deadline {
+            tokio::time::sleep(RECEIPT_POLL_INTERVAL).await;
+            continue;
+        }
+        deadline = Instant::now() + settings.tx_stuck_timeout;
+
+        let stuck_tx_hash = tx_hashes[tx_hashes.len() - 1];
+        if ceiling_deadline.is_some_and(|ceiling_deadline| Instant::now() >= ceiling_deadline) {
+            return Err(TransactionError::Stuck {
+                tx_hash: stuck_tx_hash,
+                timeout: settings.tx_fee_ceiling_timeout,
+            });
+        }
+        let current = match estimate_fees(&provider).await {
+            Ok(current) => current,
+            Err(e) => {
+                warn!(tx_hash = %stuck_tx_hash, error = %e, "Failed to estimate replacement fees");
+                fees
+            }
+        };
+        let Some(bumped) = fees.bumped(current, &settings) else {
+            ceiling_deadline
+                .get_or_insert_with(|| Instant::now() + settings.tx_fee_ceiling_timeout);
+            warn!(
+                tx_hash = %stuck_tx_hash,
+                nonce = ?request.nonce,
+                max_fee_per_gas = fees.max_fee_per_gas,
+                "Transaction stuck at the fee ceiling, waiting for it to be mined"
+            );
+            continue;
+        };
+
+        match provider
+            .send_transaction(with_fees(request.clone(), bumped))
+            .await
+        {
+            Ok(pending_tx) => {
+                let replacement_hash = *pending_tx.tx_hash();
+                info!(
+                    tx_hash = %stuck_tx_hash,
+                    %replacement_hash,
+                    nonce = ?request.nonce,
+                    max_fee_per_gas = bumped.max_fee_per_gas,
+                    max_priority_fee_per_gas = bumped.max_priority_fee_per_gas,
+                    "Replaced stuck sidechain transaction"
+                );
+                tx_hashes.push(replacement_hash);
+                fees = bumped;
+                ceiling_deadline = None;
+            }
+            Err(e) => {
+                // The node rejects the replacement if a previous version was mined meanwhile,
+                // which is picked up by the next receipt poll. Replacements which keep failing
+                // are bounded by the fee ceiling timeout.
+                ceiling_deadline
+                    .get_or_insert_with(|| Instant::now() + settings.tx_fee_ceiling_timeout);
+                warn!(tx_hash = %stuck_tx_hash, error = %e, "Failed to replace stuck transaction");
+            }
+        }
+    }
+}
+
+/// Whether the node rejected a transaction because of its nonce.
+fn is_nonce_error(error: &RpcError<TransportErrorKind>) -> bool {
+    error.as_error_resp().is_some_and(|response| {
+        let message = response.message.to_lowercase();
+        message.contains("nonce too low") || message.contains("nonce too high")
+    })
+}
+
+async fn estimate_fees<P: Provider>(provider: &P) -> Result<Fees, RpcError<TransportErrorKind>> {
+    let estimation = provider.estimate_eip1559_fees().await?;
+    Ok(Fees {
+        max_fee_per_gas: estimation.max_fee_per_gas,
+        max_priority_fee_per_gas: estimation.max_priority_fee_per_gas,
+    })
+}
+
+fn with_fees(request: TransactionRequest, fees: Fees) -> TransactionRequest {
+    request
+        .max_fee_per_gas(fees.max_fee_per_gas)
+        .max_priority_fee_per_gas(fees.max_priority_fee_per_gas)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn settings() -> SidechainTxConfig {
+        SidechainTxConfig {
+            tx_stuck_timeout: Duration::from_secs(30),
+            tx_fee_bump_percent: 20,
+            tx_max_fee_per_gas: 1_000,
+            tx_fee_ceiling_timeout: Duration::from_secs(600),
+        }
+    }
+
+    const fn fees(max_fee_per_gas: u128, max_priority_fee_per_gas: u128) -> Fees {
+        Fees {
+            max_fee_per_gas,
+            max_priority_fee_per_gas,
+        }
+    }
+
+    #[test]
+    fn fees_are_bumped_by_percentage() {
+        assert_eq!(
+            fees(100, 10).bumped(fees(50, 5), &settings()),
+            Some(fees(120, 12))
+        );
+    }
+
+    #[test]
+    fn bumped_fees_follow_network_estimate() {
+        assert_eq!(
+            fees(100, 10).bumped(fees(300, 20), &settings()),
+            Some(fees(300, 20))
+        );
+    }
+
+    #[test]
+    fn bumped_fees_are_capped() {
+        assert_eq!(
+            fees(100, 10).bumped(fees(2_000, 20), &settings()),
+            Some(fees(1_000, 20))
+        );
+        assert_eq!(fees(1_000, 10).bumped(fees(0, 0), &settings()), None);
+    }
+
+    #[test]
+    fn bump_cut_by_the_cap_reaches_the_ceiling() {
+        // The cap sits within 10% of the fees, a replacement would be underpriced
+        assert_eq!(fees(950, 10).bumped(fees(0, 0), &settings()), None);
+        assert_eq!(fees(900, 900).bumped(fees(0, 0), &settings()), None);
+    }
+
+    #[test]
+    fn zero_fees_are_bumped() {
+        assert_eq!(fees(0, 0).bumped(fees(0, 0), &settings()), Some(fees(1, 1)));
+    }
+}
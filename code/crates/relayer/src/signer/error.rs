@@ -0,0 +1,43 @@
+use std::path::PathBuf;
+
+use thiserror::Error;
+
+#[derive(Debug, Error)]
+pub enum Error {
+    #[error(
+        "No relayer key configured, set a private key, a key file, a keystore or a remote signer"
+    )]
+    MissingKey,
+    #[error("Only one of the relayer private key, key file, keystore and remote address may be configured")]
+    ConflictingKeys,
+    #[error("Failed to read relayer key file `{path}`: {source}")]
+    ReadKeyFile {
+        path: PathBuf,
+        source: std::io::Error,
+    },
+    #[error("Relayer key file `{path}` does not hold a hex encoded private key")]
+    InvalidKeyFile { path: PathBuf },
+    #[error("Failed to read relayer keystore password: {0}")]
+    Password(std::io::Error),
+    #[error("Failed to decrypt relayer keystore `{path}`: {source}")]
+    Keystore {
+        path: PathBuf,
+        source: eth_keystore::KeystoreError,
+    },
+    #[error("Relayer keystore `{path}` does not hold a valid private key")]
+    InvalidKeystore { path: PathBuf },
+    #[error("The relayer address must be configured to use a remote signer")]
+    MissingRemoteAddress,
+    #[error(
+        "Only one of the private key, key file and address of the `{role}` role may be configured"
+    )]
+    ConflictingRoleKeys { role: crate::signer::Role },
+    #[error("The address of the `{role}` role requires a remote signer")]
+    MissingRemoteSigner { role: crate::signer::Role },
+    #[error("Remote signer request failed: {0}")]
+    RemoteRequest(reqwest::Error),
+    #[error("Remote signer returned an invalid signature: {0}")]
+    RemoteSignature(alloy_primitives::SignatureError),
+    #[error(transparent)]
+    Onemoney(#[from] onemoney_protocol::Error),
+}
@@ -0,0 +1,481 @@
+//! Key material of the relayer account.
+//!
+//! The relayer key is loaded once on startup from a raw private key, a key file or an
+//! Ethereum JSON keystore. Intermediate copies of the key are wiped from memory as soon as
+//! the signer is built and the key is never formatted with `Display` or `Debug`.
+//!
+//! Alternatively the key stays on a [remote signer](remote) which signs both the sidechain
+//! transactions and the 1Money payloads.
+//!
+//! The sidechain transactions of each [`Role`] may be signed by a distinct account, which
+//! defaults to the relayer account. Each account is either local or remote on its own, e.g. a
+//! local relayer key may be combined with role accounts held by the remote signer.
+
+use core::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use alloy_primitives::{Address, Signature as EcdsaSignature};
+use alloy_provider::network::EthereumWallet;
+use alloy_rlp::Encodable;
+use alloy_signer_local::PrivateKeySigner;
+use onemoney_protocol::client::http::Client;
+use onemoney_protocol::responses::TransactionResponse;
+use onemoney_protocol::{PaymentPayload, Signature, TokenBridgeAndMintPayload};
+use serde::Serialize;
+use zeroize::{Zeroize, Zeroizing};
+
+use crate::config::SignerConfig;
+
+pub mod error;
+pub mod remote;
+
+use error::Error as SignerError;
+use remote::RemoteSigner;
+
+const PRIVATE_KEY_LEN: usize = 32;
+
+/// Length of a `0x` prefixed hex encoded private key.
+const HEX_PRIVATE_KEY_LEN: usize = 2 + 2 * PRIVATE_KEY_LEN;
+
+const BRIDGE_AND_MINT_PATH: &str = "v1/tokens/bridge_and_mint";
+const PAYMENT_PATH: &str = "v1/transactions/payment";
+
+/// Role of a sidechain account used by the relayer.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Role {
+    /// Relays transfers and checkpoints through the interop contract. Always the relayer
+    /// account, which also signs the 1Money transactions.
+    Interop,
+    /// Updates the validator set of the validator manager contract.
+    ValidatorManager,
+    /// Registers and links transaction hashes in the tx hash mapping contract.
+    TxMapping,
+}
+
+impl Role {
+    pub const fn as_str(self) -> &'static str {
+        match self {
+            Self::Interop => "interop",
+            Self::ValidatorManager => "validator_manager",
+            Self::TxMapping => "tx_mapping",
+        }
+    }
+}
+
+impl fmt::Display for Role {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// Signers of every role.
+#[derive(Debug, Clone)]
+pub struct Signers {
+    /// Signer of the relayer account, used for the [`Role::Interop`] role and on 1Money.
+    pub relayer: RelayerSigner,
+    pub validator_manager: RelayerSigner,
+    pub tx_mapping: RelayerSigner,
+}
+
+impl Signers {
+    /// Loads the relayer signer and the signers of the roles configured with their own key.
+    pub fn load(config: &SignerConfig) -> Result<Self, SignerError> {
+        let relayer = RelayerSigner::load(config)?;
+        let validator_manager = load_role_signer(
+            config,
+            Role::ValidatorManager,
+            &relayer,
+            &config.validator_manager_private_key,
+            &config.validator_manager_key_file,
+            config.validator_manager_address,
+        )?;
+        let tx_mapping = load_role_signer(
+            config,
+            Role::TxMapping,
+            &relayer,
+            &config.tx_mapping_private_key,
+            &config.tx_mapping_key_file,
+            config.tx_mapping_address,
+        )?;
+
+        Ok(Self {
+            relayer,
+            validator_manager,
+            tx_mapping,
+        })
+    }
+
+    pub const fn get(&self, role: Role) -> &RelayerSigner {
+        match role {
+            Role::Interop => &self.relayer,
+            Role::ValidatorManager => &self.validator_manager,
+            Role::TxMapping => &self.tx_mapping,
+        }
+    }
+}
+
+/// Loads the signer of `role` from its private key, key file or address on the remote signer,
+/// falling back to the `relayer` signer if none is set.
+fn load_role_signer(
+    config: &SignerConfig,
+    role: Role,
+    relayer: &RelayerSigner,
+    private_key: &Option<PrivateKeySigner>,
+    key_file: &Option<PathBuf>,
+    address: Option<Address>,
+) -> Result<RelayerSigner, SignerError> {
+    match (private_key, key_file, address) {
+        (None, None, None) => Ok(relayer.clone()),
+        (Some(signer), None, None) => Ok(RelayerSigner::Local(signer.clone())),
+        (None, Some(path), None) => Ok(RelayerSigner::Local(read_key_file(path)?)),
+        (None, None, Some(address)) => {
+            let url = config
+                .remote_signer_url
+                .as_ref()
+                .ok_or(SignerError::MissingRemoteSigner { role })?;
+            Ok(RelayerSigner::Remote(RemoteSigner::new(
+                url.clone(),
+                address,
+            )))
+        }
+        _ => Err(SignerError::ConflictingRoleKeys { role }),
+    }
+}
+
+/// Signer of the relayer account, which is the same on both chains.
+#[derive(Debug, Clone)]
+pub enum RelayerSigner {
+    /// Key held in the relayer process.
+    Local(PrivateKeySigner),
+    /// Key held by a remote signer.
+    Remote(RemoteSigner),
+}
+
+/// 1Money transaction submitted with its signature.
+#[derive(Serialize)]
+struct SignedPayload<P> {
+    #[serde(flatten)]
+    payload: P,
+    signature: Signature,
+}
+
+impl RelayerSigner {
+    /// Loads the signer from the single key source set in `config`.
+    ///
+    /// The relayer key is held by the remote signer only if `relayer_address` is set, so a
+    /// local relayer key may be combined with roles signed remotely.
+    ///
+    /// The keystore password is read from `relayer_keystore_password_file`, or prompted for
+    /// on the terminal if no password file is configured.
+    pub fn load(config: &SignerConfig) -> Result<Self, SignerError> {
+        let signer =
+            match (
+                &config.relayer_private_key,
+                &config.relayer_key_file,
+                &config.relayer_keystore,
+                config.relayer_address,
+            ) {
+                (Some(signer), None, None, None) => signer.clone(),
+                (None, Some(path), None, None) => read_key_file(path)?,
+                (None, None, Some(path), None) => {
+                    let password = match &config.relayer_keystore_password_file {
+                        Some(password_file) => read_password_file(password_file)?,
+                        None => Zeroizing::new(
+                            rpassword::prompt_password("Relayer keystore password: ")
+                                .map_err(SignerError::Password)?,
+                        ),
+                    };
+                    decrypt_keystore(path, &password)?
+                }
+                (None, None, None, Some(address)) => {
+                    let url = config.remote_signer_url.as_ref().ok_or(
+                        SignerError::MissingRemoteSigner {
+                            role: Role::Interop,
+                        },
+                    )?;
+                    return Ok(Self::Remote(RemoteSigner::new(url.clone(), address)));
+                }
+                (None, None, None, None) if config.remote_signer_url.is_some() => {
+                    return Err(SignerError::MissingRemoteAddress)
+                }
+                (None, None, None, None) => return Err(SignerError::MissingKey),
+                _ => return Err(SignerError::ConflictingKeys),
+            };
+
+        Ok(Self::Local(signer))
+    }
+
+    pub fn address(&self) -> Address {
+        match self {
+            Self::Local(signer) => signer.address(),
+            Self::Remote(signer) => signer.address(),
+        }
+    }
+
+    /// Wallet signing the sidechain transactions.
+    pub fn wallet(&self) -> EthereumWallet {
+        match self {
+            Self::Local(signer) => EthereumWallet::from(signer.clone()),
+            Self::Remote(signer) => EthereumWallet::from(signer.clone()),
+        }
+    }
+
+    /// Submits a `TokenBridgeAndMint` transaction to 1Money.
+    pub async fn bridge_and_mint(
+        &self,
+        client: &Client,
+        payload: TokenBridgeAndMintPayload,
+    ) -> Result<TransactionResponse, SignerError> {
+        match self {
+            Self::Local(signer) => {
+                let key = OnemoneyKey::new(signer);
+                Ok(client.bridge_and_mint(payload, key.as_str()).await?)
+            }
+            Self::Remote(signer) => submit(client, signer, BRIDGE_AND_MINT_PATH, payload).await,
+        }
+    }
+
+    /// Submits a `Payment` transaction to 1Money.
+    pub async fn send_payment(
+        &self,
+        client: &Client,
+        payload: PaymentPayload,
+    ) -> Result<TransactionResponse, SignerError> {
+        match self {
+            Self::Local(signer) => {
+                let key = OnemoneyKey::new(signer);
+                Ok(client.send_payment(payload, key.as_str()).await?)
+            }
+            Self::Remote(signer) => submit(client, signer, PAYMENT_PATH, payload).await,
+        }
+    }
+}
+
+/// Signs `payload` with the remote signer and submits it to the 1Money endpoint at `path`.
+///
+/// 1Money transactions are signed over the keccak256 hash of their RLP encoding.
+async fn submit<P>(
+    client: &Client,
+    signer: &RemoteSigner,
+    path: &str,
+    payload: P,
+) -> Result<TransactionResponse, SignerError>
+where
+    P: Encodable + Serialize,
+{
+    let mut encoded = Vec::new();
+    payload.encode(&mut encoded);
+    let signature = onemoney_signature(signer.sign(&encoded).await?);
+
+    Ok(client
+        .post(path, &SignedPayload { payload, signature })
+        .await?)
+}
+
+fn onemoney_signature(signature: EcdsaSignature) -> Signature {
+    Signature {
+        r: signature.r(),
+        s: signature.s(),
+        v: u64::from(signature.v()),
+    }
+}
+
+/// Hex encoded private key handed to the 1Money client, wiped from memory when dropped.
+struct OnemoneyKey(Zeroizing<[u8; HEX_PRIVATE_KEY_LEN]>);
+
+impl OnemoneyKey {
+    fn new(signer: &PrivateKeySigner) -> Self {
+        let mut key = signer.to_bytes();
+        let mut hex = Zeroizing::new([0; HEX_PRIVATE_KEY_LEN]);
+        hex[..2].copy_from_slice(b"0x");
+        hex::encode_to_slice(key.0, &mut hex[2..]).unwrap_or_else(|_| {
+            unreachable!("the buffer holds exactly two hex digits per key byte")
+        });
+        key.0.zeroize();
+        Self(hex)
+    }
+
+    fn as_str(&self) -> &str {
+        // Only ASCII hex digits are written to the buffer
+        core::str::from_utf8(&*self.0).unwrap_or_default()
+    }
+}
+
+/// Reads a hex encoded private key, with or without `0x` prefix, from the file at `path`.
+pub(crate) fn read_key_file(path: &Path) -> Result<PrivateKeySigner, SignerError> {
+    let contents =
+        Zeroizing::new(
+            fs::read_to_string(path).map_err(|source| SignerError::ReadKeyFile {
+                path: path.to_owned(),
+                source,
+            })?,
+        );
+    let hex_key = contents.trim();
+    let hex_key = hex_key.strip_prefix("0x").unwrap_or(hex_key);
+
+    let invalid = || SignerError::InvalidKeyFile {
+        path: path.to_owned(),
+    };
+    let mut key = Zeroizing::new([0; PRIVATE_KEY_LEN]);
+    hex::decode_to_slice(hex_key, &mut *key).map_err(|_| invalid())?;
+    PrivateKeySigner::from_slice(&*key).map_err(|_| invalid())
+}
+
+fn read_password_file(path: &Path) -> Result<Zeroizing<String>, SignerError> {
+    let contents = Zeroizing::new(fs::read_to_string(path).map_err(SignerError::Password)?);
+    Ok(Zeroizing::new(
+        contents.trim_end_matches(['\r', '\n']).to_owned(),
+    ))
+}
+
+fn decrypt_keystore(path: &Path, password: &str) -> Result<PrivateKeySigner, SignerError> {
+    let key = Zeroizing::new(eth_keystore::decrypt_key(path, password).map_err(|source| {
+        SignerError::Keystore {
+            path: path.to_owned(),
+            source,
+        }
+    })?);
+    PrivateKeySigner::from_slice(&key).map_err(|_| SignerError::InvalidKeystore {
+        path: path.to_owned(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use tempfile::TempDir;
+    use url::Url;
+
+    use super::*;
+
+    const KEY: &str = "ac0974bec39a17e36ba4a6b4d238ff944bacb478cbed5efcae784d7bf4f2ff80";
+
+    fn signer() -> PrivateKeySigner {
+        KEY.parse().expect("valid key")
+    }
+
+    #[test]
+    fn key_file_is_loaded() {
+        let dir = TempDir::new().expect("temp dir");
+        let path = dir.path().join("relayer.key");
+        fs::write(&path, format!("0x{KEY}\n")).expect("written key file");
+
+        let loaded = RelayerSigner::load(&SignerConfig {
+            relayer_key_file: Some(path),
+            ..SignerConfig::default()
+        })
+        .expect("loaded key file");
+        assert_eq!(loaded.address(), signer().address());
+        assert!(matches!(loaded, RelayerSigner::Local(_)));
+        assert_eq!(OnemoneyKey::new(&signer()).as_str(), format!("0x{KEY}"));
+    }
+
+    #[test]
+    fn keystore_is_decrypted() {
+        let dir = TempDir::new().expect("temp dir");
+        let key = hex::decode(KEY).expect("hex key");
+        eth_keystore::encrypt_key(
+            dir.path(),
+            &mut rand::thread_rng(),
+            key,
+            "password",
+            Some("keystore.json"),
+        )
+        .expect("encrypted keystore");
+        let password_file = dir.path().join("password");
+        fs::write(&password_file, "password\n").expect("written password file");
+
+        let loaded = RelayerSigner::load(&SignerConfig {
+            relayer_keystore: Some(dir.path().join("keystore.json")),
+            relayer_keystore_password_file: Some(password_file),
+            ..SignerConfig::default()
+        })
+        .expect("decrypted keystore");
+        assert_eq!(loaded.address(), signer().address());
+    }
+
+    #[test]
+    fn single_key_source_is_required() {
+        assert!(matches!(
+            RelayerSigner::load(&SignerConfig::default()),
+            Err(SignerError::MissingKey)
+        ));
+        assert!(matches!(
+            RelayerSigner::load(&SignerConfig {
+                relayer_private_key: Some(signer()),
+                relayer_key_file: Some("relayer.key".into()),
+                ..SignerConfig::default()
+            }),
+            Err(SignerError::ConflictingKeys)
+        ));
+    }
+
+    #[test]
+    fn roles_default_to_relayer_key() {
+        let role_key = PrivateKeySigner::random();
+        let signers = Signers::load(&SignerConfig {
+            relayer_private_key: Some(signer()),
+            tx_mapping_private_key: Some(role_key.clone()),
+            ..SignerConfig::default()
+        })
+        .expect("loaded signers");
+
+        assert_eq!(signers.get(Role::Interop).address(), signer().address());
+        assert_eq!(
+            signers.get(Role::ValidatorManager).address(),
+            signer().address()
+        );
+        assert_eq!(signers.get(Role::TxMapping).address(), role_key.address());
+    }
+
+    #[test]
+    fn role_address_requires_remote_signer() {
+        assert!(matches!(
+            Signers::load(&SignerConfig {
+                relayer_private_key: Some(signer()),
+                validator_manager_address: Some(Address::ZERO),
+                ..SignerConfig::default()
+            }),
+            Err(SignerError::MissingRemoteSigner {
+                role: Role::ValidatorManager
+            })
+        ));
+    }
+
+    #[test]
+    fn local_relayer_key_with_remote_roles() {
+        let url: Url = "http://127.0.0.1:9000".parse().expect("valid url");
+        let role_address = PrivateKeySigner::random().address();
+        let signers = Signers::load(&SignerConfig {
+            relayer_private_key: Some(signer()),
+            remote_signer_url: Some(url),
+            tx_mapping_address: Some(role_address),
+            ..SignerConfig::default()
+        })
+        .expect("loaded signers");
+
+        assert!(matches!(signers.relayer, RelayerSigner::Local(_)));
+        assert!(matches!(signers.tx_mapping, RelayerSigner::Remote(_)));
+        assert_eq!(signers.get(Role::TxMapping).address(), role_address);
+    }
+
+    #[test]
+    fn remote_signer_requires_address() {
+        let url: Url = "http://127.0.0.1:9000".parse().expect("valid url");
+        assert!(matches!(
+            RelayerSigner::load(&SignerConfig {
+                remote_signer_url: Some(url.clone()),
+                ..SignerConfig::default()
+            }),
+            Err(SignerError::MissingRemoteAddress)
+        ));
+
+        let loaded = RelayerSigner::load(&SignerConfig {
+            remote_signer_url: Some(url),
+            relayer_address: Some(signer().address()),
+            ..SignerConfig::default()
+        })
+        .expect("remote signer");
+        assert_eq!(loaded.address(), signer().address());
+    }
+}
@@ -0,0 +1,176 @@
+//! Signer delegating signatures to a remote signer exposing the Web3Signer HTTP API.
+//!
+//! Both sidechain transactions and 1Money payloads are signed through
+//! `POST /api/v1/eth1/sign/{address}`, which returns the signature of the keccak256 hash of
+//! the posted data. The key of the relayer account never reaches the relayer host.
+
+use alloy_consensus::SignableTransaction;
+use alloy_primitives::{hex, Address, Signature};
+use alloy_provider::network::TxSigner;
+use async_trait::async_trait;
+use serde::Serialize;
+use url::Url;
+
+use crate::signer::error::Error as SignerError;
+
+#[derive(Debug, Clone)]
+pub struct RemoteSigner {
+    client: reqwest::Client,
+    url: Url,
+    address: Address,
+}
+
+#[derive(Serialize)]
+struct SignRequest {
+    data: String,
+}
+
+impl RemoteSigner {
+    /// Creates a signer for the account `address` held by the remote signer at `url`.
+    pub fn new(url: Url, address: Address) -> Self {
+        Self {
+            client: reqwest::Client::new(),
+            url,
+            address,
+        }
+    }
+
+    pub const fn address(&self) -> Address {
+        self.address
+    }
+
+    /// Signs the keccak256 hash of `data` with the remote key.
+    pub async fn sign(&self, data: &[u8]) -> Result<Signature, SignerError> {
+        let url = format!(
+            "{}/api/v1/eth1/sign/{}",
+            self.url.as_str().trim_end_matches('/'),
+            self.address
+        );
+        let response = self
+            .client
+            .post(url)
+            .json(&SignRequest {
+                data: hex::encode_prefixed(data),
+            })
+            .send()
+            .await
+            .and_then(reqwest::Response::error_for_status)
+            .map_err(SignerError::RemoteRequest)?;
+        let body = response.text().await.map_err(SignerError::RemoteRequest)?;
+
+        body.trim()
+            .parse::<Signature>()
+            .map_err(SignerError::RemoteSignature)
+    }
+}
+
+#[async_trait]
+impl TxSigner<Signature> for RemoteSigner {
+    fn address(&self) -> Address {
+        self.address
+    }
+
+    async fn sign_transaction(
+        &self,
+        tx: &mut dyn SignableTransaction<Signature>,
+    ) -> alloy_signer::Result<Signature> {
+        let mut encoded = Vec::new();
+        tx.encode_for_signing(&mut encoded);
+        self.sign(&encoded)
+            .await
+            .map_err(alloy_signer::Error::other)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloy_consensus::TxEip1559;
+    use alloy_primitives::{keccak256, Bytes, TxKind, U256};
+    use alloy_signer::SignerSync;
+    use alloy_signer_local::PrivateKeySigner;
+    use axum::extract::{Path, State};
+    use axum::http::StatusCode;
+    use axum::routing::post;
+    use axum::{Json, Router};
+    use serde::Deserialize;
+    use tokio::net::TcpListener;
+
+    use super::*;
+
+    #[derive(Deserialize)]
+    struct MockSignRequest {
+        data: Bytes,
+    }
+
+    async fn mock_sign(
+        State(signer): State<PrivateKeySigner>,
+        Path(address): Path<Address>,
+        Json(request): Json<MockSignRequest>,
+    ) -> Result<String, StatusCode> {
+        if address != signer.address() {
+            return Err(StatusCode::NOT_FOUND);
+        }
+        let signature = signer
+            .sign_hash_sync(&keccak256(&request.data))
+            .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+        Ok(hex::encode_prefixed(signature.as_bytes()))
+    }
+
+    /// Serves the signing endpoint of a remote signer holding `signer` on a local port.
+    async fn spawn_mock_signer(signer: PrivateKeySigner) -> Url {
+        let listener = TcpListener::bind("127.0.0.1:0")
+            .await
+            .expect("bound mock signer");
+        let url = format!("http://{}", listener.local_addr().expect("local address"));
+        let router = Router::new()
+            .route("/api/v1/eth1/sign/{address}", post(mock_sign))
+            .with_state(signer);
+        tokio::spawn(async move { axum::serve(listener, router).await });
+        url.parse().expect("valid url")
+    }
+
+    #[tokio::test]
+    async fn data_is_signed_remotely() {
+        let key = PrivateKeySigner::random();
+        let remote = RemoteSigner::new(spawn_mock_signer(key.clone()).await, key.address());
+
+        let signature = remote.sign(b"payload").await.expect("signed payload");
+        assert_eq!(
+            signature
+                .recover_address_from_prehash(&keccak256(b"payload"))
+                .ok(),
+            Some(key.address())
+        );
+
+        let unknown = RemoteSigner::new(remote.url.clone(), Address::ZERO);
+        assert!(matches!(
+            unknown.sign(b"payload").await,
+            Err(SignerError::RemoteRequest(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn transactions_are_signed_remotely() {
+        let key = PrivateKeySigner::random();
+        let remote = RemoteSigner::new(spawn_mock_signer(key.clone()).await, key.address());
+
+        let mut tx = TxEip1559 {
+            chain_id: 1,
+            nonce: 3,
+            gas_limit: 21_000,
+            to: TxKind::Call(Address::ZERO),
+            value: U256::from(1),
+            ..TxEip1559::default()
+        };
+        let signature = remote
+            .sign_transaction(&mut tx)
+            .await
+            .expect("signed transaction");
+        assert_eq!(
+            signature
+                .recover_address_from_prehash(&tx.signature_hash())
+                .ok(),
+            Some(key.address())
+        );
+    }
+}
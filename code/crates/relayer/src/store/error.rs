@@ -0,0 +1,17 @@
+use std::path::PathBuf;
+use std::sync::Arc;
+
+use thiserror::Error;
+
+#[derive(Debug, Error)]
+pub enum Error {
+    #[error("Failed to access state store `{path}`: {source}")]
+    Io {
+        path: PathBuf,
+        source: std::io::Error,
+    },
+    #[error("Failed to encode relayer state: {0}")]
+    Encode(#[from] serde_json::Error),
+    #[error("Failed to persist relayer state: {0}")]
+    Persist(Arc<Self>),
+}
@@ -0,0 +1,631 @@
+//! Local state store used to resume the relayer flows after a restart.
+//!
+//! The whole state is kept in memory and written to a JSON file by a background thread,
+//! which coalesces the changes made while a write is in progress. Writes go to a temporary
+//! file which is synced and atomically renamed over the previous snapshot, so a crash never
+//! leaves a partially written store behind. Transfer updates wait until they are on disk,
+//! cursor updates do not.
+
+use std::collections::BTreeMap;
+use std::fs::{self, File};
+use std::io::{ErrorKind, Write};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Condvar, Mutex, MutexGuard, PoisonError};
+use std::thread::{self, JoinHandle};
+
+use alloy_primitives::B256;
+use serde::{Deserialize, Serialize};
+use tokio::sync::watch;
+use tracing::warn;
+
+pub mod error;
+
+use error::Error as StoreError;
+
+/// Position of the last sidechain log processed by the relayer.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+pub struct LogCursor {
+    pub block_number: u64,
+    pub log_index: u64,
+}
+
+impl LogCursor {
+    /// Cursor placed after every log of `block_number`.
+    pub const fn end_of_block(block_number: u64) -> Self {
+        Self {
+            block_number,
+            log_index: u64::MAX,
+        }
+    }
+}
+
+/// Kind of cross-chain transfer tracked by the relayer.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TransferKind {
+    /// Sidechain `bridgeFrom` relayed as a 1Money `BridgeAndMint`.
+    Deposit,
+    /// 1Money `BurnAndBridge` relayed as a sidechain `bridgeTo`.
+    Withdrawal,
+    /// Refund of a `BurnAndBridge` relayed as a 1Money payment.
+    Refund,
+}
+
+/// Progress of an in-flight transfer, keyed by its source transaction hash.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "state", rename_all = "snake_case")]
+pub enum TransferState {
+    /// The source transaction was observed but nothing was submitted yet.
+    Observed,
+    /// The source transaction hash was registered in `TxHashMapping`.
+    Registered,
+    /// The destination transaction was submitted but the hashes are not linked yet.
+    Relayed { destination_hash: B256 },
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RelayerState {
+    /// Last sidechain log processed by the incoming stream flow.
+    pub sidechain_cursor: Option<LogCursor>,
+    /// Last sidechain block fully processed by the incoming clearing flow.
+    pub sidechain_clearing_cursor: Option<u64>,
+    /// Last 1Money checkpoint fully processed by the outgoing clearing flow.
+    pub checkpoint_cursor: Option<u64>,
+    /// Last epoch whose validator set was applied on the sidechain.
+    pub epoch_id: Option<u64>,
+    pub deposits: BTreeMap<B256, TransferState>,
+    pub withdrawals: BTreeMap<B256, TransferState>,
+    pub refunds: BTreeMap<B256, TransferState>,
+}
+
+impl RelayerState {
+    pub fn transfers(&self, kind: TransferKind) -> &BTreeMap<B256, TransferState> {
+        match kind {
+            TransferKind::Deposit => &self.deposits,
+            TransferKind::Withdrawal => &self.withdrawals,
+            TransferKind::Refund => &self.refunds,
+        }
+    }
+
+    /// Block from which the incoming flows resume, the earliest block of both cursors.
+    pub fn sidechain_resume_block(&self) -> Option<u64> {
+        let stream = self.sidechain_cursor.map(|cursor| cursor.block_number);
+        match (stream, self.sidechain_clearing_cursor) {
+            (Some(stream), Some(clearing)) => Some(stream.min(clearing)),
+            (stream, clearing) => stream.or(clearing),
+        }
+    }
+
+    /// Last sidechain block scanned by either incoming flow, the clearing flow moving on even
+    /// when no event is emitted.
+    pub fn sidechain_scanned_block(&self) -> Option<u64> {
+        let stream = self.sidechain_cursor.map(|cursor| cursor.block_number);
+        stream.max(self.sidechain_clearing_cursor)
+    }
+
+    fn transfers_mut(&mut self, kind: TransferKind) -> &mut BTreeMap<B256, TransferState> {
+        match kind {
+            TransferKind::Deposit => &mut self.deposits,
+            TransferKind::Withdrawal => &mut self.withdrawals,
+            TransferKind::Refund => &mut self.refunds,
+        }
+    }
+}
+
+/// Shared handle to the relayer state store.
+#[derive(Clone)]
+pub struct StateStore {
+    inner: Arc<Inner>,
+}
+
+struct Inner {
+    shared: Arc<Shared>,
+    /// Thread writing the state to disk, `None` for an in-memory store.
+    writer: Option<JoinHandle<()>>,
+}
+
+/// State shared with the writer thread.
+struct Shared {
+    path: Option<PathBuf>,
+    state: Mutex<Versioned>,
+    /// Notified when the state changes or the store is closed.
+    changed: Condvar,
+    persisted: watch::Sender<Persisted>,
+}
+
+struct Versioned {
+    state: RelayerState,
+    /// Bumped by every change of `state`.
+    version: u64,
+    closed: bool,
+}
+
+/// Progress of the writer thread.
+#[derive(Default)]
+struct Persisted {
+    /// Last version written to disk.
+    version: u64,
+    /// Version whose write failed, with its error, until a later write succeeds.
+    failed: Option<(u64, Arc<StoreError>)>,
+}
+
+impl StateStore {
+    /// Opens the store backed by the file at `path`.
+    ///
+    /// A missing file yields an empty state. A file which cannot be decoded is moved aside
+    /// with a `.corrupt` extension and the store starts empty, so that the flows fall back to
+    /// deriving their start points from chain state.
+    pub fn open(path: impl Into<PathBuf>) -> Result<Self, StoreError> {
+        let path = path.into();
+        let state = match fs::read(&path) {
+            Ok(bytes) => match serde_json::from_slice(&bytes) {
+                Ok(state) => state,
+                Err(err) => {
+                    let backup = path.with_extension("corrupt");
+                    warn!(
+                        path = %path.display(),
+                        backup = %backup.display(),
+                        %err,
+                        "Failed to decode relayer state store, starting from an empty state"
+                    );
+                    fs::rename(&path, &backup).map_err(|source| StoreError::Io {
+                        path: backup,
+                        source,
+                    })?;
+                    RelayerState::default()
+                }
+            },
+            Err(err) if err.kind() == ErrorKind::NotFound => RelayerState::default(),
+            Err(source) => return Err(StoreError::Io { path, source }),
+        };
+
+        let shared = Arc::new(Shared::new(Some(path.clone()), state));
+        let writer = thread::Builder::new()
+            .name("state-store".to_owned())
+            .spawn({
+                let shared = Arc::clone(&shared);
+                move || shared.run_writer()
+            })
+            .map_err(|source| StoreError::Io { path, source })?;
+
+        Ok(Self {
+            inner: Arc::new(Inner {
+                shared,
+                writer: Some(writer),
+            }),
+        })
+    }
+
+    /// Creates a store which is never written to disk.
+    pub fn in_memory() -> Self {
+        Self {
+            inner: Arc::new(Inner {
+                shared: Arc::new(Shared::new(None, RelayerState::default())),
+                writer: None,
+            }),
+        }
+    }
+
+    pub fn snapshot(&self) -> RelayerState {
+        self.inner.shared.lock().state.clone()
+    }
+
+    /// Advances the sidechain cursor; positions behind the current cursor are ignored.
+    ///
+    /// Cursors are written to disk in the background, a cursor lost in a crash only makes the
+    /// relayer process the same logs again.
+    pub fn advance_sidechain_cursor(&self, cursor: LogCursor) {
+        self.update(|state| {
+            if state
+                .sidechain_cursor
+                .is_none_or(|current| current < cursor)
+            {
+                state.sidechain_cursor = Some(cursor);
+            }
+        });
+    }
+
+    /// Moves the sidechain cursors back before `retracted`, a log removed by a reorg, so that
+    /// the logs replacing it are processed again after a restart.
+    pub fn rewind_sidechain_cursors(&self, retracted: LogCursor) {
+        let previous = match retracted.log_index.checked_sub(1) {
+            Some(log_index) => LogCursor {
+                block_number: retracted.block_number,
+                log_index,
+            },
+            None => LogCursor::end_of_block(retracted.block_number.saturating_sub(1)),
+        };
+        self.update(|state| {
+            if state
+                .sidechain_cursor
+                .is_some_and(|current| current > previous)
+            {
+                state.sidechain_cursor = Some(previous);
+            }
+            if state
+                .sidechain_clearing_cursor
+                .is_some_and(|current| current > previous.block_number)
+            {
+                state.sidechain_clearing_cursor = Some(previous.block_number);
+            }
+        });
+    }
+
+    /// Advances the sidechain clearing cursor; blocks behind the current cursor are ignored.
+    pub fn advance_sidechain_clearing_cursor(&self, block_number: u64) {
+        self.update(|state| {
+            if state
+                .sidechain_clearing_cursor
+                .is_none_or(|current| current < block_number)
+            {
+                state.sidechain_clearing_cursor = Some(block_number);
+            }
+        });
+    }
+
+    /// Advances the checkpoint cursor; checkpoints behind the current cursor are ignored.
+    pub fn advance_checkpoint_cursor(&self, checkpoint: u64) {
+        self.update(|state| {
+            if state
+                .checkpoint_cursor
+                .is_none_or(|current| current < checkpoint)
+            {
+                state.checkpoint_cursor = Some(checkpoint);
+            }
+        });
+    }
+
+    pub async fn set_epoch(&self, epoch_id: u64) -> Result<(), StoreError> {
+        let version = self.update(|state| state.epoch_id = Some(epoch_id));
+        self.persisted(version).await
+    }
+
+    pub fn transfer(&self, kind: TransferKind, source_hash: B256) -> Option<TransferState> {
+        self.inner
+            .shared
+            .lock()
+            .state
+            .transfers(kind)
+            .get(&source_hash)
+            .copied()
+    }
+
+    /// Records the progress of a transfer and waits until it is written to disk.
+    pub async fn set_transfer(
+        &self,
+        kind: TransferKind,
+        source_hash: B256,
+        transfer_state: TransferState,
+    ) -> Result<(), StoreError> {
+        let version = self.update(|state| {
+            state
+                .transfers_mut(kind)
+                .insert(source_hash, transfer_state);
+        });
+        self.persisted(version).await
+    }
+
+    /// Removes a transfer once its hashes are linked in `TxHashMapping`.
+    pub async fn complete_transfer(
+        &self,
+        kind: TransferKind,
+        source_hash: B256,
+    ) -> Result<(), StoreError> {
+        let version = self.update(|state| {
+            state.transfers_mut(kind).remove(&source_hash);
+        });
+        self.persisted(version).await
+    }
+
+    /// Waits until the current state is written to disk, used before exiting to make sure the
+    /// last cursors are persisted.
+    pub async fn flush(&self) -> Result<(), StoreError> {
+        let version = self.inner.shared.lock().version;
+        self.persisted(version).await
+    }
+
+    /// Applies `f` to the state and returns the version including the change.
+    fn update<F>(&self, f: F) -> u64
+    where
+        F: FnOnce(&mut RelayerState),
+    {
+        let shared = &self.inner.shared;
+        let mut versioned = shared.lock();
+        let previous = versioned.state.clone();
+        f(&mut versioned.state);
+        if versioned.state != previous {
+            versioned.version += 1;
+            shared.changed.notify_one();
+        }
+        versioned.version
+    }
+
+    /// Waits until `version` of the state is written to disk.
+    async fn persisted(&self, version: u64) -> Result<(), StoreError> {
+        if self.inner.writer.is_none() {
+            return Ok(());
+        }
+
+        let mut persisted = self.inner.shared.persisted.subscribe();
+        let persisted = persisted
+            .wait_for(|persisted| {
+                persisted.version >= version
+                    || persisted
+                        .failed
+                        .as_ref()
+                        .is_some_and(|(failed, _)| *failed >= version)
+            })
+            .await
+            .expect("the writer progress outlives the store handles");
+        match &persisted.failed {
+            Some((failed, err)) if persisted.version < version && *failed >= version => {
+                Err(StoreError::Persist(Arc::clone(err)))
+            }
+            _ => Ok(()),
+        }
+    }
+}
+
+impl Drop for Inner {
+    fn drop(&mut self) {
+        self.shared.lock().closed = true;
+        self.shared.changed.notify_one();
+        if let Some(writer) = self.writer.take() {
+            let _ = writer.join();
+        }
+    }
+}
+
+impl Shared {
+    fn new(path: Option<PathBuf>, state: RelayerState) -> Self {
+        Self {
+            path,
+            state: Mutex::new(Versioned {
+                state,
+                version: 0,
+                closed: false,
+            }),
+            changed: Condvar::new(),
+            persisted: watch::Sender::new(Persisted::default()),
+        }
+    }
+
+    /// Writes the latest state each time it changes, until the store is closed.
+    ///
+    /// Changes made while a write is in progress are written together by the next one.
+    fn run_writer(&self) {
+        let Some(path) = &self.path else {
+            return;
+        };
+
+        let mut written = 0;
+        loop {
+            let (state, version) = {
+                let mut versioned = self.lock();
+                while versioned.version == written && !versioned.closed {
+                    versioned = self
+                        .changed
+                        .wait(versioned)
+                        .unwrap_or_else(PoisonError::into_inner);
+                }
+                if versioned.version == written {
+                    return;
+                }
+                (versioned.state.clone(), versioned.version)
+            };
+
+            // A failed version is retried along with the next change
+            written = version;
+            match write_state(path, &state) {
+                Ok(()) => self.persisted.send_modify(|persisted| {
+                    persisted.version = version;
+                    persisted.failed = None;
+                }),
+                Err(err) => {
+                    warn!(path = %path.display(), %err, "Failed to write relayer state store");
+                    self.persisted.send_modify(|persisted| {
+                        persisted.failed = Some((version, Arc::new(err)));
+                    });
+                }
+            }
+        }
+    }
+
+    fn lock(&self) -> MutexGuard<'_, Versioned> {
+        self.state.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+/// Writes `state` to a temporary file which is synced and renamed over `path`, then syncs the
+/// directory so that the rename survives a crash.
+fn write_state(path: &Path, state: &RelayerState) -> Result<(), StoreError> {
+    let encoded = serde_json::to_vec_pretty(state)?;
+    let tmp_path = path.with_extension("tmp");
+    let io_error = |source| StoreError::Io {
+        path: tmp_path.clone(),
+        source,
+    };
+
+    let mut file = File::create(&tmp_path).map_err(io_error)?;
+    file.write_all(&encoded).map_err(io_error)?;
+    file.sync_all().map_err(io_error)?;
+    fs::rename(&tmp_path, path).map_err(io_error)?;
+
+    let dir = match path.parent() {
+        Some(dir) if !dir.as_os_str().is_empty() => dir,
+        _ => Path::new("."),
+    };
+    File::open(dir)
+        .and_then(|dir| dir.sync_all())
+        .map_err(|source| StoreError::Io {
+            path: dir.to_path_buf(),
+            source,
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use alloy_primitives::b256;
+
+    use super::*;
+
+    const SOURCE_HASH: B256 =
+        b256!("0x1111111111111111111111111111111111111111111111111111111111111111");
+    const DESTINATION_HASH: B256 =
+        b256!("0x2222222222222222222222222222222222222222222222222222222222222222");
+
+    #[tokio::test]
+    async fn state_survives_reopen() {
+        let dir = tempfile::tempdir().expect("temp dir");
+        let path = dir.path().join("state.json");
+
+        let store = StateStore::open(&path).expect("open store");
+        store.advance_sidechain_cursor(LogCursor {
+            block_number: 10,
+            log_index: 2,
+        });
+        store.advance_checkpoint_cursor(7);
+        store.set_epoch(3).await.expect("set epoch");
+        store
+            .set_transfer(
+                TransferKind::Deposit,
+                SOURCE_HASH,
+                TransferState::Relayed {
+                    destination_hash: DESTINATION_HASH,
+                },
+            )
+            .await
+            .expect("set transfer");
+
+        let reopened = StateStore::open(&path).expect("reopen store");
+        assert_eq!(reopened.snapshot(), store.snapshot());
+        assert_eq!(
+            reopened.transfer(TransferKind::Deposit, SOURCE_HASH),
+            Some(TransferState::Relayed {
+                destination_hash: DESTINATION_HASH
+            })
+        );
+        assert_eq!(reopened.transfer(TransferKind::Refund, SOURCE_HASH), None);
+    }
+
+    #[test]
+    fn cursors_never_move_backwards() {
+        let store = StateStore::in_memory();
+        let cursor = LogCursor {
+            block_number: 10,
+            log_index: 2,
+        };
+        store.advance_sidechain_cursor(cursor);
+        store.advance_sidechain_cursor(LogCursor {
+            block_number: 10,
+            log_index: 1,
+        });
+        store.advance_sidechain_clearing_cursor(8);
+        store.advance_sidechain_clearing_cursor(6);
+        store.advance_checkpoint_cursor(5);
+        store.advance_checkpoint_cursor(4);
+
+        let state = store.snapshot();
+        assert_eq!(state.sidechain_cursor, Some(cursor));
+        assert_eq!(state.sidechain_clearing_cursor, Some(8));
+        assert_eq!(state.checkpoint_cursor, Some(5));
+    }
+
+    #[test]
+    fn retracted_logs_rewind_cursors() {
+        let store = StateStore::in_memory();
+        store.advance_sidechain_cursor(LogCursor {
+            block_number: 10,
+            log_index: 2,
+        });
+        store.advance_sidechain_clearing_cursor(10);
+        store.rewind_sidechain_cursors(LogCursor {
+            block_number: 9,
+            log_index: 0,
+        });
+
+        let state = store.snapshot();
+        assert_eq!(state.sidechain_cursor, Some(LogCursor::end_of_block(8)));
+        assert_eq!(state.sidechain_clearing_cursor, Some(8));
+
+        // Logs past the cursors were never processed
+        store.rewind_sidechain_cursors(LogCursor {
+            block_number: 9,
+            log_index: 4,
+        });
+        assert_eq!(store.snapshot(), state);
+    }
+
+    #[test]
+    fn incoming_flows_resume_from_earliest_cursor() {
+        let store = StateStore::in_memory();
+        assert_eq!(store.snapshot().sidechain_resume_block(), None);
+
+        store.advance_sidechain_cursor(LogCursor {
+            block_number: 10,
+            log_index: 2,
+        });
+        assert_eq!(store.snapshot().sidechain_resume_block(), Some(10));
+
+        store.advance_sidechain_clearing_cursor(6);
+        assert_eq!(store.snapshot().sidechain_resume_block(), Some(6));
+        assert_eq!(store.snapshot().sidechain_scanned_block(), Some(10));
+    }
+
+    #[test]
+    fn quiet_sidechain_is_scanned_by_the_clearing_flow() {
+        let store = StateStore::in_memory();
+        assert_eq!(store.snapshot().sidechain_scanned_block(), None);
+
+        store.advance_sidechain_clearing_cursor(42);
+        assert_eq!(store.snapshot().sidechain_scanned_block(), Some(42));
+    }
+
+    #[tokio::test]
+    async fn completed_transfers_are_removed() {
+        let store = StateStore::in_memory();
+        store
+            .set_transfer(
+                TransferKind::Withdrawal,
+                SOURCE_HASH,
+                TransferState::Observed,
+            )
+            .await
+            .expect("set transfer");
+        store
+            .complete_transfer(TransferKind::Withdrawal, SOURCE_HASH)
+            .await
+            .expect("complete transfer");
+        assert!(store.snapshot().withdrawals.is_empty());
+    }
+
+    #[tokio::test]
+    async fn flushed_cursors_survive_reopen() {
+        let dir = tempfile::tempdir().expect("temp dir");
+        let path = dir.path().join("state.json");
+
+        let store = StateStore::open(&path).expect("open store");
+        for block_number in 1..=100 {
+            store.advance_sidechain_cursor(LogCursor::end_of_block(block_number));
+        }
+        store.flush().await.expect("flush store");
+
+        let reopened = StateStore::open(&path).expect("reopen store");
+        assert_eq!(
+            reopened.snapshot().sidechain_cursor,
+            Some(LogCursor::end_of_block(100))
+        );
+        assert!(!path.with_extension("tmp").exists());
+    }
+
+    #[test]
+    fn corrupt_store_starts_empty() {
+        let dir = tempfile::tempdir().expect("temp dir");
+        let path = dir.path().join("state.json");
+        fs::write(&path, b"not-json").expect("write corrupt store");
+
+        let store = StateStore::open(&path).expect("open store");
+        assert_eq!(store.snapshot(), RelayerState::default());
+        assert!(path.with_extension("corrupt").exists());
+    }
+}
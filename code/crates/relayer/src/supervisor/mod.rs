@@ -0,0 +1,225 @@
+//! Restarts of the relayer flows after failures.
+//!
+//! A flow failing with a recoverable error, e.g. an RPC timeout, is restarted after an
+//! exponential backoff instead of stopping the whole relayer. The relayer only exits once a
+//! flow fails with a fatal error or keeps failing after `--flow-max-restarts` attempts.
+
+use core::error::Error;
+use core::future::Future;
+use core::time::Duration;
+
+use humantime::format_duration;
+use tokio::time::Instant;
+use tracing::{error, warn};
+
+use crate::config::{Config, SupervisorConfig};
+use crate::health::{Flow, Health};
+use crate::shutdown::Shutdown;
+
+/// Error returned by a supervised flow.
+pub trait FlowError: Error {
+    /// Whether restarting the flow cannot recover from the error.
+    fn is_fatal(&self) -> bool;
+}
+
+impl FlowError for crate::incoming::error::Error {
+    fn is_fatal(&self) -> bool {
+        matches!(self, Self::Store(_))
+    }
+}
+
+impl FlowError for crate::outgoing::error::Error {
+    fn is_fatal(&self) -> bool {
+        matches!(self, Self::Store(_))
+    }
+}
+
+impl FlowError for crate::poa::error::Error {
+    fn is_fatal(&self) -> bool {
+        matches!(self, Self::Store(_))
+    }
+}
+
+/// Runs the relayer flows, restarting them after recoverable failures.
+#[derive(Clone)]
+pub struct Supervisor {
+    config: SupervisorConfig,
+    health: Health,
+    shutdown: Shutdown,
+}
+
+impl Supervisor {
+    pub fn new(config: &Config, health: &Health, shutdown: &Shutdown) -> Self {
+        Self {
+            config: config.supervisor.clone(),
+            health: health.clone(),
+            shutdown: shutdown.clone(),
+        }
+    }
+
+    /// Runs the flow started by `start` until it stops without an error.
+    ///
+    /// `start` is called again on every restart, with `true` to let the flow resume from its
+    /// persisted progress. The number of consecutive failures is reset once the flow ran for
+    /// longer than the maximum backoff.
+    pub async fn run<S, F, E>(&self, flow: Flow, mut start: S) -> Result<(), E>
+    where
+        S: FnMut(bool) -> F,
+        F: Future<Output = Result<(), E>>,
+        E: FlowError,
+    {
+        let mut backoff = self.config.flow_restart_backoff;
+        let mut failures = 0;
+        let mut restarted = false;
+
+        loop {
+            let started = Instant::now();
+            let Err(err) = self.health.track(flow, start(restarted)).await else {
+                return Ok(());
+            };
+
+            if started.elapsed() > self.config.flow_max_restart_backoff {
+                failures = 0;
+                backoff = self.config.flow_restart_backoff;
+            }
+            failures += 1;
+
+            if self.shutdown.is_requested() {
+                return Err(err);
+            }
+            if err.is_fatal() {
+                error!(flow = flow.as_str(), %err, "Flow failed with a fatal error");
+                return Err(err);
+            }
+            if failures > self.config.flow_max_restarts {
+                error!(
+                    flow = flow.as_str(),
+                    %err,
+                    failures,
+                    "Flow keeps failing, giving up"
+                );
+                return Err(err);
+            }
+
+            let restarts = self.health.record_restart(flow, &err);
+            warn!(
+                flow = flow.as_str(),
+                %err,
+                restarts,
+                failures,
+                backoff = %format_duration(backoff),
+                "Flow failed, restarting",
+            );
+            tokio::select! {
+                () = self.shutdown.requested() => return Ok(()),
+                () = tokio::time::sleep(backoff) => {}
+            }
+            backoff = next_backoff(backoff, self.config.flow_max_restart_backoff);
+            restarted = true;
+        }
+    }
+}
+
+fn next_backoff(backoff: Duration, max: Duration) -> Duration {
+    backoff.saturating_mul(2).min(max)
+}
+
+#[cfg(test)]
+mod tests {
+    use core::sync::atomic::{AtomicU32, Ordering};
+
+    use clap::Parser;
+
+    use super::*;
+    use crate::cli::Cli;
+    use crate::store::StateStore;
+
+    #[test]
+    fn backoff_doubles_up_to_the_maximum() {
+        let max = Duration::from_secs(5);
+        let mut backoff = Duration::from_secs(1);
+        let mut delays = Vec::new();
+        for _ in 0..5 {
+            delays.push(backoff.as_secs());
+            backoff = next_backoff(backoff, max);
+        }
+        assert_eq!(delays, [1, 2, 4, 5, 5]);
+    }
+
+    #[derive(Debug, thiserror::Error)]
+    #[error("flow failed")]
+    struct TestError {
+        fatal: bool,
+    }
+
+    impl FlowError for TestError {
+        fn is_fatal(&self) -> bool {
+            self.fatal
+        }
+    }
+
+    fn supervisor(max_restarts: u32) -> Supervisor {
+        let mut config = Cli::try_parse_from([
+            "relayer",
+            "--interop-contract-address",
+            "0x0000000000000000000000000000000000000001",
+            "--tx-mapping-contract-address",
+            "0x0000000000000000000000000000000000000002",
+            "proof-of-authority",
+        ])
+        .expect("valid arguments")
+        .config;
+        config.supervisor = SupervisorConfig {
+            flow_max_restarts: max_restarts,
+            flow_restart_backoff: Duration::from_millis(1),
+            flow_max_restart_backoff: Duration::from_millis(5),
+        };
+        let onemoney = config.onemoney_api().expect("valid 1Money url");
+        let health = Health::new(&config, &StateStore::in_memory(), onemoney);
+        Supervisor::new(&config, &health, &Shutdown::new())
+    }
+
+    #[tokio::test]
+    async fn recoverable_failures_are_restarted() {
+        let supervisor = supervisor(5);
+        let attempts = AtomicU32::new(0);
+
+        let result = supervisor
+            .run(Flow::OutgoingStream, |_| async {
+                if attempts.fetch_add(1, Ordering::Relaxed) < 3 {
+                    Err(TestError { fatal: false })
+                } else {
+                    Ok(())
+                }
+            })
+            .await;
+
+        assert!(result.is_ok());
+        assert_eq!(attempts.load(Ordering::Relaxed), 4);
+    }
+
+    #[tokio::test]
+    async fn repeated_or_fatal_failures_are_escalated() {
+        let supervisor = supervisor(2);
+
+        let attempts = AtomicU32::new(0);
+        let result = supervisor
+            .run(Flow::Poa, |_| async {
+                attempts.fetch_add(1, Ordering::Relaxed);
+                Err(TestError { fatal: false })
+            })
+            .await;
+        assert!(result.is_err());
+        assert_eq!(attempts.load(Ordering::Relaxed), 3);
+
+        let attempts = AtomicU32::new(0);
+        let result = supervisor
+            .run(Flow::Poa, |_| async {
+                attempts.fetch_add(1, Ordering::Relaxed);
+                Err(TestError { fatal: true })
+            })
+            .await;
+        assert!(result.is_err());
+        assert_eq!(attempts.load(Ordering::Relaxed), 1);
+    }
+}
@@ -0,0 +1,15 @@
+use alloy_primitives::B256;
+
+#[derive(Debug, thiserror::Error)]
+pub enum Error {
+    #[error("Failed to query TxHashMapping: {0}")]
+    Contract(#[from] alloy_contract::Error),
+    #[error("Failed to query the sidechain: {0}")]
+    Rpc(#[from] alloy_transport::RpcError<alloy_transport::TransportErrorKind>),
+    #[error("Failed to query 1Money: {0}")]
+    Onemoney(#[from] onemoney_protocol::error::Error),
+    #[error("Failed to encode the trace: {0}")]
+    Json(#[from] serde_json::Error),
+    #[error("No transfer found for transaction `{0}`")]
+    NotFound(B256),
+}
@@ -0,0 +1,736 @@
+//! Lifecycle of a cross-chain transfer, resolved from any of its transaction hashes.
+//!
+//! The transfer is looked up in `TxHashMapping` first. Transfers not registered or linked by
+//! the relayer yet are found from the OMInterop events of the sidechain transaction or from the
+//! 1Money transaction: a `BridgeAndMint` through its source hash and a refund through the
+//! `OMInteropSent` event of its `bridgeTo`. Each step is then completed with its receipt.
+
+use core::fmt;
+use core::time::Duration;
+use std::collections::BTreeMap;
+use std::time::UNIX_EPOCH;
+
+use alloy_primitives::{Address, B256, U256};
+use alloy_provider::{Provider, ProviderBuilder};
+use alloy_rpc_types_eth::{BlockNumberOrTag, Filter};
+use alloy_sol_types::SolEvent;
+use onemoney_interop::contract::OMInterop::{OMInteropEvents, OMInteropSent};
+use onemoney_interop::contract::TxHashMapping;
+use onemoney_interop::event::decode_event;
+use onemoney_protocol::error::Error as OnemoneyError;
+use onemoney_protocol::TxPayload;
+use serde::Serialize;
+
+pub mod error;
+
+use error::Error as TraceError;
+
+use crate::config::Config;
+use crate::onemoney::api::OneMoneyApi;
+
+/// Largest block range of the `eth_getLogs` queries.
+const MAX_BLOCK_RANGE: u64 = 100_000;
+
+/// Direction of a transfer.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Direction {
+    /// Sidechain `bridgeFrom` relayed as a 1Money `BridgeAndMint`.
+    Deposit,
+    /// 1Money `BurnAndBridge` relayed as a sidechain `bridgeTo`, possibly refunded.
+    Withdrawal,
+}
+
+/// Transaction of a transfer.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Stage {
+    BridgeFrom,
+    BridgeAndMint,
+    BurnAndBridge,
+    BridgeTo,
+    Refund,
+}
+
+impl Stage {
+    const fn as_str(self) -> &'static str {
+        match self {
+            Self::BridgeFrom => "bridgeFrom",
+            Self::BridgeAndMint => "BridgeAndMint",
+            Self::BurnAndBridge => "BurnAndBridge",
+            Self::BridgeTo => "bridgeTo",
+            Self::Refund => "Refund",
+        }
+    }
+
+    const fn chain(self) -> &'static str {
+        match self {
+            Self::BridgeFrom | Self::BridgeTo => "sidechain",
+            Self::BridgeAndMint | Self::BurnAndBridge | Self::Refund => "1money",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum StepStatus {
+    Success,
+    Failed,
+    /// The transaction hash is known but the transaction was not found on its chain.
+    Pending,
+    /// The transaction is not linked to the transfer yet.
+    Missing,
+}
+
+impl StepStatus {
+    const fn as_str(self) -> &'static str {
+        match self {
+            Self::Success => "success",
+            Self::Failed => "failed",
+            Self::Pending => "pending",
+            Self::Missing => "missing",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TransferStatus {
+    /// The source transaction was not registered in `TxHashMapping` by the relayer yet.
+    Unregistered,
+    InProgress,
+    /// The `bridgeTo` succeeded but the refund it emitted is not linked yet.
+    AwaitingRefund,
+    Completed,
+    Failed,
+}
+
+impl TransferStatus {
+    const fn as_str(self) -> &'static str {
+        match self {
+            Self::Unregistered => "not registered by the relayer yet",
+            Self::InProgress => "in progress",
+            Self::AwaitingRefund => "awaiting refund",
+            Self::Completed => "completed",
+            Self::Failed => "failed",
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Step {
+    pub stage: Stage,
+    pub chain: &'static str,
+    pub hash: Option<B256>,
+    pub status: StepStatus,
+    /// Sidechain block including the transaction.
+    pub block_number: Option<u64>,
+    /// Unix timestamp of the sidechain block including the transaction.
+    pub timestamp: Option<u64>,
+    /// Transfer details read from the OMInterop events or the 1Money transaction.
+    pub details: BTreeMap<&'static str, String>,
+}
+
+impl Step {
+    fn new(stage: Stage, hash: Option<B256>, status: StepStatus) -> Self {
+        Self {
+            stage,
+            chain: stage.chain(),
+            hash,
+            status,
+            block_number: None,
+            timestamp: None,
+            details: BTreeMap::new(),
+        }
+    }
+}
+
+/// Lifecycle of a transfer, printed by `relayer trace`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Trace {
+    pub direction: Direction,
+    pub status: TransferStatus,
+    /// Whether the source transaction is registered in `TxHashMapping`.
+    pub registered: bool,
+    pub steps: Vec<Step>,
+}
+
+/// Hashes of a transfer, as linked in `TxHashMapping`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Links {
+    Deposit {
+        bridge_from: B256,
+        bridge_and_mint: Option<B256>,
+        registered: bool,
+    },
+    Withdrawal {
+        burn_and_bridge: B256,
+        bridge_to: Option<B256>,
+        refund: Option<B256>,
+        registered: bool,
+    },
+}
+
+/// Resolves the transfer including the transaction with hash `hash`, on either chain.
+pub async fn trace_transfer(
+    config: &Config,
+    onemoney: &dyn OneMoneyApi,
+    hash: B256,
+) -> Result<Trace, TraceError> {
+    let tracer = Tracer {
+        provider: ProviderBuilder::new().connect_http(config.side_chain_http_url.clone()),
+        onemoney,
+        config,
+    };
+
+    match tracer.resolve(hash).await? {
+        Links::Deposit {
+            bridge_from,
+            bridge_and_mint,
+            registered,
+        } => {
+            let (bridge_from, _) = tracer
+                .sidechain_step(Stage::BridgeFrom, Some(bridge_from))
+                .await?;
+            let bridge_and_mint = tracer
+                .onemoney_step(Stage::BridgeAndMint, bridge_and_mint)
+                .await?;
+            Ok(Trace::new(
+                Direction::Deposit,
+                registered,
+                vec![bridge_from, bridge_and_mint],
+            ))
+        }
+        Links::Withdrawal {
+            burn_and_bridge,
+            bridge_to,
+            refund,
+            registered,
+        } => {
+            let burn_and_bridge = tracer
+                .onemoney_step(Stage::BurnAndBridge, Some(burn_and_bridge))
+                .await?;
+            let (bridge_to, events) = tracer.sidechain_step(Stage::BridgeTo, bridge_to).await?;
+            let mut steps = vec![burn_and_bridge, bridge_to];
+
+            // A refund is only due if the `bridgeTo` returned part of the amount
+            let refund_due = events.iter().any(|event| {
+                matches!(event, OMInteropEvents::OMInteropSent(sent) if sent.refundAmount > U256::ZERO)
+            });
+            if refund.is_some() || refund_due {
+                steps.push(tracer.onemoney_step(Stage::Refund, refund).await?);
+            }
+
+            Ok(Trace::new(Direction::Withdrawal, registered, steps))
+        }
+    }
+}
+
+struct Tracer<'a, P> {
+    provider: P,
+    onemoney: &'a dyn OneMoneyApi,
+    config: &'a Config,
+}
+
+impl<P: Provider> Tracer<'_, P> {
+    async fn resolve(&self, hash: B256) -> Result<Links, TraceError> {
+        let mapping_contract =
+            TxHashMapping::new(self.config.tx_mapping_contract_address, &self.provider);
+
+        let deposit = mapping_contract.getDepositByBridgeFrom(hash).call().await?;
+        if deposit.isSet {
+            return Ok(Links::Deposit {
+                bridge_from: hash,
+                bridge_and_mint: non_zero(deposit.linked),
+                registered: true,
+            });
+        }
+
+        let bridge_from = mapping_contract
+            .getDepositByBridgeAndMint(hash)
+            .call()
+            .await?;
+        if !bridge_from.is_zero() {
+            return Ok(Links::Deposit {
+                bridge_from,
+                bridge_and_mint: Some(hash),
+                registered: true,
+            });
+        }
+
+        let withdrawal = mapping_contract.getWithdrawal(hash).call().await?;
+        if withdrawal.isSet {
+            return Ok(Links::Withdrawal {
+                burn_and_bridge: hash,
+                bridge_to: non_zero(withdrawal.bridgeTo),
+                refund: non_zero(withdrawal.refund),
+                registered: true,
+            });
+        }
+
+        let withdrawal = mapping_contract
+            .getWithdrawalFromBridgeTo(hash)
+            .call()
+            .await?;
+        if withdrawal.isSet {
+            return Ok(Links::Withdrawal {
+                burn_and_bridge: withdrawal.burnAndBridge,
+                bridge_to: Some(hash),
+                refund: non_zero(withdrawal.refund),
+                registered: true,
+            });
+        }
+
+        let withdrawal = mapping_contract
+            .getWithdrawalFromRefund(hash)
+            .call()
+            .await?;
+        if withdrawal.isSet {
+            return Ok(Links::Withdrawal {
+                burn_and_bridge: withdrawal.burnAndBridge,
+                bridge_to: non_zero(withdrawal.bridgeTo),
+                refund: Some(hash),
+                registered: true,
+            });
+        }
+
+        // Not registered by the relayer yet, look for the source transaction on both chains
+        for event in self.interop_events(hash).await?.1 {
+            match event {
+                OMInteropEvents::OMInteropReceived(_) => {
+                    return Ok(Links::Deposit {
+                        bridge_from: hash,
+                        bridge_and_mint: None,
+                        registered: false,
+                    });
+                }
+                OMInteropEvents::OMInteropSent(sent) => {
+                    return Ok(Links::Withdrawal {
+                        burn_and_bridge: sent.sourceHash,
+                        bridge_to: Some(hash),
+                        refund: None,
+                        registered: false,
+                    });
+                }
+                _ => {}
+            }
+        }
+
+        let transaction = found(self.onemoney.transaction_by_hash(hash).await)?
+            .ok_or(TraceError::NotFound(hash))?;
+        match transaction.data {
+            TxPayload::TokenBurnAndBridge { .. } => Ok(Links::Withdrawal {
+                burn_and_bridge: hash,
+                bridge_to: None,
+                refund: None,
+                registered: false,
+            }),
+            // A `BridgeAndMint` carries the hash of its `bridgeFrom`, which may be registered
+            // even though the relayer did not link them yet
+            TxPayload::TokenBridgeAndMint { source_tx_hash, .. } => {
+                let bridge_from: B256 = source_tx_hash
+                    .parse()
+                    .map_err(|_| TraceError::NotFound(hash))?;
+                let deposit = mapping_contract
+                    .getDepositByBridgeFrom(bridge_from)
+                    .call()
+                    .await?;
+                Ok(Links::Deposit {
+                    bridge_from,
+                    bridge_and_mint: Some(hash),
+                    registered: deposit.isSet,
+                })
+            }
+            // A refund is only identified by the `OMInteropSent` event of its `bridgeTo`
+            TxPayload::TokenTransfer {
+                recipient,
+                value,
+                token: Some(token),
+            } => {
+                let Some((sent, bridge_to)) = self
+                    .find_refunded_sent(recipient, token, transaction.nonce, &value)
+                    .await?
+                else {
+                    return Err(TraceError::NotFound(hash));
+                };
+                let withdrawal = mapping_contract
+                    .getWithdrawal(sent.sourceHash)
+                    .call()
+                    .await?;
+                Ok(Links::Withdrawal {
+                    burn_and_bridge: sent.sourceHash,
+                    bridge_to: Some(bridge_to),
+                    refund: Some(hash),
+                    registered: withdrawal.isSet,
+                })
+            }
+            _ => Err(TraceError::NotFound(hash)),
+        }
+    }
+
+    /// Searches the sidechain for the `OMInteropSent` event whose refund is the transfer of
+    /// `value` of `token` to `recipient` with nonce `nonce`, with the hash of its `bridgeTo`.
+    async fn find_refunded_sent(
+        &self,
+        recipient: Address,
+        token: Address,
+        nonce: u64,
+        value: &str,
+    ) -> Result<Option<(OMInteropSent, B256)>, TraceError> {
+        let Ok(value) = value.parse::<U256>() else {
+            return Ok(None);
+        };
+        let latest_block = self.provider.get_block_number().await?;
+
+        let mut from_block = 0;
+        while from_block <= latest_block {
+            let to_block = (from_block + MAX_BLOCK_RANGE - 1).min(latest_block);
+
+            // `from` and `omToken` are indexed in `OMInteropSent`
+            let filter = Filter::new()
+                .address(self.config.interop_contract_address)
+                .event_signature(OMInteropSent::SIGNATURE_HASH)
+                .topic1(recipient)
+                .topic2(token)
+                .from_block(BlockNumberOrTag::Number(from_block))
+                .to_block(BlockNumberOrTag::Number(to_block));
+
+            for log in self.provider.get_logs(&filter).await? {
+                let Ok(sent) = OMInteropSent::decode_raw_log(log.topics(), &log.data().data) else {
+                    continue;
+                };
+                if sent.nonce != nonce || sent.refundAmount != value {
+                    continue;
+                }
+                if let Some(bridge_to) = log.transaction_hash {
+                    return Ok(Some((sent, bridge_to)));
+                }
+            }
+
+            from_block = to_block + 1;
+        }
+
+        Ok(None)
+    }
+
+    /// Returns the step of the sidechain transaction `hash` with the OMInterop events it
+    /// emitted.
+    async fn sidechain_step(
+        &self,
+        stage: Stage,
+        hash: Option<B256>,
+    ) -> Result<(Step, Vec<OMInteropEvents>), TraceError> {
+        let Some(hash) = hash else {
+            return Ok((Step::new(stage, None, StepStatus::Missing), Vec::new()));
+        };
+
+        let (receipt, events) = self.interop_events(hash).await?;
+        let Some((success, block_number)) = receipt else {
+            return Ok((Step::new(stage, Some(hash), StepStatus::Pending), events));
+        };
+
+        let status = if success {
+            StepStatus::Success
+        } else {
+            StepStatus::Failed
+        };
+        let mut step = Step::new(stage, Some(hash), status);
+        step.block_number = block_number;
+        if let Some(block_number) = block_number {
+            step.timestamp = self
+                .provider
+                .get_block_by_number(BlockNumberOrTag::Number(block_number))
+                .await?
+                .map(|block| block.header.timestamp);
+        }
+        for event in &events {
+            add_event_details(&mut step.details, event);
+        }
+
+        Ok((step, events))
+    }
+
+    /// Fetches the receipt of the sidechain transaction `hash`, as its status and block, and
+    /// the OMInterop events it emitted.
+    #[allow(clippy::type_complexity)]
+    async fn interop_events(
+        &self,
+        hash: B256,
+    ) -> Result<(Option<(bool, Option<u64>)>, Vec<OMInteropEvents>), TraceError> {
+        let Some(receipt) = self.provider.get_transaction_receipt(hash).await? else {
+            return Ok((None, Vec::new()));
+        };
+
+        let events = receipt
+            .inner
+            .logs()
+            .iter()
+            .filter(|log| log.address() == self.config.interop_contract_address)
+            .filter_map(|log| decode_event(log.clone()).ok())
+            .map(|log| log.inner.data)
+            .collect();
+
+        Ok((Some((receipt.status(), receipt.block_number)), events))
+    }
+
+    async fn onemoney_step(&self, stage: Stage, hash: Option<B256>) -> Result<Step, TraceError> {
+        let Some(hash) = hash else {
+            return Ok(Step::new(stage, None, StepStatus::Missing));
+        };
+
+        let Some(transaction) = found(self.onemoney.transaction_by_hash(hash).await)? else {
+            return Ok(Step::new(stage, Some(hash), StepStatus::Pending));
+        };
+        let receipt = found(self.onemoney.transaction_receipt(hash).await)?;
+        let status = match receipt {
+            Some(receipt) if receipt.success => StepStatus::Success,
+            Some(_) => StepStatus::Failed,
+            None => StepStatus::Pending,
+        };
+
+        let mut step = Step::new(stage, Some(hash), status);
+        step.details.insert("nonce", transaction.nonce.to_string());
+        add_payload_details(&mut step.details, &transaction.data);
+        Ok(step)
+    }
+}
+
+impl Trace {
+    fn new(direction: Direction, registered: bool, steps: Vec<Step>) -> Self {
+        Self {
+            direction,
+            status: transfer_status(direction, registered, &steps),
+            registered,
+            steps,
+        }
+    }
+}
+
+fn transfer_status(direction: Direction, registered: bool, steps: &[Step]) -> TransferStatus {
+    let status = |stage| {
+        steps
+            .iter()
+            .find(|step| step.stage == stage)
+            .map(|step| step.status)
+    };
+
+    if steps.iter().any(|step| step.status == StepStatus::Failed) {
+        return TransferStatus::Failed;
+    }
+    let destination = match direction {
+        Direction::Deposit => Stage::BridgeAndMint,
+        Direction::Withdrawal => Stage::BridgeTo,
+    };
+    if status(destination) == Some(StepStatus::Success) {
+        return match status(Stage::Refund) {
+            Some(refund) if refund != StepStatus::Success => TransferStatus::AwaitingRefund,
+            _ => TransferStatus::Completed,
+        };
+    }
+    if registered {
+        TransferStatus::InProgress
+    } else {
+        TransferStatus::Unregistered
+    }
+}
+
+fn add_event_details(details: &mut BTreeMap<&'static str, String>, event: &OMInteropEvents) {
+    match event {
+        OMInteropEvents::OMInteropReceived(received) => {
+            details.insert("nonce", received.nonce.to_string());
+            details.insert("recipient", received.to.to_string());
+            details.insert("amount", received.amount.to_string());
+            details.insert("token", received.omToken.to_string());
+            details.insert("source_chain_id", received.srcChainId.to_string());
+        }
+        OMInteropEvents::OMInteropSent(sent) => {
+            details.insert("nonce", sent.nonce.to_string());
+            details.insert("sender", sent.from.to_string());
+            details.insert("refund_amount", sent.refundAmount.to_string());
+            details.insert("token", sent.omToken.to_string());
+            details.insert("destination_chain_id", sent.dstChainId.to_string());
+        }
+        _ => {}
+    }
+}
+
+fn add_payload_details(details: &mut BTreeMap<&'static str, String>, payload: &TxPayload) {
+    match payload {
+        TxPayload::TokenBridgeAndMint {
+            recipient,
+            value,
+            token,
+            ..
+        } => {
+            details.insert("recipient", recipient.to_string());
+            details.insert("amount", value.clone());
+            details.insert("token", token.to_string());
+        }
+        TxPayload::TokenTransfer {
+            recipient,
+            value,
+            token,
+        } => {
+            details.insert("recipient", recipient.to_string());
+            details.insert("amount", value.clone());
+            if let Some(token) = token {
+                details.insert("token", token.to_string());
+            }
+        }
+        TxPayload::TokenBurnAndBridge {
+            sender,
+            value,
+            token,
+            destination_address,
+            destination_chain_id,
+            ..
+        } => {
+            details.insert("sender", sender.to_string());
+            details.insert("amount", value.clone());
+            details.insert("token", token.to_string());
+            details.insert("destination_address", destination_address.clone());
+            details.insert("destination_chain_id", destination_chain_id.to_string());
+        }
+        _ => {}
+    }
+}
+
+/// Maps the answer of 1Money to unknown hashes to `None`.
+fn found<T>(result: Result<T, OnemoneyError>) -> Result<Option<T>, TraceError> {
+    match result {
+        Ok(value) => Ok(Some(value)),
+        Err(OnemoneyError::ResourceNotFound { .. }) => Ok(None),
+        Err(e) => Err(e.into()),
+    }
+}
+
+fn non_zero(hash: B256) -> Option<B256> {
+    if hash.is_zero() {
+        None
+    } else {
+        Some(hash)
+    }
+}
+
+impl fmt::Display for Trace {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let direction = match self.direction {
+            Direction::Deposit => "Deposit from the sidechain to 1Money",
+            Direction::Withdrawal => "Withdrawal from 1Money to the sidechain",
+        };
+        writeln!(f, "{direction}: {}", self.status.as_str())?;
+
+        for (index, step) in self.steps.iter().enumerate() {
+            let hash = step
+                .hash
+                .map_or_else(|| "-".to_owned(), |hash| hash.to_string());
+            write!(
+                f,
+                "{:>2}. {:<14} {:<10} {:<8} {hash}",
+                index + 1,
+                step.stage.as_str(),
+                step.chain,
+                step.status.as_str(),
+            )?;
+            if let Some(block_number) = step.block_number {
+                write!(f, " in block {block_number}")?;
+            }
+            if let Some(timestamp) = step.timestamp {
+                let time = UNIX_EPOCH + Duration::from_secs(timestamp);
+                write!(f, " at {}", humantime::format_rfc3339_seconds(time))?;
+            }
+            writeln!(f)?;
+            for (key, value) in &step.details {
+                writeln!(f, "      {key}: {value}")?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloy_primitives::b256;
+
+    use super::*;
+
+    const BURN_AND_BRIDGE: B256 =
+        b256!("0x1111111111111111111111111111111111111111111111111111111111111111");
+    const BRIDGE_TO: B256 =
+        b256!("0x2222222222222222222222222222222222222222222222222222222222222222");
+
+    fn withdrawal(refund: Option<StepStatus>) -> Trace {
+        let mut steps = vec![
+            Step::new(
+                Stage::BurnAndBridge,
+                Some(BURN_AND_BRIDGE),
+                StepStatus::Success,
+            ),
+            Step::new(Stage::BridgeTo, Some(BRIDGE_TO), StepStatus::Success),
+        ];
+        if let Some(refund) = refund {
+            steps.push(Step::new(Stage::Refund, None, refund));
+        }
+        Trace::new(Direction::Withdrawal, true, steps)
+    }
+
+    #[test]
+    fn status_follows_the_steps() {
+        assert_eq!(withdrawal(None).status, TransferStatus::Completed);
+        assert_eq!(
+            withdrawal(Some(StepStatus::Missing)).status,
+            TransferStatus::AwaitingRefund
+        );
+        assert_eq!(
+            withdrawal(Some(StepStatus::Failed)).status,
+            TransferStatus::Failed
+        );
+
+        let deposit = |registered, status| {
+            Trace::new(
+                Direction::Deposit,
+                registered,
+                vec![
+                    Step::new(Stage::BridgeFrom, Some(BRIDGE_TO), StepStatus::Success),
+                    Step::new(Stage::BridgeAndMint, None, status),
+                ],
+            )
+            .status
+        };
+        assert_eq!(
+            deposit(false, StepStatus::Missing),
+            TransferStatus::Unregistered
+        );
+        assert_eq!(
+            deposit(true, StepStatus::Pending),
+            TransferStatus::InProgress
+        );
+    }
+
+    #[test]
+    fn trace_is_printed_as_timeline() {
+        let mut trace = withdrawal(Some(StepStatus::Missing));
+        trace.steps[1].block_number = Some(42);
+        trace.steps[1].timestamp = Some(0);
+        trace.steps[1]
+            .details
+            .insert("refund_amount", "5".to_owned());
+
+        let printed = trace.to_string();
+        let lines = printed.lines().collect::<Vec<_>>();
+        assert_eq!(
+            lines[0],
+            "Withdrawal from 1Money to the sidechain: awaiting refund"
+        );
+        assert!(lines[1].starts_with(" 1. BurnAndBridge  1money     success "));
+        assert!(lines[2].ends_with(&format!("{BRIDGE_TO} in block 42 at 1970-01-01T00:00:00Z")));
+        assert_eq!(lines[3], "      refund_amount: 5");
+        assert!(lines[4].starts_with(" 3. Refund         1money     missing  -"));
+
+        let json = serde_json::to_value(&trace).expect("trace is serializable");
+        assert_eq!(json["status"], "awaiting_refund");
+        assert_eq!(json["steps"][1]["stage"], "bridge_to");
+        assert_eq!(json["steps"][1]["hash"], BRIDGE_TO.to_string());
+    }
+}
@@ -7,8 +7,14 @@ use alloy_provider::ProviderBuilder;
 use alloy_signer_local::PrivateKeySigner;
 use color_eyre::eyre::{eyre, Result};
 use onemoney_interop::contract::{OMInterop, TxHashMapping};
-use relayer::config::Config;
+use relayer::config::{
+    Config, DryRunConfig, HealthConfig, OnemoneyWsConfig, SidechainTxConfig, SignerConfig,
+    SupervisorConfig,
+};
+use relayer::context::RelayerContext;
 use relayer::outgoing::stream::relay_outgoing_events;
+use relayer::shutdown::Shutdown;
+use relayer::store::StateStore;
 use tracing::info;
 use utils::account::{fetch_balance, wait_for_balance_change};
 use utils::operator::{OperationClient, OPERATOR_PRIVATE_KEY};
@@ -21,7 +27,7 @@ use crate::utils::transaction::burn_and_bridge::burn_and_bridge;
 #[rstest::rstest]
 #[tokio::test]
 #[test_log::test]
-#[ignore = "Requires local Anvil node and 1Money API at http://127.0.0.1:18555"]
+#[ignore = "Requires a local Anvil node"]
 async fn ominterop_deposit_flow(#[future] e2e_test_context: E2ETestContext) -> Result<()> {
     let e2e_test_context = e2e_test_context.await;
     let E2ETestContext {
@@ -54,11 +60,22 @@ async fn ominterop_deposit_flow(#[future] e2e_test_context: E2ETestContext) -> R
     let config = Config {
         one_money_node_url: one_money_node_url.clone(),
         one_money_ws_url: one_money_ws_url.clone(),
+        one_money_ws: OnemoneyWsConfig::default(),
         side_chain_http_url: http_endpoint.clone(),
         side_chain_ws_url: ws_endpoint.clone(),
+        side_chain_confirmations: 0,
         interop_contract_address: interop_contract_addr,
-        relayer_private_key: relayer_wallet.clone(),
+        signer: SignerConfig {
+            relayer_private_key: Some(relayer_wallet.clone()),
+            ..SignerConfig::default()
+        },
         tx_mapping_contract_address: tx_mapping_contract_addr,
+        state_path: None,
+        metrics_addr: None,
+        health: HealthConfig::default(),
+        sidechain_tx: SidechainTxConfig::default(),
+        supervisor: SupervisorConfig::default(),
+        dry_run: DryRunConfig::default(),
     };
 
     spawn_relayer_and(config, || {
@@ -139,7 +156,7 @@ async fn ominterop_deposit_flow(#[future] e2e_test_context: E2ETestContext) -> R
 #[rstest::rstest]
 #[tokio::test]
 #[test_log::test]
-#[ignore = "Requires local Anvil node and 1Money API at http://127.0.0.1:18555"]
+#[ignore = "Requires a local Anvil node"]
 async fn clear_ominterop_deposit(#[future] e2e_test_context: E2ETestContext) -> Result<()> {
     let e2e_test_context = e2e_test_context.await;
     let E2ETestContext {
@@ -177,14 +194,35 @@ async fn clear_ominterop_deposit(#[future] e2e_test_context: E2ETestContext) ->
     let config = Config {
         one_money_node_url: one_money_node_url.clone(),
         one_money_ws_url: one_money_ws_url.clone(),
+        one_money_ws: OnemoneyWsConfig::default(),
         side_chain_http_url: http_endpoint.clone(),
         side_chain_ws_url: anvil.ws_endpoint_url(),
+        side_chain_confirmations: 0,
         interop_contract_address: interop_contract_addr,
-        relayer_private_key: relayer_wallet.clone(),
+        signer: SignerConfig {
+            relayer_private_key: Some(relayer_wallet.clone()),
+            ..SignerConfig::default()
+        },
         tx_mapping_contract_address: tx_mapping_contract_addr,
+        state_path: None,
+        metrics_addr: None,
+        health: HealthConfig::default(),
+        sidechain_tx: SidechainTxConfig::default(),
+        supervisor: SupervisorConfig::default(),
+        dry_run: DryRunConfig::default(),
     };
 
-    let relayer_nonce = config.sidechain_relayer_nonce().await?;
+    let signers = config.signers()?;
+    let onemoney = config.onemoney_api()?;
+    let ctx = RelayerContext {
+        config: config.clone(),
+        tx_manager: config
+            .sidechain_tx_manager(&signers, onemoney.clone())
+            .await?,
+        signer: signers.relayer,
+        store: StateStore::in_memory(),
+        onemoney,
+    };
 
     let relayer_provider = ProviderBuilder::new()
         .wallet(relayer_wallet.clone())
@@ -245,10 +283,7 @@ async fn clear_ominterop_deposit(#[future] e2e_test_context: E2ETestContext) ->
     )
     .await?;
 
-    let handler = {
-        let config_owned = config.clone();
-        tokio::spawn(async move { relay_outgoing_events(&config_owned, relayer_nonce).await })
-    };
+    let handler = tokio::spawn(async move { relay_outgoing_events(&ctx, &Shutdown::new()).await });
 
     // Wait for BurnAndBridge to be processed
     tokio::time::sleep(Duration::from_secs(15)).await;
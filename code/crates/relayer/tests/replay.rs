@@ -21,7 +21,7 @@ use crate::utils::operator::{OperationClient, OPERATOR_PRIVATE_KEY};
 #[rstest::rstest]
 #[tokio::test]
 #[test_log::test]
-#[ignore = "Requires 1Money API at http://127.0.0.1:18555"]
+#[ignore = "Requires a local Anvil node"]
 async fn cross_chain_replay_flow_is_enforced(
     #[future] e2e_test_context: E2ETestContext,
 ) -> Result<()> {
@@ -3,7 +3,7 @@ pub mod utils;
 use core::time::Duration;
 
 use alloy_primitives::hex::ToHexExt;
-use alloy_primitives::{Bytes, U256};
+use alloy_primitives::{Bytes, FixedBytes, U256};
 use alloy_provider::{Provider, ProviderBuilder};
 use alloy_rpc_types_eth::Filter;
 use alloy_signer_local::PrivateKeySigner;
@@ -13,7 +13,13 @@ use color_eyre::eyre::{eyre, Result};
 use onemoney_interop::contract::OMInterop::{OMInteropReceived, OMInteropSent};
 use onemoney_interop::contract::{OMInterop, TxHashMapping};
 use onemoney_protocol::{PaymentPayload, TokenBridgeAndMintPayload, TxPayload};
-use relayer::config::Config;
+use relayer::config::{
+    Config, DryRunConfig, HealthConfig, OnemoneyWsConfig, SidechainTxConfig, SignerConfig,
+    SupervisorConfig,
+};
+use relayer::context::RelayerContext;
+use relayer::outgoing::recovery::recover_incomplete_refunds_hash_mapping;
+use relayer::store::StateStore;
 use tracing::{debug, info};
 
 use crate::utils::operator::{OperationClient, OPERATOR_PRIVATE_KEY};
@@ -24,7 +30,7 @@ use crate::utils::transaction::burn_and_bridge::burn_and_bridge;
 #[rstest::rstest]
 #[tokio::test]
 #[test_log::test]
-#[ignore = "Requires local Anvil node and 1Money API at http://127.0.0.1:18555"]
+#[ignore = "Requires a local Anvil node"]
 async fn recover_incomplete_deposits(#[future] e2e_test_context: E2ETestContext) -> Result<()> {
     let e2e_test_context = e2e_test_context.await;
     let E2ETestContext {
@@ -59,11 +65,22 @@ async fn recover_incomplete_deposits(#[future] e2e_test_context: E2ETestContext)
     let config = Config {
         one_money_node_url: one_money_node_url.clone(),
         one_money_ws_url: one_money_ws_url.clone(),
+        one_money_ws: OnemoneyWsConfig::default(),
         side_chain_http_url: http_endpoint.clone(),
         side_chain_ws_url: anvil.ws_endpoint_url(),
+        side_chain_confirmations: 0,
         interop_contract_address: interop_contract_addr,
-        relayer_private_key: relayer_wallet.clone(),
+        signer: SignerConfig {
+            relayer_private_key: Some(relayer_wallet.clone()),
+            ..SignerConfig::default()
+        },
         tx_mapping_contract_address: tx_mapping_contract_addr,
+        state_path: None,
+        metrics_addr: None,
+        health: HealthConfig::default(),
+        sidechain_tx: SidechainTxConfig::default(),
+        supervisor: SupervisorConfig::default(),
+        dry_run: DryRunConfig::default(),
     };
 
     let deposit_amount = U256::from(500u64);
@@ -104,7 +121,7 @@ async fn recover_incomplete_deposits(#[future] e2e_test_context: E2ETestContext)
     let first_source_tx_hash = tx_receipt.transaction_hash;
 
     let provider = ProviderBuilder::new()
-        .wallet(config.relayer_private_key.clone())
+        .wallet(relayer_wallet.clone())
         .connect_http(config.side_chain_http_url.clone());
     let mapping_contract = TxHashMapping::new(config.tx_mapping_contract_address, provider);
 
@@ -251,7 +268,7 @@ async fn recover_incomplete_deposits(#[future] e2e_test_context: E2ETestContext)
 #[rstest::rstest]
 #[tokio::test]
 #[test_log::test]
-#[ignore = "Requires local Anvil node and 1Money API at http://127.0.0.1:18555"]
+#[ignore = "Requires a local Anvil node"]
 async fn recover_incomplete_withdrawals(#[future] e2e_test_context: E2ETestContext) -> Result<()> {
     let e2e_test_context = e2e_test_context.await;
     let E2ETestContext {
@@ -305,18 +322,29 @@ async fn recover_incomplete_withdrawals(#[future] e2e_test_context: E2ETestConte
     let config = Config {
         one_money_node_url: one_money_node_url.clone(),
         one_money_ws_url: one_money_ws_url.clone(),
+        one_money_ws: OnemoneyWsConfig::default(),
         side_chain_http_url: http_endpoint.clone(),
         side_chain_ws_url: anvil.ws_endpoint_url(),
+        side_chain_confirmations: 0,
         interop_contract_address: interop_contract_addr,
-        relayer_private_key: relayer_wallet.clone(),
+        signer: SignerConfig {
+            relayer_private_key: Some(relayer_wallet.clone()),
+            ..SignerConfig::default()
+        },
         tx_mapping_contract_address: tx_mapping_contract_addr,
+        state_path: None,
+        metrics_addr: None,
+        health: HealthConfig::default(),
+        sidechain_tx: SidechainTxConfig::default(),
+        supervisor: SupervisorConfig::default(),
+        dry_run: DryRunConfig::default(),
     };
 
     let withdrawal_amount = U256::from(500u64);
     let fee_amount = U256::from(1);
 
     let provider = ProviderBuilder::new()
-        .wallet(config.relayer_private_key.clone())
+        .wallet(relayer_wallet.clone())
         .connect_http(config.side_chain_http_url.clone());
     let mapping_contract = TxHashMapping::new(config.tx_mapping_contract_address, provider.clone());
     let relayer_contract = OMInterop::new(interop_contract_addr, provider.clone());
@@ -606,3 +634,217 @@ async fn recover_incomplete_withdrawals(#[future] e2e_test_context: E2ETestConte
     })
     .await
 }
+
+#[rstest::rstest]
+#[tokio::test]
+#[test_log::test]
+#[ignore = "Requires a local Anvil node"]
+async fn recover_unsent_refund(#[future] e2e_test_context: E2ETestContext) -> Result<()> {
+    let e2e_test_context = e2e_test_context.await;
+    let E2ETestContext {
+        anvil,
+        relayer_wallet,
+        interop_contract_addr,
+        onemoney_client,
+        tx_mapping_contract_addr,
+        token_address,
+        ..
+    } = e2e_test_context;
+
+    let keys = anvil.keys();
+    let http_endpoint = anvil.endpoint_url();
+    let recipient = anvil.addresses()[7];
+    let relayer_addr = relayer_wallet.address();
+
+    let sender_wallet: PrivateKeySigner = keys[6].clone().into();
+    let sender = sender_wallet.address();
+
+    let operator_client = OperationClient::new(&onemoney_client, OPERATOR_PRIVATE_KEY);
+
+    operator_client
+        .mint_token(sender, U256::from(10000000), token_address)
+        .await?;
+
+    // TODO: Temporary solution adds tokens to the relayer account until
+    // fees are correctly transferred by 1Money
+    operator_client
+        .mint_token(relayer_addr, U256::from(10000000), token_address)
+        .await?;
+
+    let mut one_money_ws_url = onemoney_client.base_url().clone();
+    one_money_ws_url.set_scheme("ws").map_err(|_| {
+        eyre!(
+            "Failed to set `ws` scheme for 1Money URL `{}`",
+            onemoney_client.base_url()
+        )
+    })?;
+
+    let config = Config {
+        one_money_node_url: onemoney_client.base_url().clone(),
+        one_money_ws_url,
+        one_money_ws: OnemoneyWsConfig::default(),
+        side_chain_http_url: http_endpoint.clone(),
+        side_chain_ws_url: anvil.ws_endpoint_url(),
+        side_chain_confirmations: 0,
+        interop_contract_address: interop_contract_addr,
+        signer: SignerConfig {
+            relayer_private_key: Some(relayer_wallet.clone()),
+            ..SignerConfig::default()
+        },
+        tx_mapping_contract_address: tx_mapping_contract_addr,
+        state_path: None,
+        metrics_addr: None,
+        health: HealthConfig::default(),
+        sidechain_tx: SidechainTxConfig::default(),
+        supervisor: SupervisorConfig::default(),
+        dry_run: DryRunConfig::default(),
+    };
+
+    let provider = ProviderBuilder::new()
+        .wallet(relayer_wallet.clone())
+        .connect_http(config.side_chain_http_url.clone());
+    let mapping_contract = TxHashMapping::new(config.tx_mapping_contract_address, provider.clone());
+    let relayer_contract = OMInterop::new(interop_contract_addr, provider.clone());
+
+    let burn_and_bridge_hash = burn_and_bridge(
+        onemoney_client.base_url().to_string(),
+        sender_wallet.clone(),
+        1,
+        recipient,
+        token_address,
+        U256::from(500u64),
+        U256::from(1),
+    )
+    .await?;
+
+    // Wait for the transaction to be checkpointed
+    tokio::time::sleep(core::time::Duration::from_secs(5)).await;
+
+    let burn_and_bridge_receipt = onemoney_client
+        .get_transaction_receipt_by_hash(&burn_and_bridge_hash.hash.to_string())
+        .await?;
+
+    let burn_and_bridge_tx = onemoney_client
+        .get_transaction_by_hash(&burn_and_bridge_hash.hash.to_string())
+        .await?;
+
+    let TxPayload::TokenBurnAndBridge {
+        value,
+        sender,
+        destination_chain_id,
+        destination_address,
+        escrow_fee,
+        bridge_metadata: _,
+        token,
+    } = burn_and_bridge_tx.data
+    else {
+        return Err(eyre!("Expected TokenBurnAndBridge transaction"));
+    };
+
+    // The bbnonce in the BurnAndBridge receipt is the account's next nonce,
+    // so we subtract 1 to get the current nonce.
+    let bbnonce = burn_and_bridge_receipt
+        .success_info
+        .expect("missing `success_info` from BurnAndBridge receipt")
+        .bridge_info
+        .expect("missing `bridge_info` from BurnAndBridge receipt")
+        .bbnonce
+        - 1;
+
+    let checkpoint_number = burn_and_bridge_receipt
+        .checkpoint_number
+        .expect("missing `checkpoint_number` from BurnAndBridge receipt");
+
+    mapping_contract
+        .registerWithdrawal(burn_and_bridge_hash.hash)
+        .nonce(0)
+        .send()
+        .await?
+        .get_receipt()
+        .await?;
+
+    // Bridge the withdrawal and link it, without sending its refund
+    let bridge_to_receipt = relayer_contract
+        .bridgeTo(
+            sender,
+            bbnonce,
+            destination_address.parse()?,
+            value.parse()?,
+            destination_chain_id.try_into()?,
+            escrow_fee.parse()?,
+            token,
+            checkpoint_number,
+            Bytes::new(),
+            burn_and_bridge_tx.hash,
+        )
+        .nonce(1)
+        .send()
+        .await
+        .map(Ok)
+        .or_else(|e| {
+            e.try_decode_into_interface_error::<OMInterop::OMInteropErrors>()
+                .map(Err)
+        })?
+        .map_err(|e| eyre!("Failed to send bridgeTo: {e:?}"))?
+        .get_receipt()
+        .await?;
+
+    mapping_contract
+        .linkWithdrawalHashes(
+            burn_and_bridge_hash.hash,
+            bridge_to_receipt.transaction_hash,
+        )
+        .nonce(2)
+        .send()
+        .await?
+        .get_receipt()
+        .await?;
+
+    assert!(mapping_contract
+        .incompleteWithdrawals()
+        .call()
+        .await?
+        .is_empty());
+    assert_eq!(
+        mapping_contract.incompleteRefunds().call().await?,
+        vec![burn_and_bridge_hash.hash],
+        "Expected the refund to be the only incomplete hash mapping"
+    );
+
+    let signers = config.signers()?;
+    let onemoney = config.onemoney_api()?;
+    let ctx = RelayerContext {
+        config: config.clone(),
+        tx_manager: config
+            .sidechain_tx_manager(&signers, onemoney.clone())
+            .await?,
+        signer: signers.relayer,
+        store: StateStore::in_memory(),
+        onemoney,
+    };
+
+    recover_incomplete_refunds_hash_mapping(&ctx, None, None).await?;
+
+    assert!(
+        mapping_contract
+            .incompleteRefunds()
+            .call()
+            .await?
+            .is_empty(),
+        "Expected the refund to be issued and linked"
+    );
+    assert_ne!(
+        mapping_contract
+            .getLinkedRefund(burn_and_bridge_hash.hash)
+            .call()
+            .await?,
+        FixedBytes::ZERO
+    );
+    assert_eq!(
+        onemoney_client.get_account_nonce(relayer_addr).await?.nonce,
+        1,
+        "Expected the refund to use the first nonce of the relayer"
+    );
+
+    Ok(())
+}
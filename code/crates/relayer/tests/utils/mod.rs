@@ -2,15 +2,19 @@ use core::future::Future;
 use core::time::Duration;
 
 use relayer::config::Config;
+use relayer::context::RelayerContext;
 use relayer::incoming::recovery::{
     get_latest_incomplete_block_number, recover_incomplete_deposit_hash_mapping,
     relay_incoming_events_from_blocks,
 };
 use relayer::incoming::relay_incoming_events;
 use relayer::outgoing::recovery::{
-    get_earliest_incomplete_checkpoint_number, recover_incomplete_withdrawals_hash_mapping,
+    get_earliest_incomplete_checkpoint_number, recover_incomplete_refunds_hash_mapping,
+    recover_incomplete_withdrawals_hash_mapping,
 };
 use relayer::outgoing::stream::{relay_outgoing_events, relay_outgoing_events_from_checkpoints};
+use relayer::shutdown::Shutdown;
+use relayer::store::StateStore;
 use tracing::{debug, error, info, warn};
 
 pub mod account;
@@ -55,18 +59,26 @@ where
     F: FnOnce() -> Fut,
     Fut: Future<Output = Result<()>>,
 {
-    let relayer_nonce = config.sidechain_relayer_nonce().await?;
+    let signers = config.signers()?;
+    let onemoney = config.onemoney_api()?;
+    let ctx = RelayerContext {
+        config: config.clone(),
+        tx_manager: config
+            .sidechain_tx_manager(&signers, onemoney.clone())
+            .await?,
+        signer: signers.relayer,
+        store: StateStore::in_memory(),
+        onemoney,
+    };
 
     let mut relayer_incoming_task = tokio::spawn({
-        let config = config.clone();
-        let relayer_nonce = relayer_nonce.clone();
+        let ctx = ctx.clone();
         async move {
             // Start Tx Hash Mapping recovery from checkpoint 0
-            recover_incomplete_deposit_hash_mapping(&config, relayer_nonce.clone(), None).await?;
-            let from_block = get_latest_incomplete_block_number(&config).await?;
+            recover_incomplete_deposit_hash_mapping(&ctx, None).await?;
+            let from_block = get_latest_incomplete_block_number(&ctx).await?;
             info!(from_block = %from_block, "Will start incoming relayer task");
-            let relayer_result =
-                relay_incoming_events(&config, relayer_nonce.clone(), from_block).await;
+            let relayer_result = relay_incoming_events(&ctx, from_block, &Shutdown::new()).await;
             if let Err(err) = &relayer_result {
                 warn!(%err, "relayer side-chain event loop ended");
             }
@@ -74,35 +86,22 @@ where
         }
     });
     let clear_task = tokio::spawn({
-        let config_clone = config.clone();
-        let relayer_nonce_clone = relayer_nonce.clone();
+        let ctx = ctx.clone();
         async move {
-            relay_incoming_events_from_blocks(
-                0,
-                &config_clone,
-                relayer_nonce_clone.clone(),
-                Duration::from_secs(10),
-            )
-            .await
+            relay_incoming_events_from_blocks(0, &ctx, Duration::from_secs(10), &Shutdown::new())
+                .await
         }
     });
 
     let mut relayer_outgoing_task = tokio::spawn({
-        let config_clone = config.clone();
-        let relayer_nonce_clone = relayer_nonce.clone();
+        let ctx = ctx.clone();
         async move {
             // Start Tx Hash Mapping recovery from checkpoint 0
-            recover_incomplete_withdrawals_hash_mapping(
-                &config_clone,
-                relayer_nonce_clone.clone(),
-                None,
-                None,
-            )
-            .await?;
-            let start_checkpoint = get_earliest_incomplete_checkpoint_number(&config_clone).await?;
+            recover_incomplete_withdrawals_hash_mapping(&ctx, None).await?;
+            recover_incomplete_refunds_hash_mapping(&ctx, None, None).await?;
+            let start_checkpoint = get_earliest_incomplete_checkpoint_number(&ctx.config).await?;
             info!(start_checkpoint = %start_checkpoint, "Will start outgoing relayer task");
-            let relayer_result =
-                relay_outgoing_events(&config_clone, relayer_nonce_clone.clone()).await;
+            let relayer_result = relay_outgoing_events(&ctx, &Shutdown::new()).await;
             if let Err(err) = &relayer_result {
                 warn!(%err, "relayer 1Money event loop ended");
             }
@@ -110,18 +109,15 @@ where
         }
     });
 
-    let relayer_outgoing_clearing_task = tokio::spawn({
-        let relayer_nonce_clone = relayer_nonce.clone();
-        async move {
-            let start_checkpoint = get_earliest_incomplete_checkpoint_number(&config).await?;
-            relay_outgoing_events_from_checkpoints(
-                &config,
-                relayer_nonce_clone.clone(),
-                start_checkpoint,
-                Duration::from_secs(1),
-            )
-            .await
-        }
+    let relayer_outgoing_clearing_task = tokio::spawn(async move {
+        let start_checkpoint = get_earliest_incomplete_checkpoint_number(&ctx.config).await?;
+        relay_outgoing_events_from_checkpoints(
+            &ctx,
+            start_checkpoint,
+            Duration::from_secs(1),
+            &Shutdown::new(),
+        )
+        .await
     });
 
     let work_future = work();
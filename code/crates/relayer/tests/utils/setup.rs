@@ -6,15 +6,16 @@ use alloy_provider::{Provider, ProviderBuilder};
 use alloy_rpc_types_eth::TransactionRequest;
 use alloy_signer_local::PrivateKeySigner;
 use color_eyre::eyre::Result;
+use onemoney_fake::{FakeConfig, FakeOneMoney};
 use onemoney_interop::contract::{deploy_uups_like, OMInterop, TxHashMapping};
 use onemoney_protocol::{Authority, Client as OnemoneyClient};
 use rstest::fixture;
 
 use super::operator::{OperationClient, OPERATOR_PRIVATE_KEY};
 
-pub const ONE_MONEY_BASE_URL: &str = "http://127.0.0.1:18555";
-
 pub struct E2ETestContext {
+    /// In-process 1Money node serving `onemoney_client`, stopped with the context.
+    pub onemoney: FakeOneMoney,
     pub onemoney_client: OnemoneyClient,
     pub anvil: AnvilInstance,
     pub owner_wallet: PrivateKeySigner,
@@ -35,7 +36,12 @@ pub async fn e2e_test_context() -> E2ETestContext {
 }
 
 async fn setup_e2e_test_context() -> Result<E2ETestContext> {
-    let onemoney_client = OnemoneyClient::custom(ONE_MONEY_BASE_URL.to_string())?;
+    let operator_wallet: PrivateKeySigner = OPERATOR_PRIVATE_KEY.parse()?;
+    let onemoney = FakeOneMoney::spawn(FakeConfig::new(
+        *operator_wallet.credential().verifying_key(),
+    ))
+    .await?;
+    let onemoney_client = OnemoneyClient::custom(onemoney.url().to_string())?;
 
     let anvil = Anvil::new().try_spawn()?;
     let eth_endpoint = anvil.endpoint_url();
@@ -45,7 +51,6 @@ async fn setup_e2e_test_context() -> Result<E2ETestContext> {
     let relayer_wallet: PrivateKeySigner = keys[1].clone().into();
     let sc_token_wallet: PrivateKeySigner = keys[2].clone().into();
     let user_wallet: PrivateKeySigner = keys[3].clone().into();
-    let operator_wallet: PrivateKeySigner = OPERATOR_PRIVATE_KEY.parse()?;
 
     let operator_address = operator_wallet.address();
     let relayer_address = relayer_wallet.address();
@@ -118,6 +123,7 @@ async fn setup_e2e_test_context() -> Result<E2ETestContext> {
         .await?;
 
     Ok(E2ETestContext {
+        onemoney,
         onemoney_client,
         anvil,
         owner_wallet,
@@ -10,8 +10,11 @@ use alloy_sol_types::SolEvent;
 use color_eyre::eyre::{eyre, Result};
 use onemoney_interop::contract::OMInterop::OMInteropSent;
 use onemoney_interop::contract::{OMInterop, TxHashMapping};
-use onemoney_protocol::{Client, PaymentPayload};
-use relayer::config::Config;
+use onemoney_protocol::PaymentPayload;
+use relayer::config::{
+    Config, DryRunConfig, HealthConfig, OnemoneyWsConfig, SidechainTxConfig, SignerConfig,
+    SupervisorConfig,
+};
 use tracing::info;
 use utils::operator::{OperationClient, OPERATOR_PRIVATE_KEY};
 
@@ -20,12 +23,10 @@ use crate::utils::setup::{e2e_test_context, E2ETestContext};
 use crate::utils::spawn_relayer_and;
 use crate::utils::transaction::burn_and_bridge::burn_and_bridge;
 
-const ONE_MONEY_BASE_URL: &str = "http://127.0.0.1:18555";
-
 #[rstest::rstest]
 #[tokio::test]
 #[test_log::test]
-#[ignore = "Requires local Anvil node and 1Money API at http://127.0.0.1:18555"]
+#[ignore = "Requires a local Anvil node"]
 async fn test_withdrawal(#[future] e2e_test_context: E2ETestContext) -> Result<()> {
     let e2e_test_context = e2e_test_context.await;
     let E2ETestContext {
@@ -78,11 +79,22 @@ async fn test_withdrawal(#[future] e2e_test_context: E2ETestContext) -> Result<(
     let config = Config {
         one_money_node_url: one_money_node_url.clone(),
         one_money_ws_url: one_money_ws_url.clone(),
+        one_money_ws: OnemoneyWsConfig::default(),
         side_chain_http_url: http_endpoint.clone(),
         side_chain_ws_url: ws_endpoint.clone(),
+        side_chain_confirmations: 0,
         interop_contract_address: interop_contract_addr,
-        relayer_private_key: relayer_wallet.clone(),
+        signer: SignerConfig {
+            relayer_private_key: Some(relayer_wallet.clone()),
+            ..SignerConfig::default()
+        },
         tx_mapping_contract_address: tx_mapping_contract_addr,
+        state_path: None,
+        metrics_addr: None,
+        health: HealthConfig::default(),
+        sidechain_tx: SidechainTxConfig::default(),
+        supervisor: SupervisorConfig::default(),
+        dry_run: DryRunConfig::default(),
     };
 
     let relayer_provider = ProviderBuilder::new()
@@ -388,10 +400,11 @@ async fn test_withdrawal(#[future] e2e_test_context: E2ETestContext) -> Result<(
 #[rstest::rstest]
 #[tokio::test]
 #[test_log::test]
-#[ignore = "Requires local Anvil node and 1Money API at http://127.0.0.1:18555"]
+#[ignore = "Requires a local Anvil node"]
 async fn test_clear_withdrawal(#[future] e2e_test_context: E2ETestContext) -> Result<()> {
     let e2e_test_context = e2e_test_context.await;
     let E2ETestContext {
+        onemoney_client,
         anvil,
         relayer_wallet,
         sc_token_wallet,
@@ -411,7 +424,6 @@ async fn test_clear_withdrawal(#[future] e2e_test_context: E2ETestContext) -> Re
     let sender = sender_wallet.address();
     let recipient = anvil.addresses()[7];
 
-    let onemoney_client = Client::custom(ONE_MONEY_BASE_URL.to_string())?;
     let operator_client = OperationClient::new(&onemoney_client, OPERATOR_PRIVATE_KEY);
 
     operator_client
@@ -436,11 +448,22 @@ async fn test_clear_withdrawal(#[future] e2e_test_context: E2ETestContext) -> Re
     let config = Config {
         one_money_node_url: one_money_node_url.clone(),
         one_money_ws_url: one_money_ws_url.clone(),
+        one_money_ws: OnemoneyWsConfig::default(),
         side_chain_http_url: http_endpoint.clone(),
         side_chain_ws_url: anvil.ws_endpoint_url(),
+        side_chain_confirmations: 0,
         interop_contract_address: interop_contract_addr,
-        relayer_private_key: relayer_wallet.clone(),
+        signer: SignerConfig {
+            relayer_private_key: Some(relayer_wallet.clone()),
+            ..SignerConfig::default()
+        },
         tx_mapping_contract_address: tx_mapping_contract_addr,
+        state_path: None,
+        metrics_addr: None,
+        health: HealthConfig::default(),
+        sidechain_tx: SidechainTxConfig::default(),
+        supervisor: SupervisorConfig::default(),
+        dry_run: DryRunConfig::default(),
     };
 
     let relayer_provider = ProviderBuilder::new()